@@ -18,7 +18,10 @@ pub use task::*;
 #[cfg(feature = "alloc")]
 pub use task_ext::*;
 
-pub use scheduler::{BaseScheduler, percpu_size_4k_aligned};
+pub use scheduler::{
+    BaseScheduler, CPU_IDLE, CPU_KICKED, CPU_RUNNING, RT_PRIO_LEVELS, SchedClass, SchedPolicy,
+    SchedStats, SchedStatus, percpu_size_4k_aligned,
+};
 
 pub type AxTask = scheduler::BaseTask<TaskInner>;
 pub type TaskRef = scheduler::BaseTaskRef<TaskInner>;
@@ -44,6 +44,9 @@ pub enum TaskState {
 pub struct TaskInner {
     alloc_stack: Option<usize>,
     coroutine_schedule: Option<usize>,
+    /// The size of the stack allocated by `alloc_stack` each time the
+    /// coroutine starts running.
+    coroutine_stack_size: usize,
     id: TaskId,
     is_idle: bool,
     is_init: bool,
@@ -51,6 +54,14 @@ pub struct TaskInner {
     /// Used to indicate whether the task is running on a CPU.
     // #[cfg(feature = "smp")]
     on_cpu: AtomicBool,
+    /// CPU affinity mask, the `i`-th bit is set if the task can run on CPU `i`.
+    ///
+    /// It is kept here rather than in the task extension, so that the vDSO can
+    /// check it when moving the task between run queues.
+    cpumask: AtomicUsize,
+    /// The CPU where the task is put when it becomes ready next time, set by
+    /// migrating the task. It is `NO_HOME_CPU` if not set.
+    home_cpu: AtomicUsize,
     /// Mark whether the task is in the wait queue.
     in_wait_queue: AtomicBool,
     /// A ticket ID used to identify the timer event.
@@ -66,6 +77,14 @@ pub struct TaskInner {
     ctx: UnsafeCell<TaskContext>,
 }
 
+const _: () = assert!(config::SMP <= usize::BITS as usize);
+
+/// The value of `home_cpu` when the task has no home CPU.
+const NO_HOME_CPU: usize = usize::MAX;
+
+/// The cpumask with all CPUs set.
+const FULL_CPUMASK: usize = usize::MAX >> (usize::BITS as usize - config::SMP);
+
 // #[cfg(feature = "alloc")]
 // #[repr(C)]
 // pub struct TaskInner {
@@ -145,6 +164,7 @@ impl TaskInner {
         Self {
             alloc_stack: None,
             coroutine_schedule: None,
+            coroutine_stack_size: config::TASK_STACK_SIZE,
             id: TaskId::new(),
             is_idle: false,
             is_init: false,
@@ -154,6 +174,8 @@ impl TaskInner {
             timer_ticket_id: AtomicU64::new(0),
             // #[cfg(feature = "smp")]
             on_cpu: AtomicBool::new(false),
+            cpumask: AtomicUsize::new(FULL_CPUMASK),
+            home_cpu: AtomicUsize::new(NO_HOME_CPU),
             in_wait_queue: AtomicBool::new(false),
             // #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
@@ -318,6 +340,24 @@ impl TaskInner {
         self.coroutine_schedule = Some(coroutine_schedule);
     }
 
+    /// Setup the size of the stack used by the coroutine, which is passed to
+    /// the TaskStack alloc fn.
+    pub fn set_coroutine_stack_size(&mut self, size: usize) {
+        self.coroutine_stack_size = size;
+    }
+
+    /// Returns the size of the stack used by the coroutine.
+    #[inline]
+    pub const fn coroutine_stack_size(&self) -> usize {
+        self.coroutine_stack_size
+    }
+
+    /// Returns whether the task is a coroutine.
+    #[inline]
+    pub const fn is_coroutine(&self) -> bool {
+        self.coroutine_schedule.is_some()
+    }
+
     /// Returns a mutable reference to the task context.
     #[inline]
     pub const fn ctx_mut(&mut self) -> &mut TaskContext {
@@ -348,9 +388,9 @@ impl TaskInner {
     pub fn set_kstack(&self) {
         let kstack = unsafe { &mut *self.kernel_stack() };
         if kstack.is_none() && !self.is_init && !self.is_idle {
-            let alloc_stack_fn: fn() -> TaskStack =
+            let alloc_stack_fn: fn(usize) -> TaskStack =
                 unsafe { core::mem::transmute(self.alloc_stack.unwrap()) };
-            let stack = alloc_stack_fn();
+            let stack = alloc_stack_fn(self.coroutine_stack_size);
             let kstack_top = stack.top();
             *kstack = Some(stack);
             let ctx = unsafe { &mut *self.ctx_mut_ptr() };
@@ -471,6 +511,17 @@ impl TaskInner {
             .store(timer_ticket_id, Ordering::Release);
     }
 
+    /// Replace the timer ticket ID with `new` if it is still `current`.
+    ///
+    /// Returns `true` if it is replaced, so that only one of the timer event
+    /// and other wakers can claim the task.
+    #[inline]
+    pub fn replace_timer_ticket(&self, current: u64, new: u64) -> bool {
+        self.timer_ticket_id
+            .compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
     /// Expire timer ticket ID by setting it to 0,
     /// it can be used to identify one timer event is triggered or expired.
     #[cfg(feature = "alloc")]
@@ -559,6 +610,42 @@ impl TaskInner {
     pub fn set_on_cpu(&self, on_cpu: bool) {
         self.on_cpu.store(on_cpu, Ordering::Release)
     }
+
+    /// Gets the cpu affinity mask of the task, where the `i`-th bit is set
+    /// if the task can run on CPU `i`.
+    #[inline]
+    pub fn cpumask_bits(&self) -> usize {
+        self.cpumask.load(Ordering::Acquire)
+    }
+
+    /// Sets the cpu affinity mask of the task, where the `i`-th bit is set
+    /// if the task can run on CPU `i`.
+    #[inline]
+    pub fn set_cpumask_bits(&self, cpumask: usize) {
+        self.cpumask.store(cpumask, Ordering::Release)
+    }
+
+    /// Returns whether the task can run on CPU `cpu_id`.
+    #[inline]
+    pub fn can_run_on(&self, cpu_id: usize) -> bool {
+        self.cpumask_bits() & (1 << cpu_id) != 0
+    }
+
+    /// Sets the CPU where the task is put when it becomes ready next time,
+    /// e.g. when it is woken up or switched out.
+    #[inline]
+    pub fn set_home_cpu(&self, cpu_id: usize) {
+        self.home_cpu.store(cpu_id, Ordering::Release)
+    }
+
+    /// Takes the CPU set by `set_home_cpu()`, leaving it unset.
+    #[inline]
+    pub fn take_home_cpu(&self) -> Option<usize> {
+        match self.home_cpu.swap(NO_HOME_CPU, Ordering::AcqRel) {
+            NO_HOME_CPU => None,
+            cpu_id => Some(cpu_id),
+        }
+    }
 }
 
 #[cfg(feature = "alloc")]
@@ -595,6 +682,10 @@ impl TaskStack {
         }
     }
 
+    pub const fn size(&self) -> usize {
+        self.layout.size()
+    }
+
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
@@ -1,22 +1,22 @@
 use crossbeam::atomic::AtomicCell;
 
-use crate::BaseScheduler;
-use core::fmt::Debug;
-use core::ops::Deref;
-use core::ptr::NonNull;
+use crate::{BaseScheduler, BaseTaskRef};
 use core::sync::atomic::{AtomicIsize, Ordering};
 use utils::LockFreeBTreeMap;
-#[cfg(feature = "alloc")]
-use {alloc::sync::Arc, core::mem::ManuallyDrop};
 
-/// task for CFS
-#[repr(C)]
-pub struct CFSTask<T> {
+/// Scheduling information kept in each task for the [`CFScheduler`].
+pub struct CFSTask {
     init_vruntime: AtomicIsize,
     delta: AtomicIsize,
     nice: AtomicIsize,
+    /// The vruntime and the id when the task was put into the ready queue,
+    /// which are its key there.
+    key_vruntime: AtomicIsize,
     id: AtomicIsize,
-    inner: T,
+    /// The weight accounted in the load of the run queue when the task was
+    /// put into it, which is subtracted when it is taken out, even if its
+    /// nice value is changed in between.
+    load_weight: AtomicIsize,
 }
 
 // https://elixir.bootlin.com/linux/latest/source/include/linux/sched/prio.h
@@ -34,19 +34,36 @@ const NICE2WEIGHT_NEG: [isize; NICE_RANGE_NEG + 1] = [
     29154, 36291, 46273, 56483, 71755, 88761,
 ];
 
-impl<T> CFSTask<T> {
+// https://elixir.bootlin.com/linux/latest/source/kernel/sched/fair.c
+
+const WAKEUP_GRANULARITY: isize = 1_000_000; // sysctl_sched_wakeup_granularity in Linux, in nanoseconds
+
+/// Returns `true` if a woken task with the vruntime `woken` should preempt
+/// the current task with the vruntime `current`.
+pub(crate) fn vruntime_preempts(current: isize, woken: isize) -> bool {
+    // Avoid over-scheduling when the vruntimes are close.
+    current - woken > WAKEUP_GRANULARITY
+}
+
+impl CFSTask {
     /// new with default values
-    pub const fn new(inner: T) -> Self {
+    pub const fn new() -> Self {
         Self {
-            inner,
             init_vruntime: AtomicIsize::new(0_isize),
             delta: AtomicIsize::new(0_isize),
             nice: AtomicIsize::new(0_isize),
+            key_vruntime: AtomicIsize::new(0_isize),
             id: AtomicIsize::new(0_isize),
+            load_weight: AtomicIsize::new(0_isize),
         }
     }
 
-    fn get_weight(&self) -> isize {
+    /// Returns the nice value of the task.
+    pub(crate) fn nice(&self) -> isize {
+        self.nice.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn get_weight(&self) -> isize {
         let nice = self.nice.load(Ordering::Acquire);
         if nice >= 0 {
             NICE2WEIGHT_POS[nice as usize]
@@ -55,12 +72,27 @@ impl<T> CFSTask<T> {
         }
     }
 
-    #[allow(unused)]
-    fn get_id(&self) -> isize {
-        self.id.load(Ordering::Acquire)
+    /// Records the current weight as the load of the task in the run queue it
+    /// is put into, and returns it.
+    pub(crate) fn set_load_weight(&self) -> usize {
+        let weight = self.get_weight();
+        self.load_weight.store(weight, Ordering::Release);
+        weight as usize
     }
 
-    fn get_vruntime(&self) -> isize {
+    /// Returns the weight recorded by [`CFSTask::set_load_weight`].
+    pub(crate) fn load_weight(&self) -> usize {
+        self.load_weight.load(Ordering::Acquire) as usize
+    }
+
+    fn key(&self) -> (isize, isize) {
+        (
+            self.key_vruntime.load(Ordering::Acquire),
+            self.id.load(Ordering::Acquire),
+        )
+    }
+
+    pub(crate) fn get_vruntime(&self) -> isize {
         if self.nice.load(Ordering::Acquire) == 0 {
             self.init_vruntime.load(Ordering::Acquire) + self.delta.load(Ordering::Acquire)
         } else {
@@ -69,12 +101,17 @@ impl<T> CFSTask<T> {
         }
     }
 
-    fn set_vruntime(&self, v: isize) {
+    /// Sets the vruntime to `v`, and clears the runtime accumulated since the
+    /// last setting.
+    pub(crate) fn rebase_vruntime(&self, v: isize) {
         self.init_vruntime.store(v, Ordering::Release);
+        self.delta.store(0, Ordering::Release);
     }
 
-    // Simple Implementation: no change in vruntime.
-    // Only modifying priority of current process is supported currently.
+    // Simple Implementation: no change in vruntime, only its growth rate.
+    // A ready task is still found by the key and the weight recorded when it
+    // was put into the ready queue, but it should be removed and put back to
+    // take its new position in the tree and its new weight in the load.
     fn set_priority(&self, nice: isize) {
         let current_init_vruntime = self.get_vruntime();
         self.init_vruntime
@@ -83,90 +120,24 @@ impl<T> CFSTask<T> {
         self.nice.store(nice, Ordering::Release);
     }
 
-    fn set_id(&self, id: isize) {
+    fn set_key(&self, vruntime: isize, id: isize) {
+        self.key_vruntime.store(vruntime, Ordering::Release);
         self.id.store(id, Ordering::Release);
     }
 
-    fn task_tick(&self) {
-        self.delta.fetch_add(1, Ordering::Release);
-    }
-
-    /// Returns a reference to the inner task struct.
-    pub const fn inner(&self) -> &T {
-        &self.inner
-    }
-}
-
-impl<T> Deref for CFSTask<T> {
-    type Target = T;
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
-
-#[repr(transparent)]
-pub struct CFSTaskRef<T> {
-    inner: NonNull<CFSTask<T>>,
-}
-
-impl<T> Clone for CFSTaskRef<T> {
-    fn clone(&self) -> Self {
-        Self::new(self.inner.as_ptr())
-    }
-}
-
-unsafe impl<T> Send for CFSTaskRef<T> {}
-unsafe impl<T> Sync for CFSTaskRef<T> {}
-
-impl<T> CFSTaskRef<T> {
-    pub fn new(inner: *const CFSTask<T>) -> Self {
-        Self {
-            inner: NonNull::new(inner as _).unwrap(),
-        }
-    }
-
-    pub fn ptr_eq(&self, other: &Self) -> bool {
-        self.inner.as_ptr() == other.inner.as_ptr()
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.inner == NonNull::dangling()
-    }
-
-    #[cfg(feature = "alloc")]
-    pub fn into_arc(&self) -> ManuallyDrop<Arc<CFSTask<T>>> {
-        unsafe { ManuallyDrop::new(Arc::from_raw(self.inner.as_ptr() as _)) }
-    }
-}
-
-impl<T> Deref for CFSTaskRef<T> {
-    type Target = CFSTask<T>;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.inner.as_ref() }
-    }
-}
-
-impl<T: Debug> Debug for CFSTask<T> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("CFSTask")
-            .field("inner", self.inner())
-            .finish()
-    }
-}
-
-impl<T: Debug> Debug for CFSTaskRef<T> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("CFSTaskRef")
-            .field("CFSTask", unsafe { self.inner.as_ref() })
-            .finish()
+    fn task_tick(&self, elapsed: usize) {
+        self.delta.fetch_add(elapsed as isize, Ordering::Release);
     }
 }
 
 /// A simple [Completely Fair Scheduler][1] (CFS).
 ///
+/// The vruntime of a task is its runtime in nanoseconds weighted by its nice
+/// value, so it stays fair even if the timer ticks are irregular.
+///
 /// [1]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
 pub struct CFScheduler<T, const CAPACITY: usize> {
-    ready_queue: LockFreeBTreeMap<(isize, isize), CFSTaskRef<T>, CAPACITY>, // (vruntime, taskid)
+    ready_queue: LockFreeBTreeMap<(isize, isize), BaseTaskRef<T>, CAPACITY>, // (vruntime, taskid)
     min_vruntime: AtomicCell<Option<isize>>,
     id_pool: AtomicIsize,
 }
@@ -184,55 +155,108 @@ impl<T, const CAPACITY: usize> CFScheduler<T, CAPACITY> {
     pub fn scheduler_name() -> &'static str {
         "Completely Fair"
     }
+
+    fn base_vruntime(&self) -> isize {
+        self.min_vruntime.load().unwrap_or(0)
+    }
+
+    /// Makes the vruntime of a task taken from this scheduler relative to
+    /// `min_vruntime`, before it is moved to another run queue.
+    pub(crate) fn detach_task(&self, task: &BaseTaskRef<T>) {
+        let vruntime = task.cfs().get_vruntime();
+        task.cfs().rebase_vruntime(vruntime - self.base_vruntime());
+    }
+
+    /// Makes the relative vruntime of a task moved from another run queue
+    /// absolute again, so it keeps its lag against the tasks of this
+    /// scheduler.
+    pub(crate) fn attach_task(&self, task: &BaseTaskRef<T>) {
+        let vruntime = task.cfs().get_vruntime();
+        task.cfs().rebase_vruntime(vruntime + self.base_vruntime());
+    }
 }
 
 impl<T, const CAPACITY: usize> BaseScheduler for CFScheduler<T, CAPACITY> {
-    type SchedItem = CFSTaskRef<T>;
+    type SchedItem = BaseTaskRef<T>;
 
     fn init(&mut self) {}
 
-    fn add_task(&self, task: Self::SchedItem) {
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
         if self.min_vruntime.load().is_none() {
             self.min_vruntime.store(Some(0_isize));
         }
         let vruntime = self.min_vruntime.load().unwrap();
         let taskid = self.id_pool.fetch_add(1, Ordering::Release);
-        task.set_vruntime(vruntime);
-        task.set_id(taskid);
-        self.ready_queue.insert((vruntime, taskid), task);
+        task.cfs().rebase_vruntime(vruntime);
+        task.cfs().set_key(vruntime, taskid);
+        if self
+            .ready_queue
+            .insert((vruntime, taskid), task.clone())
+            .is_none()
+        {
+            return Err(task);
+        }
         if let Some(((min_vruntime, _), _)) = self.ready_queue.first_key_value() {
             self.min_vruntime.store(Some(min_vruntime));
         } else {
             self.min_vruntime.store(None);
         }
+        Ok(())
     }
 
     fn pick_next_task(&self) -> Option<Self::SchedItem> {
-        if let Some((_, v)) = self.ready_queue.pop_first() {
+        if let Some(((vruntime, _), v)) = self.ready_queue.pop_first() {
+            // Advance `min_vruntime` with the picked task, so that it follows the
+            // progress of this run queue, and can be used as the base of the
+            // vruntime of migrated tasks.
+            if self.min_vruntime.load().is_none_or(|min| min < vruntime) {
+                self.min_vruntime.store(Some(vruntime));
+            }
             Some(v)
         } else {
             None
         }
     }
 
-    fn put_prev_task(&self, prev: Self::SchedItem, _preempt: bool) {
+    fn put_prev_task(&self, prev: Self::SchedItem, _preempt: bool) -> Result<(), Self::SchedItem> {
         let taskid = self.id_pool.fetch_add(1, Ordering::Release);
-        prev.set_id(taskid);
-        self.ready_queue.insert((prev.get_vruntime(), taskid), prev);
+        let vruntime = prev.cfs().get_vruntime();
+        prev.cfs().set_key(vruntime, taskid);
+        match self.ready_queue.insert((vruntime, taskid), prev.clone()) {
+            Some(_) => Ok(()),
+            None => Err(prev),
+        }
+    }
+
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        self.ready_queue.remove(&task.cfs().key())
     }
 
-    fn task_tick(&self, current: &Self::SchedItem) -> bool {
-        current.task_tick();
+    fn remove_first_by<F>(&self, mut f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        self.ready_queue
+            .remove_first_by(|_, task| f(task))
+            .map(|(_, task)| task)
+    }
+
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool {
+        current.cfs().task_tick(elapsed);
         if self.ready_queue.is_empty() {
             return false;
         }
         self.min_vruntime.load().is_none()
-            || current.get_vruntime() > self.min_vruntime.load().unwrap()
+            || current.cfs().get_vruntime() > self.min_vruntime.load().unwrap()
+    }
+
+    fn check_preempt_wakeup(&self, current: &Self::SchedItem, woken: &Self::SchedItem) -> bool {
+        vruntime_preempts(current.cfs().get_vruntime(), woken.cfs().get_vruntime())
     }
 
     fn set_priority(&self, task: &Self::SchedItem, prio: isize) -> bool {
         if (-20..=19).contains(&prio) {
-            task.set_priority(prio);
+            task.cfs().set_priority(prio);
             true
         } else {
             false
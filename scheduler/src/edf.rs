@@ -0,0 +1,309 @@
+use crate::{BaseScheduler, BaseTaskRef};
+use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use utils::LockFreeBTreeMap;
+
+/// Fixed-point shift of the bandwidth (`runtime / period`) of a task.
+const BW_SHIFT: usize = 20;
+/// The maximum total bandwidth of a run queue, i.e. 100% of the CPU.
+const MAX_BW: usize = 1 << BW_SHIFT;
+/// The key of tasks without reservation, so they run after all reserved tasks.
+const NO_DEADLINE: usize = usize::MAX;
+
+/// Scheduling information kept in each task for the [`EDFScheduler`].
+///
+/// A task has a reservation of `runtime` nanoseconds in every `period`
+/// nanoseconds, and the reserved runtime should be used up within `deadline`
+/// nanoseconds from the start of each period. A task without reservation (`runtime == 0`) runs
+/// only when no reserved task is ready.
+pub struct EDFTask {
+    runtime: AtomicUsize,
+    deadline: AtomicUsize,
+    period: AtomicUsize,
+    /// Remaining runtime in the current period.
+    budget: AtomicIsize,
+    /// Absolute deadline of the current period.
+    abs_deadline: AtomicUsize,
+    /// The absolute deadline and the id when the task was put into the ready
+    /// queue, which are its key there.
+    key_deadline: AtomicUsize,
+    id: AtomicIsize,
+    deadline_misses: AtomicUsize,
+    /// Absolute deadline of the last period counted as missed, so a period
+    /// is counted at most once.
+    missed_deadline: AtomicUsize,
+}
+
+impl EDFTask {
+    /// Creates a new [`EDFTask`] without reservation.
+    pub const fn new() -> Self {
+        Self {
+            runtime: AtomicUsize::new(0),
+            deadline: AtomicUsize::new(0),
+            period: AtomicUsize::new(0),
+            budget: AtomicIsize::new(0),
+            abs_deadline: AtomicUsize::new(NO_DEADLINE),
+            key_deadline: AtomicUsize::new(NO_DEADLINE),
+            id: AtomicIsize::new(0),
+            deadline_misses: AtomicUsize::new(0),
+            missed_deadline: AtomicUsize::new(NO_DEADLINE),
+        }
+    }
+
+    /// Returns the reservation parameters `(runtime, deadline, period)`.
+    pub fn params(&self) -> (usize, usize, usize) {
+        (
+            self.runtime.load(Ordering::Acquire),
+            self.deadline.load(Ordering::Acquire),
+            self.period.load(Ordering::Acquire),
+        )
+    }
+
+    /// Returns the number of periods in which the task did not get its
+    /// reserved runtime before the deadline.
+    pub fn deadline_misses(&self) -> usize {
+        self.deadline_misses.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn has_reservation(&self) -> bool {
+        self.runtime.load(Ordering::Acquire) > 0
+    }
+
+    fn bandwidth(&self) -> usize {
+        let (runtime, _, period) = self.params();
+        if runtime == 0 {
+            0
+        } else {
+            (runtime << BW_SHIFT) / period
+        }
+    }
+
+    pub(crate) fn abs_deadline(&self) -> usize {
+        self.abs_deadline.load(Ordering::Acquire)
+    }
+
+    /// Starts a new period at `now`, with a full budget.
+    fn replenish(&self, now: usize) {
+        if self.has_reservation() {
+            let (runtime, deadline, _) = self.params();
+            self.budget.store(runtime as isize, Ordering::Release);
+            self.abs_deadline.store(now + deadline, Ordering::Release);
+        } else {
+            self.abs_deadline.store(NO_DEADLINE, Ordering::Release);
+        }
+    }
+
+    /// Counts a deadline miss if the deadline of the current period has passed
+    /// at `now`, while the reserved runtime is not used up.
+    fn check_miss(&self, now: usize) {
+        let abs_deadline = self.abs_deadline();
+        if self.has_reservation()
+            && now >= abs_deadline
+            && self.budget.load(Ordering::Acquire) > 0
+            && self.missed_deadline.swap(abs_deadline, Ordering::AcqRel) != abs_deadline
+        {
+            self.deadline_misses.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    /// Postpones the deadline by one period, and recharges the budget. The
+    /// overrun of the current period is charged to the new one.
+    fn postpone(&self) {
+        let (runtime, _, period) = self.params();
+        self.budget.fetch_add(runtime as isize, Ordering::Release);
+        self.abs_deadline.fetch_add(period, Ordering::Release);
+    }
+}
+
+/// An [Earliest Deadline First][1] scheduler with [Constant Bandwidth Server][2].
+///
+/// The ready task with the earliest absolute deadline is picked first. The
+/// budget of the current task is consumed by the elapsed time at each timer
+/// tick, and when it is used up, the deadline of the task is postponed by one
+/// period with a replenished budget, so a task that overruns cannot affect the guarantees
+/// of other tasks.
+///
+/// Reservations are admitted by [`EDFScheduler::set_params`] only if the
+/// total bandwidth of the run queue does not exceed 100%.
+///
+/// It has no clock of its own. The deadlines are measured by the clock of the
+/// [`Scheduler`] of the run queue, which starts a new period for a task when
+/// it is put into the ready queue, and checks the deadlines of the current
+/// and the ready tasks at each timer tick.
+///
+/// [`Scheduler`]: crate::Scheduler
+///
+/// [1]: https://en.wikipedia.org/wiki/Earliest_deadline_first_scheduling
+/// [2]: https://en.wikipedia.org/wiki/Constant_bandwidth_server
+pub struct EDFScheduler<T, const CAPACITY: usize> {
+    ready_queue: LockFreeBTreeMap<(usize, isize), BaseTaskRef<T>, CAPACITY>, // (abs_deadline, taskid)
+    total_bw: AtomicUsize,
+    id_pool: AtomicIsize,
+}
+
+impl<T, const CAPACITY: usize> EDFScheduler<T, CAPACITY> {
+    /// Creates a new empty [`EDFScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            ready_queue: LockFreeBTreeMap::new(),
+            total_bw: AtomicUsize::new(0),
+            id_pool: AtomicIsize::new(0_isize),
+        }
+    }
+
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Earliest Deadline First"
+    }
+
+    /// Sets the reservation parameters of a task, in nanoseconds, and starts
+    /// its first period at `now`.
+    ///
+    /// The parameters must satisfy `0 < runtime <= deadline <= period`, and
+    /// the reservation is admitted only if the total bandwidth of the run
+    /// queue does not exceed 100% with it. Setting `runtime` to 0 removes the
+    /// reservation and releases its bandwidth.
+    ///
+    /// The task should not be in the ready queue, usually it is the current
+    /// task.
+    ///
+    /// Returns `true` if the parameters are set successfully.
+    pub fn set_params(
+        &self,
+        task: &BaseTaskRef<T>,
+        runtime: usize,
+        deadline: usize,
+        period: usize,
+        now: usize,
+    ) -> bool {
+        let edf = task.edf();
+        let old_bw = edf.bandwidth();
+        let new_bw = if runtime == 0 {
+            0
+        } else if runtime <= deadline && deadline <= period {
+            (runtime << BW_SHIFT) / period
+        } else {
+            return false;
+        };
+        // Admission control.
+        if self
+            .total_bw
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |total| {
+                let total = total - old_bw + new_bw;
+                (total <= MAX_BW).then_some(total)
+            })
+            .is_err()
+        {
+            return false;
+        }
+        edf.runtime.store(runtime, Ordering::Release);
+        edf.deadline.store(deadline, Ordering::Release);
+        edf.period.store(period, Ordering::Release);
+        edf.replenish(now);
+        true
+    }
+
+    /// Updates the deadline of a task at `now`, before it is put into the
+    /// ready queue.
+    ///
+    /// A woken or new task starts a new period. A task put back after running
+    /// starts a new period only if its deadline has passed, so that it cannot
+    /// use the bandwidth of the past periods (the CBS wakeup rule).
+    pub(crate) fn update_enqueued(&self, task: &BaseTaskRef<T>, now: usize, wakeup: bool) {
+        let edf = task.edf();
+        if wakeup {
+            edf.replenish(now);
+        } else if edf.has_reservation() && now >= edf.abs_deadline() {
+            edf.check_miss(now);
+            edf.replenish(now);
+        }
+    }
+
+    /// Checks the deadlines at `now`, at each timer tick before
+    /// [`BaseScheduler::task_tick`].
+    ///
+    /// The current task starts a new period if its deadline has passed. A
+    /// ready task whose deadline has passed keeps its position, and starts a
+    /// new period when it runs. Both are counted as deadline misses if their
+    /// reserved runtime is not used up.
+    pub(crate) fn update_deadlines(&self, current: &BaseTaskRef<T>, now: usize) {
+        let edf = current.edf();
+        if edf.has_reservation() && now >= edf.abs_deadline() {
+            edf.check_miss(now);
+            edf.replenish(now);
+        }
+        self.ready_queue.for_each(|&(deadline, _), task| {
+            if deadline <= now {
+                task.edf().check_miss(now);
+            }
+        });
+    }
+
+    fn enqueue(&self, task: BaseTaskRef<T>) -> Result<(), BaseTaskRef<T>> {
+        let taskid = self.id_pool.fetch_add(1, Ordering::Release);
+        let deadline = task.edf().abs_deadline();
+        task.edf().key_deadline.store(deadline, Ordering::Release);
+        task.edf().id.store(taskid, Ordering::Release);
+        match self.ready_queue.insert((deadline, taskid), task.clone()) {
+            Some(_) => Ok(()),
+            None => Err(task),
+        }
+    }
+}
+
+impl<T, const CAPACITY: usize> BaseScheduler for EDFScheduler<T, CAPACITY> {
+    type SchedItem = BaseTaskRef<T>;
+
+    fn init(&mut self) {}
+
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
+        self.enqueue(task)
+    }
+
+    fn pick_next_task(&self) -> Option<Self::SchedItem> {
+        self.ready_queue.pop_first().map(|(_, task)| task)
+    }
+
+    fn put_prev_task(&self, prev: Self::SchedItem, _preempt: bool) -> Result<(), Self::SchedItem> {
+        self.enqueue(prev)
+    }
+
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        let edf = task.edf();
+        self.ready_queue.remove(&(
+            edf.key_deadline.load(Ordering::Acquire),
+            edf.id.load(Ordering::Acquire),
+        ))
+    }
+
+    fn remove_first_by<F>(&self, mut f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        self.ready_queue
+            .remove_first_by(|_, task| f(task))
+            .map(|(_, task)| task)
+    }
+
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool {
+        let edf = current.edf();
+        if edf.has_reservation() {
+            edf.budget.fetch_sub(elapsed as isize, Ordering::AcqRel);
+            // A long tick may overrun the budget of several periods.
+            while edf.has_reservation() && edf.budget.load(Ordering::Acquire) <= 0 {
+                edf.postpone();
+            }
+        }
+        match self.ready_queue.first_key_value() {
+            Some(((deadline, _), _)) => deadline < edf.abs_deadline(),
+            None => false,
+        }
+    }
+
+    fn check_preempt_wakeup(&self, current: &Self::SchedItem, woken: &Self::SchedItem) -> bool {
+        woken.edf().abs_deadline() < current.edf().abs_deadline()
+    }
+
+    fn set_priority(&self, _task: &Self::SchedItem, _prio: isize) -> bool {
+        false
+    }
+}
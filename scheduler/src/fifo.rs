@@ -1,93 +1,5 @@
-use crate::BaseScheduler;
-use core::fmt::Debug;
-use core::ops::Deref;
-use core::ptr::NonNull;
-use heapless::mpmc::MpMcQueue;
-#[cfg(feature = "alloc")]
-use {alloc::sync::Arc, core::mem::ManuallyDrop};
-
-#[repr(C)]
-pub struct FifoTask<T> {
-    inner: T,
-}
-
-impl<T> FifoTask<T> {
-    /// Creates a new [`RRTask`] from the inner task struct.
-    pub const fn new(inner: T) -> Self {
-        Self { inner }
-    }
-
-    /// Returns a reference to the inner task struct.
-    pub const fn inner(&self) -> &T {
-        &self.inner
-    }
-}
-
-impl<T> Deref for FifoTask<T> {
-    type Target = T;
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
-
-#[repr(transparent)]
-pub struct FiFoTaskRef<T> {
-    inner: NonNull<FifoTask<T>>,
-}
-
-impl<T> Clone for FiFoTaskRef<T> {
-    fn clone(&self) -> Self {
-        Self::new(self.inner.as_ptr())
-    }
-}
-
-unsafe impl<T> Send for FiFoTaskRef<T> {}
-unsafe impl<T> Sync for FiFoTaskRef<T> {}
-
-impl<T> FiFoTaskRef<T> {
-    pub fn new(inner: *const FifoTask<T>) -> Self {
-        Self {
-            inner: NonNull::new(inner as _).unwrap(),
-        }
-    }
-
-    pub fn ptr_eq(&self, other: &Self) -> bool {
-        self.inner.as_ptr() == other.inner.as_ptr()
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.inner == NonNull::dangling()
-    }
-
-    #[cfg(feature = "alloc")]
-    pub fn into_arc(&self) -> ManuallyDrop<Arc<FifoTask<T>>> {
-        unsafe { ManuallyDrop::new(Arc::from_raw(self.inner.as_ptr() as _)) }
-    }
-}
-
-impl<T> Deref for FiFoTaskRef<T> {
-    type Target = FifoTask<T>;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.inner.as_ref() }
-    }
-}
-
-impl<T: Debug> Debug for FifoTask<T> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("FiFoTask")
-            .field("inner", self.inner())
-            .finish()
-    }
-}
-
-impl<T: Debug> Debug for FiFoTaskRef<T> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("FiFoTaskRef")
-            .field("FifoTask", unsafe { self.inner.as_ref() })
-            .finish()
-    }
-}
+use crate::{BaseScheduler, BaseTaskRef};
+use utils::LockFreeDeque;
 
 /// A simple FIFO (First-In-First-Out) cooperative scheduler.
 ///
@@ -97,16 +9,16 @@ impl<T: Debug> Debug for FiFoTaskRef<T> {
 ///
 /// As it's a cooperative scheduler, it does nothing when the timer tick occurs.
 ///
-/// It internally uses a linked list as the ready queue.
+/// It internally uses a bounded lock-free deque as the ready queue.
 pub struct FifoScheduler<T, const CAPACITY: usize> {
-    ready_queue: MpMcQueue<FiFoTaskRef<T>, CAPACITY>,
+    ready_queue: LockFreeDeque<BaseTaskRef<T>, CAPACITY>,
 }
 
 impl<T, const CAPACITY: usize> FifoScheduler<T, CAPACITY> {
     /// Creates a new empty [`FifoScheduler`].
     pub const fn new() -> Self {
         Self {
-            ready_queue: MpMcQueue::new(),
+            ready_queue: LockFreeDeque::new(),
         }
     }
     /// get the name of scheduler
@@ -116,26 +28,41 @@ impl<T, const CAPACITY: usize> FifoScheduler<T, CAPACITY> {
 }
 
 impl<T, const CAPACITY: usize> BaseScheduler for FifoScheduler<T, CAPACITY> {
-    type SchedItem = FiFoTaskRef<T>;
+    type SchedItem = BaseTaskRef<T>;
 
     fn init(&mut self) {}
 
-    fn add_task(&self, task: Self::SchedItem) {
-        let _ = self.ready_queue.enqueue(task);
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
+        self.ready_queue.push_back(task)
     }
 
     fn pick_next_task(&self) -> Option<Self::SchedItem> {
-        self.ready_queue.dequeue()
+        self.ready_queue.pop_front()
+    }
+
+    fn put_prev_task(&self, prev: Self::SchedItem, _preempt: bool) -> Result<(), Self::SchedItem> {
+        self.ready_queue.push_back(prev)
     }
 
-    fn put_prev_task(&self, prev: Self::SchedItem, _preempt: bool) {
-        let _ = self.ready_queue.enqueue(prev);
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        self.ready_queue.remove_by(|t| t.ptr_eq(task))
     }
 
-    fn task_tick(&self, _current: &Self::SchedItem) -> bool {
+    fn remove_first_by<F>(&self, f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        self.ready_queue.remove_by(f)
+    }
+
+    fn task_tick(&self, _current: &Self::SchedItem, _elapsed: usize) -> bool {
         false // no reschedule
     }
 
+    fn check_preempt_wakeup(&self, _current: &Self::SchedItem, _woken: &Self::SchedItem) -> bool {
+        false
+    }
+
     fn set_priority(&self, _task: &Self::SchedItem, _prio: isize) -> bool {
         false
     }
@@ -5,37 +5,64 @@
 //! - [`FifoScheduler`]: FIFO (First-In-First-Out) scheduler (cooperative).
 //! - [`RRScheduler`]: Round-robin scheduler (preemptive).
 //! - [`CFScheduler`]: Completely Fair Scheduler (preemptive).
+//! - [`EDFScheduler`]: Earliest Deadline First scheduler with Constant
+//!   Bandwidth Server (preemptive).
+//! - [`RTScheduler`]: Real-time scheduler with fixed priorities (preemptive).
+//!
+//! The policy is chosen for each run queue at runtime through [`SchedPolicy`],
+//! and all policies share the same task wrapper [`BaseTask`]. Real-time tasks
+//! (see [`SchedClass`]) are layered above the policy of the run queue.
 
 #![cfg_attr(not(test), no_std)]
 #![feature(unsafe_cell_access)]
 
-use config::RQ_CAP;
-
 #[cfg(any(test, feature = "alloc"))]
 extern crate alloc;
 
 mod percpu;
 pub use percpu::*;
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "sched-rr")] {
-        mod round_robin;
-        const MAX_TIME_SLICE: usize = 5;
-        pub type BaseTask<T> = round_robin::RRTask<T, MAX_TIME_SLICE>;
-        pub type BaseTaskRef<T> = round_robin::RRTaskRef<T, MAX_TIME_SLICE>;
-        pub type Scheduler<T> = round_robin::RRScheduler<T, MAX_TIME_SLICE, RQ_CAP>;
-    } else if #[cfg(feature = "sched-cfs")] {
-        mod cfs;
-        pub type BaseTask<T> = cfs::CFSTask<T>;
-        pub type BaseTaskRef<T> = cfs::CFSTaskRef<T>;
-        pub type Scheduler<T> = cfs::CFScheduler<T, RQ_CAP>;
-    } else {
-        mod fifo;
-        // If no scheduler features are set, use FIFO as the default.
-        pub type BaseTask<T> = fifo::FifoTask<T>;
-        pub type BaseTaskRef<T> = fifo::FiFoTaskRef<T>;
-        pub type Scheduler<T> = fifo::FifoScheduler<T, RQ_CAP>;
-    }
+mod cfs;
+mod edf;
+mod fifo;
+mod policy;
+mod round_robin;
+mod rt;
+mod stats;
+mod task;
+#[cfg(test)]
+mod tests;
+pub use cfs::{CFSTask, CFScheduler};
+pub use edf::{EDFScheduler, EDFTask};
+pub use fifo::FifoScheduler;
+pub use policy::*;
+pub use round_robin::{RRScheduler, RRTask};
+pub use rt::{RTScheduler, RTTask, SchedClass, RT_PRIO_LEVELS};
+pub use stats::{SchedStats, TaskStats};
+pub use task::*;
+
+/// The default time slice of the tasks in the [`RRScheduler`] and the
+/// [`RtRoundRobin`](SchedClass::RtRoundRobin) tasks, in nanoseconds.
+///
+/// It can be changed for each run queue by [`Scheduler::set_time_slice`].
+pub const DEFAULT_TIME_SLICE: usize = 5_000_000;
+
+/// The status code of the vDSO operations that put a task into a run queue.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SchedStatus {
+    /// The operation is done.
+    Ok = 0,
+    /// The operation is done, and the current task is switched out.
+    Resched = 1,
+    /// The ready queue of the run queue is full, so the task is not put into it.
+    RunQueueFull = 2,
+    /// The operation is refused, e.g. a cpumask allowing no CPU, or moving a
+    /// task with a deadline reservation to another run queue.
+    NotAllowed = 3,
+    /// The operation is done, and the current task of the destination CPU
+    /// should be preempted, so the CPU needs to be notified.
+    RemoteResched = 4,
 }
 
 /// The base scheduler trait that all schedulers should implement.
@@ -50,7 +77,9 @@ pub trait BaseScheduler {
     fn init(&mut self);
 
     /// Adds a task to the scheduler.
-    fn add_task(&self, task: Self::SchedItem);
+    ///
+    /// Returns [`Err`] with the task if the ready queue is full.
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem>;
 
     /// Picks the next task to run, it will be removed from the scheduler.
     /// Returns [`None`] if there is not runnable task.
@@ -63,13 +92,38 @@ pub trait BaseScheduler {
     /// `preempt` indicates whether the previous task is preempted by the next
     /// task. In this case, the previous task may be placed at the front of the
     /// ready queue.
-    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool);
+    ///
+    /// Returns [`Err`] with the task if the ready queue is full.
+    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool) -> Result<(), Self::SchedItem>;
+
+    /// Removes a certain task from the ready queue, wherever it is.
+    ///
+    /// Returns the removed task, or [`None`] if the task is not in the ready
+    /// queue (e.g. it is running, blocked, or already picked by others).
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem>;
+
+    /// Removes the first task in the picking order that satisfies `f`, e.g.
+    /// to move it to another run queue. Unlike picking the tasks and putting
+    /// them back, other tasks keep their positions in the ready queue.
+    ///
+    /// Returns [`None`] if no task in the ready queue satisfies `f`.
+    fn remove_first_by<F>(&self, f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool;
 
     /// Advances the scheduler state at each timer tick. Returns `true` if
     /// re-scheduling is required.
     ///
-    /// `current` is the current running task.
-    fn task_tick(&self, current: &Self::SchedItem) -> bool;
+    /// `current` is the current running task, and `elapsed` is the time in
+    /// nanoseconds since the last tick, so the ticks need not be regular.
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool;
+
+    /// Checks whether the `woken` task, which has just been put into the ready
+    /// queue, should preempt the `current` running task.
+    ///
+    /// Like `check_preempt_wakeup` in Linux, it is called when a task is woken
+    /// up, instead of waiting for the next timer tick to re-schedule.
+    fn check_preempt_wakeup(&self, current: &Self::SchedItem, woken: &Self::SchedItem) -> bool;
 
     /// set priority for a task
     fn set_priority(&self, task: &Self::SchedItem, prio: isize) -> bool;
@@ -1,8 +1,9 @@
-use crate::{BaseTaskRef, Scheduler};
+use crate::{BaseTaskRef, SchedPolicy, SchedStats, Scheduler};
 use config::PAGES_SIZE_4K;
 use core::{
     cell::UnsafeCell,
     mem::{size_of, MaybeUninit},
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
 };
 
 pub const fn percpu_size_4k_aligned<T>() -> usize {
@@ -10,26 +11,94 @@ pub const fn percpu_size_4k_aligned<T>() -> usize {
     (size_of::<PerCPU<T>>() + PAGES_SIZE_4K - 1) & MASK
 }
 
+/// The value of [`PerCPU::idle_state`] when the CPU is running tasks.
+pub const CPU_RUNNING: u32 = 0;
+/// The value of [`PerCPU::idle_state`] when the CPU has no task to run, and
+/// is going to sleep or sleeping.
+pub const CPU_IDLE: u32 = 1;
+/// The value of [`PerCPU::idle_state`] when a task is put into the run queue
+/// of the idle CPU, so the CPU should be woken up.
+pub const CPU_KICKED: u32 = 2;
+
 #[repr(C, align(4096))] // 此处的align应保持与PAGES_SIZE_4K相同（因为注解中无法传入常量）
 pub struct PerCPU<T> {
     /// The ID of the CPU this run queue is associated with.
     pub cpu_id: usize,
     pub current_task: UnsafeCell<BaseTaskRef<T>>,
+    /// The address of `current_task`, published whenever it is switched, so
+    /// that other CPUs can check the task running on this CPU without reading
+    /// `current_task`. See [`PerCPU::is_current`].
+    pub current_addr: AtomicUsize,
     pub idle_task: BaseTaskRef<T>,
     /// Stores the weak reference to the previous task that is running on this CPU.
     pub prev_task: UnsafeCell<MaybeUninit<BaseTaskRef<T>>>,
-    /// The core scheduler of this run queue.
+    /// The task being switched out of this CPU that should be put into the run
+    /// queue of another CPU, with the ID of that CPU. It is only put there
+    /// after this CPU finishes switching out of it, so other CPUs never pick a
+    /// task that is still running on this CPU.
+    pub deferred_task: UnsafeCell<Option<(BaseTaskRef<T>, usize)>>,
+    /// The core scheduler of this run queue, its policy is chosen in [`PerCPU::new`].
     pub scheduler: Scheduler<T>,
+    /// Time in nanoseconds since the last load balancing on this CPU.
+    pub balance_time: AtomicUsize,
+    /// The mailbox of reschedule requests sent by other CPUs, set when a task
+    /// woken onto this CPU should preempt the current task.
+    pub resched_pending: AtomicBool,
+    /// Whether this CPU is idle ([`CPU_RUNNING`], [`CPU_IDLE`] or
+    /// [`CPU_KICKED`]), it is also the word that the idle CPU sleeps on, e.g.
+    /// with a futex.
+    pub idle_state: AtomicU32,
 }
 
 impl<T> PerCPU<T> {
-    pub fn new(cpu_id: usize, idle_task: BaseTaskRef<T>, boot_task: BaseTaskRef<T>) -> Self {
+    pub fn new(
+        cpu_id: usize,
+        idle_task: BaseTaskRef<T>,
+        boot_task: BaseTaskRef<T>,
+        policy: SchedPolicy,
+    ) -> Self {
+        let scheduler = Scheduler::new(policy);
+        scheduler.set_current(&boot_task, false);
         Self {
             cpu_id,
             current_task: UnsafeCell::new(boot_task.clone()),
+            current_addr: AtomicUsize::new(boot_task.addr()),
             idle_task: idle_task,
             prev_task: UnsafeCell::new(MaybeUninit::new(boot_task)),
-            scheduler: Scheduler::new(),
+            deferred_task: UnsafeCell::new(None),
+            scheduler,
+            balance_time: AtomicUsize::new(0),
+            resched_pending: AtomicBool::new(false),
+            idle_state: AtomicU32::new(CPU_RUNNING),
+        }
+    }
+
+    /// Returns `true` if `task` is running on this CPU.
+    ///
+    /// It can be called on any CPU, but the task may be switched out at once
+    /// if it is a remote CPU.
+    pub fn is_current(&self, task: &BaseTaskRef<T>) -> bool {
+        self.current_addr.load(Ordering::Acquire) == task.addr()
+    }
+
+    /// Returns the scheduling statistics of this CPU, which are the totals of
+    /// the tasks that ran or waited on it, excluding the idle task.
+    ///
+    /// The totals and the statistics of the idle task are read at different
+    /// times, so the differences are saturated at zero.
+    pub fn stats(&self) -> SchedStats {
+        let total = self.scheduler.stats();
+        let idle = self.idle_task.stats().get();
+        SchedStats {
+            runtime: total.runtime.saturating_sub(idle.runtime),
+            wait_time: total.wait_time.saturating_sub(idle.wait_time),
+            voluntary_switches: total
+                .voluntary_switches
+                .saturating_sub(idle.voluntary_switches),
+            involuntary_switches: total
+                .involuntary_switches
+                .saturating_sub(idle.involuntary_switches),
+            wakeups: total.wakeups.saturating_sub(idle.wakeups),
         }
     }
 }
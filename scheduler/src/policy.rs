@@ -0,0 +1,524 @@
+use crate::{
+    cfs::vruntime_preempts, stats::AtomicSchedStats, BaseScheduler, BaseTaskRef, CFScheduler,
+    EDFScheduler, FifoScheduler, RRScheduler, RTScheduler, SchedClass, SchedStats,
+};
+use config::RQ_CAP;
+use core::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+/// The scheduling policy of a run queue.
+///
+/// It is chosen for each CPU when its run queue is initialized, so different
+/// CPUs can run different policies with the same vDSO image.
+#[repr(usize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SchedPolicy {
+    /// [`FifoScheduler`]
+    Fifo = 0,
+    /// [`RRScheduler`]
+    RoundRobin = 1,
+    /// [`CFScheduler`]
+    Cfs = 2,
+    /// [`EDFScheduler`]
+    Edf = 3,
+}
+
+/// The scheduler for tasks in the [`Normal`](SchedClass::Normal) class,
+/// which dispatches to the policy chosen at initialization.
+pub enum PolicyScheduler<T> {
+    Fifo(FifoScheduler<T, RQ_CAP>),
+    RoundRobin(RRScheduler<T, RQ_CAP>),
+    Cfs(CFScheduler<T, RQ_CAP>),
+    Edf(EDFScheduler<T, RQ_CAP>),
+}
+
+macro_rules! dispatch {
+    ($self: expr, $sched: ident => $call: expr) => {
+        match $self {
+            PolicyScheduler::Fifo($sched) => $call,
+            PolicyScheduler::RoundRobin($sched) => $call,
+            PolicyScheduler::Cfs($sched) => $call,
+            PolicyScheduler::Edf($sched) => $call,
+        }
+    };
+}
+
+impl<T> PolicyScheduler<T> {
+    /// Creates a new empty scheduler with the given policy.
+    pub const fn new(policy: SchedPolicy) -> Self {
+        match policy {
+            SchedPolicy::Fifo => Self::Fifo(FifoScheduler::new()),
+            SchedPolicy::RoundRobin => Self::RoundRobin(RRScheduler::new()),
+            SchedPolicy::Cfs => Self::Cfs(CFScheduler::new()),
+            SchedPolicy::Edf => Self::Edf(EDFScheduler::new()),
+        }
+    }
+
+    /// Returns the policy of this scheduler.
+    pub const fn policy(&self) -> SchedPolicy {
+        match self {
+            Self::Fifo(_) => SchedPolicy::Fifo,
+            Self::RoundRobin(_) => SchedPolicy::RoundRobin,
+            Self::Cfs(_) => SchedPolicy::Cfs,
+            Self::Edf(_) => SchedPolicy::Edf,
+        }
+    }
+
+    /// get the name of scheduler
+    pub fn scheduler_name(&self) -> &'static str {
+        match self {
+            Self::Fifo(_) => FifoScheduler::<T, RQ_CAP>::scheduler_name(),
+            Self::RoundRobin(_) => RRScheduler::<T, RQ_CAP>::scheduler_name(),
+            Self::Cfs(_) => CFScheduler::<T, RQ_CAP>::scheduler_name(),
+            Self::Edf(_) => EDFScheduler::<T, RQ_CAP>::scheduler_name(),
+        }
+    }
+
+    fn detach_task(&self, task: &BaseTaskRef<T>) {
+        match self {
+            Self::Cfs(cfs) => cfs.detach_task(task),
+            // The vruntime is not maintained by other policies, so the task
+            // starts at `min_vruntime` if it is moved to a CFS run queue.
+            _ => task.cfs().rebase_vruntime(0),
+        }
+    }
+
+    fn attach_task(&self, task: &BaseTaskRef<T>) {
+        if let Self::Cfs(cfs) = self {
+            cfs.attach_task(task)
+        }
+    }
+
+    fn update_enqueued(&self, task: &BaseTaskRef<T>, now: usize, wakeup: bool) {
+        if let Self::Edf(edf) = self {
+            edf.update_enqueued(task, now, wakeup)
+        }
+    }
+
+    fn update_deadlines(&self, current: &BaseTaskRef<T>, now: usize) {
+        if let Self::Edf(edf) = self {
+            edf.update_deadlines(current, now)
+        }
+    }
+}
+
+impl<T> BaseScheduler for PolicyScheduler<T> {
+    type SchedItem = BaseTaskRef<T>;
+
+    fn init(&mut self) {
+        dispatch!(self, s => s.init())
+    }
+
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
+        dispatch!(self, s => s.add_task(task))
+    }
+
+    fn pick_next_task(&self) -> Option<Self::SchedItem> {
+        dispatch!(self, s => s.pick_next_task())
+    }
+
+    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool) -> Result<(), Self::SchedItem> {
+        dispatch!(self, s => s.put_prev_task(prev, preempt))
+    }
+
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        dispatch!(self, s => s.remove_task(task))
+    }
+
+    fn remove_first_by<F>(&self, f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        dispatch!(self, s => s.remove_first_by(f))
+    }
+
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool {
+        dispatch!(self, s => s.task_tick(current, elapsed))
+    }
+
+    fn check_preempt_wakeup(&self, current: &Self::SchedItem, woken: &Self::SchedItem) -> bool {
+        dispatch!(self, s => s.check_preempt_wakeup(current, woken))
+    }
+
+    fn set_priority(&self, task: &Self::SchedItem, prio: isize) -> bool {
+        dispatch!(self, s => s.set_priority(task, prio))
+    }
+}
+
+/// The parameters of the current task of a run queue which decide whether a
+/// woken task should preempt it, published by [`Scheduler::set_current`].
+///
+/// Other CPUs check the preemption with them instead of the current task,
+/// which may be switched out and freed at any time.
+struct CurrentParams {
+    idle: AtomicBool,
+    /// The real-time priority, or [`CurrentParams::NOT_RT`] for a normal task.
+    rt_prio: AtomicIsize,
+    vruntime: AtomicIsize,
+    abs_deadline: AtomicUsize,
+}
+
+impl CurrentParams {
+    const NOT_RT: isize = isize::MIN;
+
+    const fn new() -> Self {
+        Self {
+            idle: AtomicBool::new(true),
+            rt_prio: AtomicIsize::new(Self::NOT_RT),
+            vruntime: AtomicIsize::new(0),
+            abs_deadline: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// The scheduler of a run queue.
+///
+/// Real-time tasks are scheduled by [`RTScheduler`], and are always picked
+/// before the tasks in the [`Normal`](SchedClass::Normal) class, which are
+/// scheduled by the policy of the run queue.
+///
+/// The scheduler also keeps the load of its ready tasks for load balancing,
+/// which is the sum of their weights. The weight of a task is determined by
+/// its nice value (see [`CFScheduler`]), so it is the same for all tasks in
+/// run queues with other policies.
+///
+/// The parameters of the current task are published in the scheduler by
+/// [`Scheduler::set_current`], so that other CPUs can check whether a task
+/// woken onto this run queue should preempt it.
+///
+/// It also keeps the scheduling statistics of the tasks (see [`TaskStats`])
+/// and the totals of them, in nanoseconds of its clock. The clock is the
+/// monotonic time set by [`Scheduler::update_clock`], which should have the
+/// same base on all CPUs, so the statistics of a task stay consistent when it
+/// moves between run queues.
+///
+/// [`TaskStats`]: crate::TaskStats
+pub struct Scheduler<T> {
+    rt: RTScheduler<T, RQ_CAP>,
+    normal: PolicyScheduler<T>,
+    load: AtomicUsize,
+    clock: AtomicUsize,
+    stats: AtomicSchedStats,
+    current: CurrentParams,
+}
+
+impl<T> Scheduler<T> {
+    /// Creates a new empty scheduler, whose normal tasks use the given policy.
+    pub const fn new(policy: SchedPolicy) -> Self {
+        Self {
+            rt: RTScheduler::new(),
+            normal: PolicyScheduler::new(policy),
+            load: AtomicUsize::new(0),
+            clock: AtomicUsize::new(0),
+            stats: AtomicSchedStats::new(),
+            current: CurrentParams::new(),
+        }
+    }
+
+    /// Returns the load of the ready tasks in this scheduler.
+    pub fn load(&self) -> usize {
+        self.load.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if there is no ready task in this scheduler.
+    ///
+    /// The load is updated before a task is put into the ready queue, so a
+    /// task being added may be counted before it can be picked.
+    pub fn is_empty(&self) -> bool {
+        self.load() == 0
+    }
+
+    /// Returns the time of the clock of this scheduler, in nanoseconds.
+    pub fn clock(&self) -> usize {
+        self.clock.load(Ordering::Acquire)
+    }
+
+    /// Advances the clock of this scheduler to `now`, the monotonic time in
+    /// nanoseconds.
+    ///
+    /// The clock never goes backwards, so any CPU can update it, e.g. with its
+    /// own clock before putting a task into this run queue.
+    pub fn update_clock(&self, now: usize) {
+        self.clock.fetch_max(now, Ordering::AcqRel);
+    }
+
+    /// Returns the time slice of the round-robin tasks, in nanoseconds.
+    pub fn time_slice(&self) -> usize {
+        self.rt.time_slice()
+    }
+
+    /// Sets the time slice of the tasks in the [`RRScheduler`] and the
+    /// [`RtRoundRobin`](SchedClass::RtRoundRobin) tasks, in nanoseconds.
+    ///
+    /// Returns `false` if `time_slice` is 0.
+    pub fn set_time_slice(&self, time_slice: usize) -> bool {
+        if time_slice == 0 {
+            return false;
+        }
+        self.rt.set_time_slice(time_slice);
+        if let PolicyScheduler::RoundRobin(rr) = &self.normal {
+            rr.set_time_slice(time_slice);
+        }
+        true
+    }
+
+    /// Returns the totals of the scheduling statistics of the tasks on this
+    /// run queue.
+    pub fn stats(&self) -> SchedStats {
+        self.stats.load()
+    }
+
+    /// Accounts a switch from `prev` to `next` on this run queue.
+    ///
+    /// `voluntary` indicates whether `prev` is switched out because of
+    /// blocking or exiting.
+    pub fn account_switch(&self, prev: &BaseTaskRef<T>, next: &BaseTaskRef<T>, voluntary: bool) {
+        let now = self.clock();
+        self.stats.add_runtime(prev.stats().account_runtime(now));
+        prev.stats().stats().add_switch(voluntary);
+        self.stats.add_switch(voluntary);
+        next.stats().start_running(now);
+    }
+
+    /// Publishes the parameters of the current task of this run queue, which
+    /// are checked by [`Scheduler::check_preempt_current`].
+    ///
+    /// It should be called when a task is switched in, and when the
+    /// parameters of the current task change, e.g. at a timer tick.
+    pub fn set_current(&self, current: &BaseTaskRef<T>, idle: bool) {
+        let rt_prio = if current.rt().is_rt() {
+            current.rt().prio()
+        } else {
+            CurrentParams::NOT_RT
+        };
+        self.current.rt_prio.store(rt_prio, Ordering::Release);
+        self.current
+            .vruntime
+            .store(current.cfs().get_vruntime(), Ordering::Release);
+        self.current
+            .abs_deadline
+            .store(current.edf().abs_deadline(), Ordering::Release);
+        self.current.idle.store(idle, Ordering::Release);
+    }
+
+    /// Returns `true` if the woken task should preempt the current task of
+    /// this run queue, as [`BaseScheduler::check_preempt_wakeup`] does, with
+    /// the parameters published by [`Scheduler::set_current`].
+    ///
+    /// Unlike [`BaseScheduler::check_preempt_wakeup`], it can be called by
+    /// any CPU, since it never references the current task.
+    pub fn check_preempt_current(&self, woken: &BaseTaskRef<T>) -> bool {
+        if self.current.idle.load(Ordering::Acquire) {
+            return true;
+        }
+        let rt_prio = self.current.rt_prio.load(Ordering::Acquire);
+        match (rt_prio != CurrentParams::NOT_RT, woken.rt().is_rt()) {
+            (true, true) => woken.rt().prio() > rt_prio,
+            (true, false) => false,
+            (false, true) => true,
+            (false, false) => match &self.normal {
+                PolicyScheduler::Fifo(_) | PolicyScheduler::RoundRobin(_) => false,
+                PolicyScheduler::Cfs(_) => vruntime_preempts(
+                    self.current.vruntime.load(Ordering::Acquire),
+                    woken.cfs().get_vruntime(),
+                ),
+                PolicyScheduler::Edf(_) => {
+                    woken.edf().abs_deadline() < self.current.abs_deadline.load(Ordering::Acquire)
+                }
+            },
+        }
+    }
+
+    /// Accounts a wakeup of a task which is put into this run queue.
+    pub fn account_wakeup(&self, task: &BaseTaskRef<T>) {
+        task.stats().stats().add_wakeup();
+        self.stats.add_wakeup();
+    }
+
+    /// Returns the policy of the normal tasks in this scheduler.
+    pub const fn policy(&self) -> SchedPolicy {
+        self.normal.policy()
+    }
+
+    /// get the name of scheduler
+    pub fn scheduler_name(&self) -> &'static str {
+        self.normal.scheduler_name()
+    }
+
+    /// Sets the scheduling class and the real-time priority of a task.
+    ///
+    /// The task should not be in the ready queue, usually it is the current
+    /// task. It will be put into the queue of its new class when it is put
+    /// back to the scheduler.
+    ///
+    /// Returns `false` if `prio` is out of range for a real-time class.
+    pub fn set_sched_class(&self, task: &BaseTaskRef<T>, class: SchedClass, prio: isize) -> bool {
+        task.rt().set_class(class, prio)
+    }
+
+    /// Sets the reservation parameters of a task, in nanoseconds.
+    ///
+    /// See [`EDFScheduler::set_params`] for the requirements of the
+    /// parameters and the admission control. Setting `runtime` to 0 removes
+    /// the reservation.
+    ///
+    /// Returns `false` if the policy of the run queue is not
+    /// [`SchedPolicy::Edf`], or the reservation is not admitted.
+    pub fn set_deadline_params(
+        &self,
+        task: &BaseTaskRef<T>,
+        runtime: usize,
+        deadline: usize,
+        period: usize,
+    ) -> bool {
+        match &self.normal {
+            PolicyScheduler::Edf(edf) => {
+                edf.set_params(task, runtime, deadline, period, self.clock())
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the task can be moved to another run queue.
+    ///
+    /// A task with a deadline reservation stays in the run queue which
+    /// admitted it, since the reservation is only guaranteed there.
+    pub fn can_migrate(&self, task: &BaseTaskRef<T>) -> bool {
+        !task.edf().has_reservation()
+    }
+
+    /// Prepares a task taken from this scheduler to be moved to another run
+    /// queue, where [`Scheduler::attach_task`] should be called on it.
+    ///
+    /// The task should not be in the ready queue.
+    pub fn detach_task(&self, task: &BaseTaskRef<T>) {
+        if !task.rt().is_rt() {
+            self.normal.detach_task(task)
+        }
+    }
+
+    /// Prepares a task moved from another run queue to be put into this
+    /// scheduler or run on this CPU. The CFS vruntime of the task is preserved
+    /// relative to the `min_vruntime` of the run queues.
+    ///
+    /// The task should not be in the ready queue.
+    pub fn attach_task(&self, task: &BaseTaskRef<T>) {
+        if !task.rt().is_rt() {
+            self.normal.attach_task(task)
+        }
+    }
+
+    /// Accounts the load and starts accounting the wait time of a task before
+    /// it is put into the ready queue, since it may be taken by other CPUs at
+    /// once, and the load must not drop below zero then.
+    fn enqueued(&self, task: &BaseTaskRef<T>, weight: usize) {
+        task.stats().start_waiting(self.clock());
+        self.load.fetch_add(weight, Ordering::AcqRel);
+    }
+
+    /// Reverts the load accounted by [`Scheduler::enqueued`] if the ready
+    /// queue is full.
+    fn enqueue_failed(&self, weight: usize) {
+        self.load.fetch_sub(weight, Ordering::AcqRel);
+    }
+
+    /// Accounts the load and the wait time of a task taken from the ready queue.
+    fn dequeued(&self, task: &BaseTaskRef<T>) {
+        self.load
+            .fetch_sub(task.cfs().load_weight(), Ordering::AcqRel);
+        self.stats
+            .add_wait_time(task.stats().account_wait_time(self.clock()));
+    }
+}
+
+impl<T> BaseScheduler for Scheduler<T> {
+    type SchedItem = BaseTaskRef<T>;
+
+    fn init(&mut self) {
+        self.rt.init();
+        self.normal.init();
+    }
+
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
+        let weight = task.cfs().set_load_weight();
+        self.enqueued(&task, weight);
+        if task.rt().is_rt() {
+            self.rt.add_task(task)
+        } else {
+            self.normal.update_enqueued(&task, self.clock(), true);
+            self.normal.add_task(task)
+        }
+        .inspect_err(|_| self.enqueue_failed(weight))
+    }
+
+    fn pick_next_task(&self) -> Option<Self::SchedItem> {
+        let task = self
+            .rt
+            .pick_next_task()
+            .or_else(|| self.normal.pick_next_task())?;
+        self.dequeued(&task);
+        Some(task)
+    }
+
+    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool) -> Result<(), Self::SchedItem> {
+        let weight = prev.cfs().set_load_weight();
+        self.enqueued(&prev, weight);
+        if prev.rt().is_rt() {
+            self.rt.put_prev_task(prev, preempt)
+        } else {
+            self.normal.update_enqueued(&prev, self.clock(), false);
+            self.normal.put_prev_task(prev, preempt)
+        }
+        .inspect_err(|_| self.enqueue_failed(weight))
+    }
+
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        let task = if task.rt().is_rt() {
+            self.rt.remove_task(task)?
+        } else {
+            self.normal.remove_task(task)?
+        };
+        self.dequeued(&task);
+        Some(task)
+    }
+
+    fn remove_first_by<F>(&self, mut f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        let task = self
+            .rt
+            .remove_first_by(&mut f)
+            .or_else(|| self.normal.remove_first_by(f))?;
+        self.dequeued(&task);
+        Some(task)
+    }
+
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool {
+        let now = self.clock();
+        self.stats.add_runtime(current.stats().account_runtime(now));
+        self.normal.update_deadlines(current, now);
+        if current.rt().is_rt() {
+            self.rt.task_tick(current, elapsed)
+        } else {
+            // A runnable real-time task always preempts a normal task.
+            self.normal.task_tick(current, elapsed) || !self.rt.is_empty()
+        }
+    }
+
+    fn check_preempt_wakeup(&self, current: &Self::SchedItem, woken: &Self::SchedItem) -> bool {
+        match (current.rt().is_rt(), woken.rt().is_rt()) {
+            (true, true) => self.rt.check_preempt_wakeup(current, woken),
+            (true, false) => false,
+            (false, true) => true,
+            (false, false) => self.normal.check_preempt_wakeup(current, woken),
+        }
+    }
+
+    fn set_priority(&self, task: &Self::SchedItem, prio: isize) -> bool {
+        if task.rt().is_rt() {
+            self.rt.set_priority(task, prio)
+        } else {
+            self.normal.set_priority(task, prio)
+        }
+    }
+}
@@ -1,27 +1,22 @@
-use crate::BaseScheduler;
-use core::fmt::Debug;
-use core::ops::Deref;
-use core::ptr::NonNull;
-use core::sync::atomic::{AtomicIsize, Ordering};
+use crate::{BaseScheduler, BaseTaskRef, DEFAULT_TIME_SLICE};
+use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
 use utils::LockFreeDeque;
-#[cfg(feature = "alloc")]
-use {alloc::sync::Arc, core::mem::ManuallyDrop};
 
-/// A task wrapper for the [`RRScheduler`].
+/// Scheduling information kept in each task for the [`RRScheduler`].
 ///
-/// It add a time slice counter to use in round-robin scheduling.
-#[repr(C)]
-pub struct RRTask<T, const MAX_TIME_SLICE: usize> {
+/// It add a time slice counter to use in round-robin scheduling, in
+/// nanoseconds.
+pub struct RRTask {
     time_slice: AtomicIsize,
-    inner: T,
 }
 
-impl<T, const S: usize> RRTask<T, S> {
-    /// Creates a new [`RRTask`] from the inner task struct.
-    pub const fn new(inner: T) -> Self {
+impl RRTask {
+    /// Creates a new [`RRTask`] with an empty time slice.
+    ///
+    /// The time slice is filled when the task is added to a [`RRScheduler`].
+    pub const fn new() -> Self {
         Self {
-            inner,
-            time_slice: AtomicIsize::new(S as isize),
+            time_slice: AtomicIsize::new(0),
         }
     }
 
@@ -29,136 +24,104 @@ impl<T, const S: usize> RRTask<T, S> {
         self.time_slice.load(Ordering::Acquire)
     }
 
-    fn reset_time_slice(&self) {
-        self.time_slice.store(S as isize, Ordering::Release);
-    }
-
-    /// Returns a reference to the inner task struct.
-    pub const fn inner(&self) -> &T {
-        &self.inner
-    }
-}
-
-impl<T, const S: usize> Deref for RRTask<T, S> {
-    type Target = T;
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
-}
-
-#[repr(transparent)]
-pub struct RRTaskRef<T, const S: usize> {
-    inner: NonNull<RRTask<T, S>>,
-}
-
-impl<T, const S: usize> Clone for RRTaskRef<T, S> {
-    fn clone(&self) -> Self {
-        Self::new(self.inner.as_ptr())
-    }
-}
-
-unsafe impl<T, const S: usize> Send for RRTaskRef<T, S> {}
-unsafe impl<T, const S: usize> Sync for RRTaskRef<T, S> {}
-
-impl<T, const S: usize> RRTaskRef<T, S> {
-    pub fn new(inner: *const RRTask<T, S>) -> Self {
-        Self {
-            inner: NonNull::new(inner as _).unwrap(),
-        }
-    }
-
-    pub fn ptr_eq(&self, other: &Self) -> bool {
-        self.inner.as_ptr() == other.inner.as_ptr()
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.inner == NonNull::dangling()
-    }
-
-    #[cfg(feature = "alloc")]
-    pub fn into_arc(&self) -> ManuallyDrop<Arc<RRTask<T, S>>> {
-        unsafe { ManuallyDrop::new(Arc::from_raw(self.inner.as_ptr() as _)) }
-    }
-}
-
-impl<T, const S: usize> Deref for RRTaskRef<T, S> {
-    type Target = RRTask<T, S>;
-    fn deref(&self) -> &Self::Target {
-        unsafe { self.inner.as_ref() }
-    }
-}
-
-impl<T: Debug, const S: usize> Debug for RRTask<T, S> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("RRTask")
-            .field("inner", self.inner())
-            .finish()
-    }
-}
-
-impl<T: Debug, const S: usize> Debug for RRTaskRef<T, S> {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("RRTaskRef")
-            .field("RRTask", unsafe { self.inner.as_ref() })
-            .finish()
+    fn reset_time_slice(&self, time_slice: usize) {
+        self.time_slice
+            .store(time_slice as isize, Ordering::Release);
     }
 }
 
 /// A simple [Round-Robin] (RR) preemptive scheduler.
 ///
 /// It's very similar to the [`FifoScheduler`], but every task has a time slice
-/// counter that is decremented each time a timer tick occurs. When the current
-/// task's time slice counter reaches zero, the task is preempted and needs to
-/// be rescheduled.
+/// counter that is decremented by the elapsed time each time a timer tick
+/// occurs. When the current task's time slice counter reaches zero, the task
+/// is preempted and needs to be rescheduled.
+///
+/// The time slice is [`DEFAULT_TIME_SLICE`] by default, and can be changed by
+/// [`RRScheduler::set_time_slice`].
 ///
 /// Unlike [`FifoScheduler`], it uses [`VecDeque`] as the ready queue. So it may
 /// take O(n) time to remove a task from the ready queue.
 ///
 /// [Round-Robin]: https://en.wikipedia.org/wiki/Round-robin_scheduling
 /// [`FifoScheduler`]: crate::FifoScheduler
-pub struct RRScheduler<T, const MAX_TIME_SLICE: usize, const CAPACITY: usize> {
-    ready_queue: LockFreeDeque<RRTaskRef<T, MAX_TIME_SLICE>, CAPACITY>,
+pub struct RRScheduler<T, const CAPACITY: usize> {
+    ready_queue: LockFreeDeque<BaseTaskRef<T>, CAPACITY>,
+    time_slice: AtomicUsize,
 }
 
-impl<T, const S: usize, const CAPACITY: usize> RRScheduler<T, S, CAPACITY> {
+impl<T, const CAPACITY: usize> RRScheduler<T, CAPACITY> {
     /// Creates a new empty [`RRScheduler`].
     pub const fn new() -> Self {
         Self {
             ready_queue: LockFreeDeque::new(),
+            time_slice: AtomicUsize::new(DEFAULT_TIME_SLICE),
         }
     }
     /// get the name of scheduler
     pub fn scheduler_name() -> &'static str {
         "Round-robin"
     }
+
+    /// Returns the time slice of the tasks, in nanoseconds.
+    pub fn time_slice(&self) -> usize {
+        self.time_slice.load(Ordering::Acquire)
+    }
+
+    /// Sets the time slice of the tasks, in nanoseconds.
+    ///
+    /// It takes effect when a task gets a new time slice, i.e. when it is
+    /// added or its current time slice is used up.
+    pub fn set_time_slice(&self, time_slice: usize) {
+        self.time_slice.store(time_slice, Ordering::Release);
+    }
 }
 
-impl<T, const S: usize, const CAPACITY: usize> BaseScheduler for RRScheduler<T, S, CAPACITY> {
-    type SchedItem = RRTaskRef<T, S>;
+impl<T, const CAPACITY: usize> BaseScheduler for RRScheduler<T, CAPACITY> {
+    type SchedItem = BaseTaskRef<T>;
 
     fn init(&mut self) {}
 
-    fn add_task(&self, task: Self::SchedItem) {
-        let _ = self.ready_queue.push_back(task);
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
+        task.rr().reset_time_slice(self.time_slice());
+        self.ready_queue.push_back(task)
     }
 
     fn pick_next_task(&self) -> Option<Self::SchedItem> {
         self.ready_queue.pop_front()
     }
 
-    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool) {
-        if prev.time_slice() > 0 && preempt {
-            let _ = self.ready_queue.push_front(prev);
+    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool) -> Result<(), Self::SchedItem> {
+        if prev.rr().time_slice() > 0 && preempt {
+            self.ready_queue.push_front(prev)
         } else {
-            prev.reset_time_slice();
-            let _ = self.ready_queue.push_back(prev);
+            prev.rr().reset_time_slice(self.time_slice());
+            self.ready_queue.push_back(prev)
         }
     }
 
-    fn task_tick(&self, current: &Self::SchedItem) -> bool {
-        let old_slice = current.time_slice.fetch_sub(1, Ordering::Release);
-        old_slice <= 1
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        self.ready_queue.remove_by(|t| t.ptr_eq(task))
+    }
+
+    fn remove_first_by<F>(&self, f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        self.ready_queue.remove_by(f)
+    }
+
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool {
+        let elapsed = elapsed as isize;
+        let old_slice = current
+            .rr()
+            .time_slice
+            .fetch_sub(elapsed, Ordering::Release);
+        old_slice <= elapsed
+    }
+
+    fn check_preempt_wakeup(&self, _current: &Self::SchedItem, _woken: &Self::SchedItem) -> bool {
+        false // wait for the time slice to run out
     }
 
     fn set_priority(&self, _task: &Self::SchedItem, _prio: isize) -> bool {
@@ -0,0 +1,254 @@
+use crate::{BaseScheduler, BaseTaskRef, DEFAULT_TIME_SLICE};
+use core::sync::atomic::{AtomicIsize, AtomicU8, AtomicUsize, Ordering};
+use utils::LockFreeBTreeMap;
+
+/// Number of real-time priority levels. The range of real-time priority is
+/// `[0, RT_PRIO_LEVELS)`, and a larger value means a higher priority.
+pub const RT_PRIO_LEVELS: usize = 100;
+
+/// The scheduling class of a task.
+///
+/// Tasks in the real-time classes are always picked before the tasks in the
+/// [`Normal`](SchedClass::Normal) class, which are scheduled by the policy of
+/// the run queue (see [`SchedPolicy`](crate::SchedPolicy)).
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SchedClass {
+    /// Scheduled by the policy of the run queue.
+    Normal = 0,
+    /// Real-time, first-in-first-out within the same priority (like `SCHED_FIFO`).
+    RtFifo = 1,
+    /// Real-time, round-robin within the same priority (like `SCHED_RR`).
+    RtRoundRobin = 2,
+}
+
+impl From<u8> for SchedClass {
+    #[inline]
+    fn from(class: u8) -> Self {
+        match class {
+            0 => Self::Normal,
+            1 => Self::RtFifo,
+            2 => Self::RtRoundRobin,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Scheduling information kept in each task for the [`RTScheduler`].
+pub struct RTTask {
+    class: AtomicU8,
+    prio: AtomicIsize,
+    time_slice: AtomicIsize,
+    /// The priority and the sequence number when the task was put into the
+    /// ready queue, which are its key there. The priority may be changed
+    /// while the task is ready, so it is not used to find the task.
+    key_prio: AtomicIsize,
+    seq: AtomicIsize,
+}
+
+impl RTTask {
+    /// Creates a new [`RTTask`] in the [`Normal`](SchedClass::Normal) class.
+    pub const fn new() -> Self {
+        Self {
+            class: AtomicU8::new(SchedClass::Normal as u8),
+            prio: AtomicIsize::new(0),
+            time_slice: AtomicIsize::new(0),
+            key_prio: AtomicIsize::new(0),
+            seq: AtomicIsize::new(0),
+        }
+    }
+
+    /// Returns the scheduling class of the task.
+    pub fn class(&self) -> SchedClass {
+        self.class.load(Ordering::Acquire).into()
+    }
+
+    /// Returns `true` if the task is in a real-time class.
+    pub fn is_rt(&self) -> bool {
+        self.class() != SchedClass::Normal
+    }
+
+    /// Returns the real-time priority of the task.
+    pub fn prio(&self) -> isize {
+        self.prio.load(Ordering::Acquire)
+    }
+
+    /// Sets the scheduling class and the real-time priority of the task.
+    ///
+    /// Returns `false` if `prio` is out of range for a real-time class.
+    /// `prio` is ignored for the [`Normal`](SchedClass::Normal) class.
+    ///
+    /// The task should not be in any ready queue, since the queue it belongs
+    /// to may be changed.
+    pub(crate) fn set_class(&self, class: SchedClass, prio: isize) -> bool {
+        if class != SchedClass::Normal {
+            if !(0..RT_PRIO_LEVELS as isize).contains(&prio) {
+                return false;
+            }
+            self.prio.store(prio, Ordering::Release);
+        }
+        self.class.store(class as u8, Ordering::Release);
+        true
+    }
+
+    fn set_prio(&self, prio: isize) -> bool {
+        if (0..RT_PRIO_LEVELS as isize).contains(&prio) {
+            self.prio.store(prio, Ordering::Release);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn time_slice(&self) -> isize {
+        self.time_slice.load(Ordering::Acquire)
+    }
+
+    fn reset_time_slice(&self, time_slice: usize) {
+        self.time_slice
+            .store(time_slice as isize, Ordering::Release);
+    }
+}
+
+/// A real-time scheduler with [`RT_PRIO_LEVELS`] fixed priority levels.
+///
+/// The task with the highest priority is always picked first. Tasks with the
+/// same priority are picked in FIFO order. A task in the
+/// [`RtRoundRobin`](SchedClass::RtRoundRobin) class additionally has a time
+/// slice, and goes to the end of its priority level when the slice is used up,
+/// while a task in the [`RtFifo`](SchedClass::RtFifo) class runs until it
+/// yields or blocks. The time slice is [`DEFAULT_TIME_SLICE`] by default, and
+/// can be changed by [`RTScheduler::set_time_slice`].
+///
+/// It is not used as the policy of a run queue alone, but layered above it
+/// in [`Scheduler`](crate::Scheduler).
+pub struct RTScheduler<T, const CAPACITY: usize> {
+    ready_queue: LockFreeBTreeMap<(isize, isize), BaseTaskRef<T>, CAPACITY>, // (-prio, seq)
+    tail_seq: AtomicIsize,
+    head_seq: AtomicIsize,
+    time_slice: AtomicUsize,
+}
+
+impl<T, const CAPACITY: usize> RTScheduler<T, CAPACITY> {
+    /// Creates a new empty [`RTScheduler`].
+    pub const fn new() -> Self {
+        Self {
+            ready_queue: LockFreeBTreeMap::new(),
+            tail_seq: AtomicIsize::new(0),
+            head_seq: AtomicIsize::new(-1),
+            time_slice: AtomicUsize::new(DEFAULT_TIME_SLICE),
+        }
+    }
+
+    /// get the name of scheduler
+    pub fn scheduler_name() -> &'static str {
+        "Real-time"
+    }
+
+    /// Returns `true` if there is no runnable real-time task.
+    pub fn is_empty(&self) -> bool {
+        self.ready_queue.is_empty()
+    }
+
+    /// Returns the time slice of the [`RtRoundRobin`](SchedClass::RtRoundRobin)
+    /// tasks, in nanoseconds.
+    pub fn time_slice(&self) -> usize {
+        self.time_slice.load(Ordering::Acquire)
+    }
+
+    /// Sets the time slice of the [`RtRoundRobin`](SchedClass::RtRoundRobin)
+    /// tasks, in nanoseconds.
+    ///
+    /// It takes effect when a task gets a new time slice, i.e. when it is
+    /// added or its current time slice is used up.
+    pub fn set_time_slice(&self, time_slice: usize) {
+        self.time_slice.store(time_slice, Ordering::Release);
+    }
+
+    fn push_back(&self, task: BaseTaskRef<T>) -> Result<(), BaseTaskRef<T>> {
+        let seq = self.tail_seq.fetch_add(1, Ordering::Release);
+        self.insert(seq, task)
+    }
+
+    fn push_front(&self, task: BaseTaskRef<T>) -> Result<(), BaseTaskRef<T>> {
+        let seq = self.head_seq.fetch_sub(1, Ordering::Release);
+        self.insert(seq, task)
+    }
+
+    fn insert(&self, seq: isize, task: BaseTaskRef<T>) -> Result<(), BaseTaskRef<T>> {
+        let prio = task.rt().prio();
+        task.rt().key_prio.store(prio, Ordering::Release);
+        task.rt().seq.store(seq, Ordering::Release);
+        match self.ready_queue.insert((-prio, seq), task.clone()) {
+            Some(_) => Ok(()),
+            None => Err(task),
+        }
+    }
+}
+
+impl<T, const CAPACITY: usize> BaseScheduler for RTScheduler<T, CAPACITY> {
+    type SchedItem = BaseTaskRef<T>;
+
+    fn init(&mut self) {}
+
+    fn add_task(&self, task: Self::SchedItem) -> Result<(), Self::SchedItem> {
+        task.rt().reset_time_slice(self.time_slice());
+        self.push_back(task)
+    }
+
+    fn pick_next_task(&self) -> Option<Self::SchedItem> {
+        if self.ready_queue.is_empty() {
+            return None;
+        }
+        self.ready_queue.pop_first().map(|(_, task)| task)
+    }
+
+    fn put_prev_task(&self, prev: Self::SchedItem, preempt: bool) -> Result<(), Self::SchedItem> {
+        // A preempted task keeps its position at the head of its priority level.
+        if preempt && (prev.rt().class() == SchedClass::RtFifo || prev.rt().time_slice() > 0) {
+            self.push_front(prev)
+        } else {
+            prev.rt().reset_time_slice(self.time_slice());
+            self.push_back(prev)
+        }
+    }
+
+    fn remove_task(&self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        let rt = task.rt();
+        self.ready_queue.remove(&(
+            -rt.key_prio.load(Ordering::Acquire),
+            rt.seq.load(Ordering::Acquire),
+        ))
+    }
+
+    fn remove_first_by<F>(&self, mut f: F) -> Option<Self::SchedItem>
+    where
+        F: FnMut(&Self::SchedItem) -> bool,
+    {
+        self.ready_queue
+            .remove_first_by(|_, task| f(task))
+            .map(|(_, task)| task)
+    }
+
+    fn task_tick(&self, current: &Self::SchedItem, elapsed: usize) -> bool {
+        match current.rt().class() {
+            SchedClass::RtRoundRobin => {
+                let elapsed = elapsed as isize;
+                let old_slice = current
+                    .rt()
+                    .time_slice
+                    .fetch_sub(elapsed, Ordering::Release);
+                old_slice <= elapsed
+            }
+            _ => false,
+        }
+    }
+
+    fn check_preempt_wakeup(&self, current: &Self::SchedItem, woken: &Self::SchedItem) -> bool {
+        woken.rt().prio() > current.rt().prio()
+    }
+
+    fn set_priority(&self, task: &Self::SchedItem, prio: isize) -> bool {
+        task.rt().set_prio(prio)
+    }
+}
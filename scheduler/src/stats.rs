@@ -0,0 +1,131 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Scheduling statistics of a task or a CPU.
+///
+/// Times are measured in nanoseconds of the clocks of the run queues (see
+/// [`Scheduler::update_clock`](crate::Scheduler::update_clock)).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct SchedStats {
+    /// Time spent running on CPUs.
+    pub runtime: usize,
+    /// Time spent waiting in ready queues.
+    pub wait_time: usize,
+    /// Number of switches out of the CPU because of blocking or exiting.
+    pub voluntary_switches: usize,
+    /// Number of switches out of the CPU while still runnable, i.e. preempted
+    /// or yielded.
+    pub involuntary_switches: usize,
+    /// Number of wakeups from the blocked state.
+    pub wakeups: usize,
+}
+
+/// [`SchedStats`] which can be updated concurrently.
+pub(crate) struct AtomicSchedStats {
+    runtime: AtomicUsize,
+    wait_time: AtomicUsize,
+    voluntary_switches: AtomicUsize,
+    involuntary_switches: AtomicUsize,
+    wakeups: AtomicUsize,
+}
+
+impl AtomicSchedStats {
+    pub(crate) const fn new() -> Self {
+        Self {
+            runtime: AtomicUsize::new(0),
+            wait_time: AtomicUsize::new(0),
+            voluntary_switches: AtomicUsize::new(0),
+            involuntary_switches: AtomicUsize::new(0),
+            wakeups: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn load(&self) -> SchedStats {
+        SchedStats {
+            runtime: self.runtime.load(Ordering::Acquire),
+            wait_time: self.wait_time.load(Ordering::Acquire),
+            voluntary_switches: self.voluntary_switches.load(Ordering::Acquire),
+            involuntary_switches: self.involuntary_switches.load(Ordering::Acquire),
+            wakeups: self.wakeups.load(Ordering::Acquire),
+        }
+    }
+
+    pub(crate) fn add_runtime(&self, delta: usize) {
+        self.runtime.fetch_add(delta, Ordering::AcqRel);
+    }
+
+    pub(crate) fn add_wait_time(&self, delta: usize) {
+        self.wait_time.fetch_add(delta, Ordering::AcqRel);
+    }
+
+    pub(crate) fn add_switch(&self, voluntary: bool) {
+        if voluntary {
+            self.voluntary_switches.fetch_add(1, Ordering::AcqRel);
+        } else {
+            self.involuntary_switches.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+
+    pub(crate) fn add_wakeup(&self) {
+        self.wakeups.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// Scheduling statistics kept in each task.
+///
+/// They are updated by the [`Scheduler`](crate::Scheduler) of the run queue
+/// where the task is, when the task is put into or taken from the ready
+/// queue, switched in or out, and at each timer tick while it is running.
+pub struct TaskStats {
+    stats: AtomicSchedStats,
+    /// The time when the task starts running, or is last accounted.
+    run_start: AtomicUsize,
+    /// The time when the task is put into the ready queue.
+    ready_start: AtomicUsize,
+}
+
+impl TaskStats {
+    /// Creates a new [`TaskStats`] with all statistics cleared.
+    pub const fn new() -> Self {
+        Self {
+            stats: AtomicSchedStats::new(),
+            run_start: AtomicUsize::new(0),
+            ready_start: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a snapshot of the statistics.
+    pub fn get(&self) -> SchedStats {
+        self.stats.load()
+    }
+
+    pub(crate) fn stats(&self) -> &AtomicSchedStats {
+        &self.stats
+    }
+
+    /// Starts running at `now`.
+    pub(crate) fn start_running(&self, now: usize) {
+        self.run_start.store(now, Ordering::Release);
+    }
+
+    /// Starts waiting in the ready queue at `now`.
+    pub(crate) fn start_waiting(&self, now: usize) {
+        self.ready_start.store(now, Ordering::Release);
+    }
+
+    /// Accounts the time since the task starts running or is last accounted,
+    /// and returns it.
+    pub(crate) fn account_runtime(&self, now: usize) -> usize {
+        let delta = now.saturating_sub(self.run_start.swap(now, Ordering::AcqRel));
+        self.stats.add_runtime(delta);
+        delta
+    }
+
+    /// Accounts the time since the task is put into the ready queue, and
+    /// returns it.
+    pub(crate) fn account_wait_time(&self, now: usize) -> usize {
+        let delta = now.saturating_sub(self.ready_start.load(Ordering::Acquire));
+        self.stats.add_wait_time(delta);
+        delta
+    }
+}
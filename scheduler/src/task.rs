@@ -0,0 +1,154 @@
+use crate::{cfs::CFSTask, edf::EDFTask, round_robin::RRTask, rt::RTTask, stats::TaskStats};
+use core::fmt::Debug;
+use core::ops::Deref;
+use core::ptr::NonNull;
+#[cfg(feature = "alloc")]
+use {alloc::sync::Arc, core::mem::ManuallyDrop};
+
+/// A task wrapper shared by all schedulers.
+///
+/// Since the scheduling policy is chosen per run queue at runtime, a task
+/// carries the scheduling information of every policy, so it can be put into
+/// any run queue.
+#[repr(C)]
+pub struct BaseTask<T> {
+    rt: RTTask,
+    rr: RRTask,
+    cfs: CFSTask,
+    edf: EDFTask,
+    stats: TaskStats,
+    inner: T,
+}
+
+impl<T> BaseTask<T> {
+    /// Creates a new [`BaseTask`] from the inner task struct.
+    pub const fn new(inner: T) -> Self {
+        Self {
+            rt: RTTask::new(),
+            rr: RRTask::new(),
+            cfs: CFSTask::new(),
+            edf: EDFTask::new(),
+            stats: TaskStats::new(),
+            inner,
+        }
+    }
+
+    /// Returns a reference to the inner task struct.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the priority of the task, which is the real-time priority for
+    /// a real-time task, and the nice value otherwise.
+    pub fn priority(&self) -> isize {
+        if self.rt.is_rt() {
+            self.rt.prio()
+        } else {
+            self.cfs.nice()
+        }
+    }
+
+    /// Returns the real-time scheduling information of the task.
+    pub const fn rt(&self) -> &RTTask {
+        &self.rt
+    }
+
+    /// Returns the scheduling information used by the [`RRScheduler`].
+    ///
+    /// [`RRScheduler`]: crate::RRScheduler
+    pub(crate) const fn rr(&self) -> &RRTask {
+        &self.rr
+    }
+
+    /// Returns the scheduling information used by the [`CFScheduler`].
+    ///
+    /// [`CFScheduler`]: crate::CFScheduler
+    pub(crate) const fn cfs(&self) -> &CFSTask {
+        &self.cfs
+    }
+
+    /// Returns the scheduling information used by the [`EDFScheduler`],
+    /// e.g. the reservation parameters and the number of deadline misses.
+    ///
+    /// [`EDFScheduler`]: crate::EDFScheduler
+    pub const fn edf(&self) -> &EDFTask {
+        &self.edf
+    }
+
+    /// Returns the scheduling statistics of the task, e.g. the runtime and the
+    /// number of switches.
+    pub const fn stats(&self) -> &TaskStats {
+        &self.stats
+    }
+}
+
+impl<T> Deref for BaseTask<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[repr(transparent)]
+pub struct BaseTaskRef<T> {
+    inner: NonNull<BaseTask<T>>,
+}
+
+impl<T> Clone for BaseTaskRef<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.as_ptr())
+    }
+}
+
+unsafe impl<T> Send for BaseTaskRef<T> {}
+unsafe impl<T> Sync for BaseTaskRef<T> {}
+
+impl<T> BaseTaskRef<T> {
+    pub fn new(inner: *const BaseTask<T>) -> Self {
+        Self {
+            inner: NonNull::new(inner as _).unwrap(),
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.inner.as_ptr() == other.inner.as_ptr()
+    }
+
+    /// Returns the address of the task, which identifies it while it is alive.
+    pub fn addr(&self) -> usize {
+        self.inner.as_ptr() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner == NonNull::dangling()
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn into_arc(&self) -> ManuallyDrop<Arc<BaseTask<T>>> {
+        unsafe { ManuallyDrop::new(Arc::from_raw(self.inner.as_ptr() as _)) }
+    }
+}
+
+impl<T> Deref for BaseTaskRef<T> {
+    type Target = BaseTask<T>;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.inner.as_ref() }
+    }
+}
+
+impl<T: Debug> Debug for BaseTask<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BaseTask")
+            .field("inner", self.inner())
+            .finish()
+    }
+}
+
+impl<T: Debug> Debug for BaseTaskRef<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BaseTaskRef")
+            .field("BaseTask", unsafe { self.inner.as_ref() })
+            .finish()
+    }
+}
@@ -1,119 +1,354 @@
-macro_rules! def_test_sched {
-    ($name: ident, $scheduler: ty, $task: ty, $task_ref: ty, $weak_task_ref: ty) => {
-        mod $name {
-            use crate::*;
-            use alloc::sync::{Arc, Weak};
-
-            pub extern "C" fn task_clone(raw_ptr: *const $task) {
-                unsafe { Arc::increment_strong_count(raw_ptr) };
-            }
-
-            pub extern "C" fn task_drop(raw_ptr: *const $task) {
-                drop(unsafe { Arc::from_raw(raw_ptr) });
-            }
-
-            pub extern "C" fn task_strong_count(raw_ptr: *const $task) -> usize {
-                let arc_task = unsafe { std::mem::ManuallyDrop::new(Arc::from_raw(raw_ptr)) };
-                let count = Arc::strong_count(&arc_task);
-                count
-            }
-
-            pub extern "C" fn task_drop_weak(raw_ptr: *const $task) {
-                let weak_task = unsafe { Weak::from_raw(raw_ptr) };
-                let _ = weak_task.upgrade().unwrap();
-            }
-
-            pub extern "C" fn task_weak_clone(raw_ptr: *const $task) -> $weak_task_ref {
-                let _arc_task = unsafe { std::mem::ManuallyDrop::new(Arc::from_raw(raw_ptr)) };
-                let weak_task_ptr = Arc::downgrade(&_arc_task).into_raw() as _;
-                <$weak_task_ref>::new(
-                    core::ptr::NonNull::new(weak_task_ptr).unwrap(),
-                    task_drop_weak,
-                )
-            }
-
-            #[test]
-            fn test_sched() {
-                const NUM_TASKS: usize = 11;
-
-                let scheduler = <$scheduler>::new();
-                for i in 0..NUM_TASKS {
-                    scheduler.add_task(<$task_ref>::new(
-                        core::ptr::NonNull::new(Arc::into_raw(Arc::new(<$task>::new(i))) as _)
-                            .unwrap(),
-                        task_clone,
-                        task_weak_clone,
-                        task_drop,
-                        task_strong_count,
-                    ));
-                }
-
-                for i in 0..NUM_TASKS * 10 - 1 {
-                    let next = scheduler.pick_next_task().unwrap();
-                    assert_eq!(*next.inner(), i % NUM_TASKS);
-                    // pass a tick to ensure the order of tasks
-                    scheduler.task_tick(&next);
-                    scheduler.put_prev_task(next, false);
-                }
-
-                let mut n = 0;
-                while scheduler.pick_next_task().is_some() {
-                    n += 1;
-                }
-                assert_eq!(n, NUM_TASKS);
-            }
-
-            #[test]
-            fn bench_yield() {
-                const NUM_TASKS: usize = 208;
-                const COUNT: usize = NUM_TASKS * 3;
-
-                let scheduler = <$scheduler>::new();
-                for i in 0..NUM_TASKS {
-                    scheduler.add_task(<$task_ref>::new(
-                        core::ptr::NonNull::new(Arc::into_raw(Arc::new(<$task>::new(i))) as _)
-                            .unwrap(),
-                        task_clone,
-                        task_weak_clone,
-                        task_drop,
-                        task_strong_count,
-                    ));
-                }
-
-                let t0 = std::time::Instant::now();
-                for _ in 0..COUNT {
-                    let next = scheduler.pick_next_task().unwrap();
-                    scheduler.put_prev_task(next, false);
-                }
-                let t1 = std::time::Instant::now();
-                println!(
-                    "  {}: task yield speed: {:?}/task",
-                    stringify!($scheduler),
-                    (t1 - t0) / (COUNT as u32)
-                );
-            }
+use crate::*;
+use alloc::boxed::Box;
+
+const MS: usize = 1_000_000;
+
+/// Creates a task whose inner value is `id`. The task is leaked, since
+/// [`BaseTaskRef`] does not own it.
+fn new_task(id: usize) -> BaseTaskRef<usize> {
+    BaseTaskRef::new(Box::leak(Box::new(BaseTask::new(id))))
+}
+
+fn new_scheduler(policy: SchedPolicy) -> Box<Scheduler<usize>> {
+    let mut scheduler = Box::new(Scheduler::new(policy));
+    scheduler.init();
+    scheduler
+}
+
+/// Picks all tasks and returns their ids in the picking order.
+fn pick_all(scheduler: &Scheduler<usize>) -> alloc::vec::Vec<usize> {
+    core::iter::from_fn(|| scheduler.pick_next_task())
+        .map(|task| *task.inner())
+        .collect()
+}
+
+#[test]
+fn fifo_order_and_remove() {
+    for policy in [SchedPolicy::Fifo, SchedPolicy::RoundRobin] {
+        let scheduler = new_scheduler(policy);
+        let tasks: alloc::vec::Vec<_> = (0..5).map(new_task).collect();
+        for task in tasks.iter() {
+            scheduler.add_task(task.clone()).unwrap();
+        }
+        assert!(scheduler.remove_task(&tasks[2]).is_some());
+        assert!(scheduler.remove_task(&tasks[2]).is_none());
+        assert_eq!(pick_all(&scheduler), [0, 1, 3, 4]);
+        assert_eq!(scheduler.load(), 0);
+    }
+}
+
+#[test]
+fn round_robin_time_slice() {
+    let scheduler = new_scheduler(SchedPolicy::RoundRobin);
+    assert!(scheduler.set_time_slice(2 * MS));
+    let task = new_task(0);
+    scheduler.add_task(task.clone()).unwrap();
+    let task = scheduler.pick_next_task().unwrap();
+    assert!(!scheduler.task_tick(&task, MS));
+    assert!(scheduler.task_tick(&task, MS));
+}
+
+#[test]
+fn cfs_picks_least_vruntime() {
+    let scheduler = new_scheduler(SchedPolicy::Cfs);
+    for i in 0..3 {
+        scheduler.add_task(new_task(i)).unwrap();
+    }
+    // Task 0 runs for a while, so it is picked after the others.
+    let task = scheduler.pick_next_task().unwrap();
+    assert_eq!(*task.inner(), 0);
+    scheduler.task_tick(&task, 10 * MS);
+    scheduler.put_prev_task(task, false).unwrap();
+    assert_eq!(pick_all(&scheduler), [1, 2, 0]);
+}
+
+#[test]
+fn cfs_remove_and_nice() {
+    let scheduler = new_scheduler(SchedPolicy::Cfs);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    for task in tasks.iter() {
+        scheduler.add_task(task.clone()).unwrap();
+    }
+    assert_eq!(scheduler.load(), 3 * 1024);
+    let task = scheduler.remove_task(&tasks[1]).unwrap();
+    assert_eq!(scheduler.load(), 2 * 1024);
+    // A heavier task gains vruntime slower.
+    assert!(scheduler.set_priority(&task, -5));
+    assert!(!scheduler.set_priority(&task, 20));
+    scheduler.add_task(task).unwrap();
+    assert_eq!(scheduler.load(), 2 * 1024 + 3121);
+    assert_eq!(pick_all(&scheduler).len(), 3);
+    assert_eq!(scheduler.load(), 0);
+}
+
+#[test]
+fn rt_before_normal() {
+    let scheduler = new_scheduler(SchedPolicy::Cfs);
+    let tasks: alloc::vec::Vec<_> = (0..4).map(new_task).collect();
+    assert!(scheduler.set_sched_class(&tasks[1], SchedClass::RtFifo, 10));
+    assert!(scheduler.set_sched_class(&tasks[2], SchedClass::RtRoundRobin, 20));
+    assert!(!scheduler.set_sched_class(&tasks[3], SchedClass::RtFifo, RT_PRIO_LEVELS as isize));
+    for task in tasks.iter() {
+        scheduler.add_task(task.clone()).unwrap();
+    }
+    // A real-time task always preempts a normal task, and a higher priority
+    // preempts a lower one.
+    assert!(scheduler.check_preempt_wakeup(&tasks[0], &tasks[1]));
+    assert!(!scheduler.check_preempt_wakeup(&tasks[1], &tasks[0]));
+    assert!(scheduler.check_preempt_wakeup(&tasks[1], &tasks[2]));
+    assert!(!scheduler.check_preempt_wakeup(&tasks[2], &tasks[1]));
+    assert!(scheduler.remove_task(&tasks[1]).is_some());
+    scheduler.add_task(tasks[1].clone()).unwrap();
+    assert_eq!(pick_all(&scheduler), [2, 1, 0, 3]);
+}
+
+#[test]
+fn rt_preempted_keeps_head() {
+    let scheduler = new_scheduler(SchedPolicy::Fifo);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    for task in tasks.iter() {
+        assert!(scheduler.set_sched_class(task, SchedClass::RtFifo, 10));
+        scheduler.add_task(task.clone()).unwrap();
+    }
+    let task = scheduler.pick_next_task().unwrap();
+    scheduler.put_prev_task(task, true).unwrap();
+    let task = scheduler.pick_next_task().unwrap();
+    assert_eq!(*task.inner(), 0);
+    scheduler.put_prev_task(task, false).unwrap();
+    assert_eq!(pick_all(&scheduler), [1, 2, 0]);
+}
+
+#[test]
+fn edf_admission() {
+    let scheduler = new_scheduler(SchedPolicy::Edf);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    // The parameters must satisfy `runtime <= deadline <= period`.
+    assert!(!scheduler.set_deadline_params(&tasks[0], 5 * MS, 4 * MS, 10 * MS));
+    assert!(!scheduler.set_deadline_params(&tasks[0], 5 * MS, 20 * MS, 10 * MS));
+    assert!(scheduler.set_deadline_params(&tasks[0], 5 * MS, 10 * MS, 10 * MS));
+    assert!(scheduler.set_deadline_params(&tasks[1], 4 * MS, 10 * MS, 10 * MS));
+    // The total bandwidth would exceed 100%.
+    assert!(!scheduler.set_deadline_params(&tasks[2], 2 * MS, 10 * MS, 10 * MS));
+    // Changing a reservation replaces its old bandwidth.
+    assert!(scheduler.set_deadline_params(&tasks[1], 5 * MS, 10 * MS, 10 * MS));
+    assert!(!scheduler.can_migrate(&tasks[1]));
+    // Removing a reservation releases its bandwidth.
+    assert!(scheduler.set_deadline_params(&tasks[0], 0, 0, 0));
+    assert!(scheduler.can_migrate(&tasks[0]));
+    assert!(scheduler.set_deadline_params(&tasks[2], 2 * MS, 10 * MS, 10 * MS));
+    // Other policies do not admit reservations.
+    let fifo = new_scheduler(SchedPolicy::Fifo);
+    assert!(!fifo.set_deadline_params(&new_task(3), MS, MS, MS));
+}
+
+#[test]
+fn edf_earliest_deadline_first() {
+    let scheduler = new_scheduler(SchedPolicy::Edf);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    assert!(scheduler.set_deadline_params(&tasks[1], MS, 20 * MS, 20 * MS));
+    assert!(scheduler.set_deadline_params(&tasks[2], MS, 10 * MS, 20 * MS));
+    for task in tasks.iter() {
+        scheduler.add_task(task.clone()).unwrap();
+    }
+    // Tasks without reservation run after all reserved tasks.
+    assert!(scheduler.check_preempt_wakeup(&tasks[0], &tasks[1]));
+    assert!(scheduler.check_preempt_wakeup(&tasks[1], &tasks[2]));
+    assert_eq!(pick_all(&scheduler), [2, 1, 0]);
+}
+
+#[test]
+fn edf_postpone_on_overrun() {
+    let scheduler = new_scheduler(SchedPolicy::Edf);
+    let (task, other) = (new_task(0), new_task(1));
+    assert!(scheduler.set_deadline_params(&task, 2 * MS, 5 * MS, 10 * MS));
+    assert!(scheduler.set_deadline_params(&other, 2 * MS, 8 * MS, 10 * MS));
+    scheduler.add_task(task.clone()).unwrap();
+    scheduler.add_task(other.clone()).unwrap();
+    let curr = scheduler.pick_next_task().unwrap();
+    assert!(curr.ptr_eq(&task));
+    scheduler.update_clock(MS);
+    assert!(!scheduler.task_tick(&curr, MS));
+    // The budget is used up, so the deadline is postponed by one period
+    // (to 15 ms), after the deadline of the other task.
+    scheduler.update_clock(2 * MS);
+    assert!(scheduler.task_tick(&curr, MS));
+    scheduler.put_prev_task(curr, true).unwrap();
+    assert_eq!(pick_all(&scheduler), [1, 0]);
+    assert_eq!(task.edf().deadline_misses(), 0);
+}
+
+#[test]
+fn edf_replenish_and_miss() {
+    let scheduler = new_scheduler(SchedPolicy::Edf);
+    let task = new_task(0);
+    assert!(scheduler.set_deadline_params(&task, 4 * MS, 5 * MS, 10 * MS));
+    scheduler.add_task(task.clone()).unwrap();
+    let curr = scheduler.pick_next_task().unwrap();
+    // The deadline passes before the reserved runtime is used up, so a new
+    // period starts with a full budget.
+    scheduler.update_clock(6 * MS);
+    scheduler.task_tick(&curr, MS);
+    assert_eq!(task.edf().deadline_misses(), 1);
+    scheduler.update_clock(7 * MS);
+    scheduler.task_tick(&curr, MS);
+    assert_eq!(task.edf().deadline_misses(), 1);
+    // A task put back after its deadline starts a new period, and the missed
+    // period is counted once.
+    scheduler.update_clock(20 * MS);
+    scheduler.put_prev_task(curr, true).unwrap();
+    assert_eq!(task.edf().deadline_misses(), 2);
+    let curr = scheduler.pick_next_task().unwrap();
+    scheduler.update_clock(21 * MS);
+    scheduler.task_tick(&curr, MS);
+    assert_eq!(task.edf().deadline_misses(), 2);
+}
+
+#[test]
+fn edf_queued_miss() {
+    let scheduler = new_scheduler(SchedPolicy::Edf);
+    let (running, queued) = (new_task(0), new_task(1));
+    assert!(scheduler.set_deadline_params(&queued, MS, 5 * MS, 10 * MS));
+    scheduler.add_task(queued.clone()).unwrap();
+    // The ready task misses its deadline while another task keeps running.
+    scheduler.update_clock(6 * MS);
+    scheduler.task_tick(&running, 6 * MS);
+    assert_eq!(queued.edf().deadline_misses(), 1);
+    scheduler.update_clock(7 * MS);
+    scheduler.task_tick(&running, MS);
+    assert_eq!(queued.edf().deadline_misses(), 1);
+    // It starts a new period when it runs, without counting the miss again.
+    let curr = scheduler.pick_next_task().unwrap();
+    scheduler.update_clock(8 * MS);
+    scheduler.task_tick(&curr, MS);
+    assert_eq!(queued.edf().deadline_misses(), 1);
+}
+
+#[test]
+fn stats_follow_clock() {
+    let scheduler = new_scheduler(SchedPolicy::Fifo);
+    let (prev, next) = (new_task(0), new_task(1));
+    scheduler.update_clock(MS);
+    scheduler.add_task(next.clone()).unwrap();
+    scheduler.update_clock(3 * MS);
+    // The clock never goes backwards.
+    scheduler.update_clock(2 * MS);
+    assert_eq!(scheduler.clock(), 3 * MS);
+    let picked = scheduler.pick_next_task().unwrap();
+    scheduler.account_switch(&prev, &picked, false);
+    scheduler.update_clock(7 * MS);
+    scheduler.account_switch(&picked, &prev, true);
+    let stats = next.stats().get();
+    assert_eq!(stats.wait_time, 2 * MS);
+    assert_eq!(stats.runtime, 4 * MS);
+    assert_eq!(stats.voluntary_switches, 1);
+    assert_eq!(scheduler.stats().involuntary_switches, 1);
+}
+
+#[test]
+fn remove_first_by_keeps_order() {
+    for policy in [SchedPolicy::Fifo, SchedPolicy::RoundRobin, SchedPolicy::Cfs] {
+        let scheduler = new_scheduler(policy);
+        let tasks: alloc::vec::Vec<_> = (0..4).map(new_task).collect();
+        assert!(scheduler.set_sched_class(&tasks[3], SchedClass::RtFifo, 10));
+        for task in tasks.iter() {
+            scheduler.add_task(task.clone()).unwrap();
         }
-    };
-}
-
-def_test_sched!(
-    fifo,
-    FifoScheduler::<usize, 256>,
-    FifoTask::<usize>,
-    FiFoTaskRef::<usize>,
-    WeakFiFoTaskRef<usize>
-);
-def_test_sched!(
-    rr,
-    RRScheduler::<usize, 5, 256>,
-    RRTask::<usize, 5>,
-    RRTaskRef::<usize, 5>,
-    WeakRRTaskRef<usize, 5>
-);
-def_test_sched!(
-    cfs,
-    CFScheduler::<usize, 256>,
-    CFSTask::<usize>,
-    CFSTaskRef<usize>,
-    WeakCFSTaskRef<usize>
-);
+        // Real-time tasks are checked first, then normal tasks in order.
+        let task = scheduler
+            .remove_first_by(|task| *task.inner() != 0)
+            .unwrap();
+        assert_eq!(*task.inner(), 3);
+        let task = scheduler
+            .remove_first_by(|task| *task.inner() != 0)
+            .unwrap();
+        assert_eq!(*task.inner(), 1);
+        assert!(scheduler
+            .remove_first_by(|task| *task.inner() > 2)
+            .is_none());
+        assert_eq!(scheduler.load(), 2 * 1024);
+        assert_eq!(pick_all(&scheduler), [0, 2]);
+    }
+}
+
+#[test]
+fn remove_after_priority_change() {
+    let scheduler = new_scheduler(SchedPolicy::Cfs);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    assert!(scheduler.set_sched_class(&tasks[2], SchedClass::RtFifo, 10));
+    for task in tasks.iter() {
+        scheduler.add_task(task.clone()).unwrap();
+    }
+    // The priority of a ready task may be changed by other CPUs before it is
+    // removed, but it is still found by its key in the ready queue, and the
+    // load is restored with the weight it was enqueued with.
+    let task = scheduler.pick_next_task().unwrap();
+    assert!(scheduler.set_priority(&tasks[0], -5));
+    assert!(scheduler.set_priority(&tasks[1], 10));
+    scheduler.task_tick(&task, 10 * MS);
+    assert!(scheduler.remove_task(&tasks[1]).is_some());
+    assert!(scheduler.remove_task(&tasks[0]).is_some());
+    assert!(scheduler.set_priority(&task, 20));
+    scheduler.put_prev_task(task.clone(), false).unwrap();
+    assert!(scheduler.set_priority(&task, 30));
+    assert!(scheduler.remove_task(&task).is_some());
+    assert_eq!(scheduler.load(), 0);
+}
+
+#[test]
+fn check_preempt_published_current() {
+    let scheduler = new_scheduler(SchedPolicy::Cfs);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    assert!(scheduler.set_sched_class(&tasks[1], SchedClass::RtFifo, 10));
+    assert!(scheduler.set_sched_class(&tasks[2], SchedClass::RtFifo, 20));
+    // Any task preempts the idle task.
+    assert!(scheduler.check_preempt_current(&tasks[0]));
+    scheduler.set_current(&tasks[1], false);
+    assert!(!scheduler.check_preempt_current(&tasks[0]));
+    assert!(scheduler.check_preempt_current(&tasks[2]));
+    // The published parameters are kept until they are published again.
+    assert!(scheduler.set_sched_class(&tasks[1], SchedClass::Normal, 0));
+    assert!(!scheduler.check_preempt_current(&tasks[0]));
+    scheduler.set_current(&tasks[1], false);
+    assert!(scheduler.check_preempt_current(&tasks[2]));
+    assert!(!scheduler.check_preempt_current(&tasks[0]));
+    // A normal task runs for a while, so a woken task with less vruntime
+    // preempts it after the next publishing.
+    scheduler.add_task(tasks[1].clone()).unwrap();
+    let task = scheduler.pick_next_task().unwrap();
+    scheduler.task_tick(&task, 10 * MS);
+    assert!(!scheduler.check_preempt_current(&tasks[0]));
+    scheduler.set_current(&task, false);
+    assert!(scheduler.check_preempt_current(&tasks[0]));
+}
+
+#[test]
+fn cfs_wakeup_on_another_run_queue() {
+    let src = new_scheduler(SchedPolicy::Cfs);
+    let dst = new_scheduler(SchedPolicy::Cfs);
+    let tasks: alloc::vec::Vec<_> = (0..3).map(new_task).collect();
+    // `min_vruntime` of `src` advances to 10ms, and task 0 blocks 3ms after it.
+    src.add_task(tasks[0].clone()).unwrap();
+    src.add_task(tasks[1].clone()).unwrap();
+    for _ in 0..2 {
+        let task = src.pick_next_task().unwrap();
+        src.task_tick(&task, 10 * MS);
+        src.put_prev_task(task, false).unwrap();
+    }
+    let task = src.pick_next_task().unwrap();
+    assert_eq!(*task.inner(), 0);
+    src.task_tick(&task, 3 * MS);
+    // `min_vruntime` of `dst` advances to 20ms, and task 2 is ready at 25ms.
+    dst.add_task(tasks[2].clone()).unwrap();
+    for elapsed in [20 * MS, 5 * MS] {
+        let task = dst.pick_next_task().unwrap();
+        dst.task_tick(&task, elapsed);
+        dst.put_prev_task(task, false).unwrap();
+    }
+    // Task 0 is woken up on `dst`, keeping its lag of 3ms against the run queue.
+    src.detach_task(&task);
+    dst.attach_task(&task);
+    assert_eq!(task.cfs().get_vruntime(), 23 * MS as isize);
+    dst.account_wakeup(&task);
+    dst.put_prev_task(task, false).unwrap();
+    assert_eq!(pick_all(&dst), [0, 2]);
+}
@@ -0,0 +1,183 @@
+//! 在创建任务时设置任务参数的[`TaskBuilder`]。
+
+use alloc::{boxed::Box, string::String};
+use core::any::{Any, TypeId};
+
+use base_task::SchedStatus;
+use config::{AxCpuMask, SMP};
+
+use crate::{
+    join_handle::JoinHandle,
+    sched::{kick_idle_cpus, update_clock, without_preempt},
+    task_inner_ext::{ArcTaskRef, TaskLocals, arcext_to_base, base_to_arcext},
+};
+
+/// 任务构建器，用于在创建任务时设置其参数。
+///
+/// 所有参数都在任务被放入就绪队列之前设置，因此任务第一次被调度器看到时，其参数已经全部生效。
+///
+/// ```ignore
+/// let handle = TaskBuilder::new()
+///     .name("worker".into())
+///     .stack_size(0x10000)
+///     .priority(5)
+///     .cpu(1)
+///     .local(0usize)
+///     .spawn_f(async { 42 })
+///     .unwrap();
+/// assert_eq!(handle.await, Some(42));
+/// ```
+pub struct TaskBuilder {
+    name: String,
+    stack_size: usize,
+    priority: Option<isize>,
+    cpumask: Option<AxCpuMask>,
+    cpu_id: Option<usize>,
+    detached: bool,
+    locals: TaskLocals,
+}
+
+impl TaskBuilder {
+    /// 创建一个使用默认参数的任务构建器。
+    ///
+    /// 默认的任务名称为空字符串，栈大小为[`config::TASK_STACK_SIZE`]，
+    /// 优先级为调度器的默认优先级，可以在所有CPU上运行，且可以被等待。
+    /// 任务被放入的CPU的选择方式见[`cpu`](Self::cpu)。
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            stack_size: config::TASK_STACK_SIZE,
+            priority: None,
+            cpumask: None,
+            cpu_id: None,
+            detached: false,
+            locals: TaskLocals::new(),
+        }
+    }
+
+    /// 设置任务名称。
+    pub fn name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// 设置任务的栈大小。
+    ///
+    /// 对线程而言，是其栈的大小；对协程而言，是其每次运行时从栈池中获取的栈的大小。
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    /// 设置任务的初始优先级，即其nice值（范围为`-20..=19`，仅在[`SchedPolicy::Cfs`]调度策略下有效）。
+    ///
+    /// [`SchedPolicy::Cfs`]: crate::task_api::SchedPolicy::Cfs
+    pub fn priority(mut self, prio: isize) -> Self {
+        self.priority = Some(prio);
+        self
+    }
+
+    /// 设置任务可以运行的CPU。
+    pub fn cpumask(mut self, cpumask: AxCpuMask) -> Self {
+        self.cpumask = Some(cpumask);
+        self
+    }
+
+    /// 设置任务被放入的CPU。
+    ///
+    /// 未设置时，若当前CPU在任务的CPU亲和性掩码中，则放入当前CPU，否则从掩码中轮流选择一个CPU。
+    pub fn cpu(mut self, cpu_id: usize) -> Self {
+        self.cpu_id = Some(cpu_id);
+        self
+    }
+
+    /// 设置任务是否为分离的。
+    ///
+    /// 分离的任务不能被等待，其句柄的[`join`](JoinHandle::join)和`.await`会立即返回`None`。
+    pub fn detached(mut self, detached: bool) -> Self {
+        self.detached = detached;
+        self
+    }
+
+    /// 设置任务局部变量的初始值，每种类型最多保存一个值。
+    ///
+    /// 任务可通过[`with_task_local`](crate::task_api::with_task_local)访问其局部变量。
+    pub fn local<T: Any + Send>(mut self, value: T) -> Self {
+        self.locals.insert(TypeId::of::<T>(), Box::new(value));
+        self
+    }
+
+    /// 以返回`T`的`entry`为入口函数创建线程，设置其参数，并将其放入就绪队列。
+    ///
+    /// 返回任务的句柄。若目标CPU的就绪队列已满，则返回`Err`并交还该任务。
+    ///
+    /// # Panics
+    ///
+    /// 目标CPU不存在或不在任务的CPU亲和性掩码中，或优先级无效时panic。
+    pub fn spawn<F, T>(self, entry: F) -> Result<JoinHandle<T>, ArcTaskRef>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let handle = JoinHandle::new(entry, self.name.clone(), self.stack_size);
+        self.spawn_task(handle.task().clone())?;
+        Ok(handle)
+    }
+
+    /// 以输出`T`的`future`创建协程，设置其参数，并将其放入就绪队列。
+    ///
+    /// 返回任务的句柄。若目标CPU的就绪队列已满，则返回`Err`并交还该任务。
+    ///
+    /// # Panics
+    ///
+    /// 目标CPU不存在或不在任务的CPU亲和性掩码中，或优先级无效时panic。
+    pub fn spawn_f<F, T>(self, future: F) -> Result<JoinHandle<T>, ArcTaskRef>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let handle = JoinHandle::new_f(future, self.name.clone(), self.stack_size);
+        self.spawn_task(handle.task().clone())?;
+        Ok(handle)
+    }
+
+    /// 设置还未运行的任务的参数，并将其放入就绪队列。
+    fn spawn_task(self, task: ArcTaskRef) -> Result<(), ArcTaskRef> {
+        if let Some(cpumask) = self.cpumask {
+            task.set_cpumask(cpumask);
+        }
+        let cpu_id = self.cpu_id.unwrap_or_else(|| task.select_run_queue_index());
+        assert!(
+            cpu_id < SMP && task.cpumask().get(cpu_id),
+            "CPU {} is not allowed for {}",
+            cpu_id,
+            task.id_name()
+        );
+        task.set_detached(self.detached);
+        task.locals().lock().extend(self.locals);
+
+        let task = arcext_to_base(task);
+        if let Some(prio) = self.priority {
+            // 任务还未放入就绪队列，由目标CPU的调度器检查并设置其优先级。
+            let ok = without_preempt(|| libvsched::set_task_priority(&task, prio, cpu_id));
+            assert!(ok, "invalid priority {} on CPU {}", prio, cpu_id);
+        }
+        let status = without_preempt(|| {
+            update_clock(cpu_id);
+            libvsched::spawn(cpu_id, task.clone())
+        });
+        match status {
+            SchedStatus::RunQueueFull => Err(unsafe { base_to_arcext(task) }),
+            _ => {
+                kick_idle_cpus();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Default for TaskBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
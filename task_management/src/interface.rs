@@ -3,6 +3,7 @@
 //! 通过[`crate_interface`](https://docs.rs/crate_interface/latest/crate_interface/)实现接口的定义和调用，
 //! 因此其它模块也需通过`crate_interface`来实现这些接口。
 
+use core::{sync::atomic::AtomicU32, time::Duration};
 use crate_interface::{call_interface, def_interface};
 
 /// 与多核相关的接口
@@ -10,6 +11,13 @@ use crate_interface::{call_interface, def_interface};
 pub trait SMPIf {
     /// 获取当前CPU的ID
     fn get_cpu_id() -> usize;
+
+    /// 通知CPU `cpu_id`处理其它CPU发送的重新调度请求，即在该CPU上调用
+    /// [`resched_ipi`](crate::task_api::resched_ipi)，类似于核间中断。
+    ///
+    /// 在用户态中，可以向该CPU对应的线程发送信号实现。
+    /// 若不支持抢占，则可以不做任何事，被唤醒的任务会在该CPU下一次调度时运行。
+    fn send_resched_ipi(cpu_id: usize);
 }
 
 #[inline]
@@ -17,6 +25,11 @@ pub(crate) fn get_cpu_id() -> usize {
     call_interface!(SMPIf::get_cpu_id())
 }
 
+#[inline]
+pub(crate) fn send_resched_ipi(cpu_id: usize) {
+    call_interface!(SMPIf::send_resched_ipi(cpu_id))
+}
+
 /// 与任务调度相关的接口
 #[def_interface]
 pub trait TaskIf {
@@ -28,3 +41,40 @@ pub trait TaskIf {
 pub(crate) fn main_task_exit(exit_code: i32) -> ! {
     call_interface!(TaskIf::main_task_exit(exit_code))
 }
+
+/// 与时间相关的接口
+#[def_interface]
+pub trait TimeIf {
+    /// 获取当前的单调时间，即自某一固定时刻（如系统启动）起经过的时间。
+    ///
+    /// 所有CPU上的返回值应基于同一时刻。
+    fn current_time() -> Duration;
+}
+
+#[inline]
+pub(crate) fn current_time() -> Duration {
+    call_interface!(TimeIf::current_time())
+}
+
+/// 与CPU空闲相关的接口
+#[def_interface]
+pub trait IdleIf {
+    /// 当前CPU没有可运行的任务时，阻塞当前CPU，直到`word`的值不等于`expected`、
+    /// 被[`wake`](IdleIf::wake)唤醒，或经过`timeout`。
+    ///
+    /// 允许提前返回。在用户态中，可以通过futex实现；若直接返回，则空闲CPU会忙等。
+    fn wait(word: &AtomicU32, expected: u32, timeout: Duration);
+
+    /// 唤醒阻塞在`word`上的CPU。
+    fn wake(word: &AtomicU32);
+}
+
+#[inline]
+pub(crate) fn idle_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+    call_interface!(IdleIf::wait(word, expected, timeout))
+}
+
+#[inline]
+pub(crate) fn idle_wake(word: &AtomicU32) {
+    call_interface!(IdleIf::wake(word))
+}
@@ -0,0 +1,119 @@
+//! 可获取任务返回值的任务句柄。
+
+use alloc::{boxed::Box, string::String, sync::Arc};
+use core::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use base_task::TaskState;
+
+use crate::{sched::SpinNoPreempt, task_inner_ext::ArcTaskRef};
+
+/// 等待任务退出的Future。
+type JoinFuture = Pin<Box<dyn Future<Output = Option<i32>> + Send>>;
+
+/// 任务的句柄，用于等待任务退出并获取其返回值。
+///
+/// 线程通过[`JoinHandle::join`]阻塞等待，协程可以直接`.await`该句柄。
+///
+/// 若任务通过[`exit`](crate::task_api::exit)或[`exit_f`](crate::task_api::exit_f)提前退出，
+/// 则没有返回值，等待的结果为`None`。
+pub struct JoinHandle<T> {
+    task: ArcTaskRef,
+    result: Arc<SpinNoPreempt<Option<T>>>,
+    /// 协程等待任务退出时使用的Future，在第一次poll时创建。
+    join_future: Option<JoinFuture>,
+}
+
+impl<T: Send + 'static> JoinHandle<T> {
+    /// 以`entry`为入口函数创建线程，返回其句柄，不运行该任务。
+    pub(crate) fn new<F>(entry: F, name: String, stack_size: usize) -> Self
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let result = Arc::new(SpinNoPreempt::new(None));
+        let slot = result.clone();
+        let task = crate::task::new(
+            move || {
+                let value = entry();
+                *slot.lock() = Some(value);
+            },
+            name,
+            stack_size,
+        );
+        Self {
+            task,
+            result,
+            join_future: None,
+        }
+    }
+
+    /// 以`future`创建协程，协程运行时使用大小为`stack_size`的栈。返回其句柄，不运行该任务。
+    pub(crate) fn new_f<F>(future: F, name: String, stack_size: usize) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let result = Arc::new(SpinNoPreempt::new(None));
+        let slot = result.clone();
+        let task = crate::task::new_f(
+            async move {
+                let value = future.await;
+                *slot.lock() = Some(value);
+            },
+            name,
+            stack_size,
+        );
+        Self {
+            task,
+            result,
+            join_future: None,
+        }
+    }
+}
+
+impl<T> JoinHandle<T> {
+    /// 获取对任务的引用。
+    pub fn task(&self) -> &ArcTaskRef {
+        &self.task
+    }
+
+    /// 返回任务是否已退出。
+    pub fn is_finished(&self) -> bool {
+        self.task.state() == TaskState::Exited
+    }
+
+    /// 使当前线程等待该任务退出，返回任务的返回值。
+    pub fn join(self) -> Option<T> {
+        self.task.join();
+        self.result.lock().take()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let join_future = this.join_future.get_or_insert_with(|| {
+            let task = this.task.clone();
+            Box::pin(async move { task.join_f().await })
+        });
+        match join_future.as_mut().poll(cx) {
+            Poll::Ready(_) => {
+                this.join_future = None;
+                Poll::Ready(this.result.lock().take())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("task", &self.task)
+            .finish_non_exhaustive()
+    }
+}
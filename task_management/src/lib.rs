@@ -7,10 +7,13 @@
 
 extern crate alloc;
 
+pub mod builder;
 pub mod interface;
+pub mod join_handle;
 pub mod sched;
+pub mod sync;
 pub mod task;
 pub mod task_api;
 pub mod task_inner_ext;
+pub mod timers;
 pub mod wait_queue;
-pub mod waker_queue;
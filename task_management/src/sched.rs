@@ -1,29 +1,198 @@
 //! 初始化vsched，并基于vsched实现任务相关操作：
 //!
-//! - 线程的让出（[`yield_now`]）、阻塞（[`blocked_resched`]）和退出（[`exit`]）
-//! - 协程的让出（[`yield_now_f`]）、阻塞（[`BlockedReschedFuture`]）和退出（[`exit_f`]）
+//! - 线程的让出（[`yield_now`]）、阻塞（[`blocked_resched`]）、睡眠（[`sleep_until`]）和退出（[`exit`]）
+//! - 协程的让出（[`yield_now_f`]）、阻塞（[`BlockedReschedFuture`]）、睡眠（[`SleepUntilFuture`]）和退出（[`exit_f`]）
 //!
 //! 本模块在上述操作中负责的部分为：任务状态与调度器状态的维护、协程接口的Future包装。
 //!
-//! 本模块没有对外API。
+//! 此外，本模块还实现了时钟中断的处理（[`timer_tick`]）、其它CPU的重新调度请求的处理（[`resched_ipi`]）、
+//! 任务的抢占（[`current_check_preempt_pending`]）和空闲CPU的睡眠与唤醒（[`idle_sleep`]、[`kick_idle_cpus`]）。
+//!
+//! ## 抢占计数
+//!
+//! 任务的抢占计数（`preempt_disable_count`）不为0时，时钟中断处理函数不会进入vsched，也不会抢占该任务。
+//! 因此，本模块在调用vsched的操作前会增加当前任务的抢占计数，并保证：
+//!
+//! - 被切换出的任务的抢占计数至少为1，在其恢复运行并完成切换后才减少，
+//!   使时钟中断不会在任务切换的过程中到来；
+//! - 新建的任务的抢占计数为1，在其入口处完成切换后减少；
+//! - 协程只在poll的过程中抢占计数为0，见`task`模块中的`coroutine_schedule`。
+//!
+//! 本模块没有对外API（[`current_check_preempt_pending`]是供`base_task`调用的符号）。
 
 use core::{
     mem::ManuallyDrop,
     pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     task::{Context, Poll},
+    time::Duration,
 };
 
-use base_task::TaskState;
-use config::AxCpuMask;
+use base_task::{CPU_IDLE, CPU_KICKED, CPU_RUNNING, SchedPolicy, SchedStatus, TaskState};
+use config::{AxCpuMask, SMP};
 
 use crate::{
-    interface::{get_cpu_id, main_task_exit},
+    interface::{current_time, get_cpu_id, idle_wait, idle_wake, main_task_exit, send_resched_ipi},
     task::{self, run_idle},
-    task_inner_ext::{arcext_to_base, base_to_ext},
-    wait_queue::{WaitQueue, WaitQueueGuard},
+    task_inner_ext::{ArcTaskRef, TaskRef, arcext_to_base, base_to_ext, ext_to_base},
+    timers::{check_events, next_deadline, set_alarm_wakeup, set_wait_timeout},
+    wait_queue::{WaitQueue, WaitQueueGuard, Waiter},
 };
 
-pub(crate) fn init_vsched() {
+/// 各CPU上的vsched是否已初始化。
+static VSCHED_INITED: [AtomicBool; SMP] = [const { AtomicBool::new(false) }; SMP];
+
+/// 空闲CPU每次睡眠的最长时间，使其仍能周期性地从其它CPU拉取任务。
+const IDLE_MAX_SLEEP: Duration = Duration::from_millis(10);
+
+/// 各CPU上一次处理时钟中断的时间，单位为纳秒。
+static LAST_TICK: [AtomicU64; SMP] = [const { AtomicU64::new(0) }; SMP];
+
+fn mark_vsched_inited() {
+    LAST_TICK[get_cpu_id()].store(current_time().as_nanos() as u64, Ordering::Release);
+    VSCHED_INITED[get_cpu_id()].store(true, Ordering::Release);
+}
+
+/// 返回CPU `cpu_id`上的vsched是否已初始化。
+#[inline]
+pub(crate) fn is_vsched_inited(cpu_id: usize) -> bool {
+    VSCHED_INITED[cpu_id].load(Ordering::Acquire)
+}
+
+/// 获取当前任务。若当前CPU上的vsched未初始化，则返回`None`。
+#[inline]
+fn try_current() -> Option<TaskRef> {
+    VSCHED_INITED[get_cpu_id()]
+        .load(Ordering::Acquire)
+        .then(|| unsafe { base_to_ext(libvsched::current(get_cpu_id())) })
+}
+
+/// 在关闭当前任务抢占的情况下执行`f`，`f`中不能切换任务。
+///
+/// 用于不切换任务的vsched调用，使时钟中断处理函数不会在其执行过程中重入vsched。
+pub(crate) fn without_preempt<R>(f: impl FnOnce() -> R) -> R {
+    let curr = try_current();
+    if let Some(curr) = &curr {
+        curr.disable_preempt();
+    }
+    let ret = f();
+    if let Some(curr) = &curr {
+        curr.enable_preempt(true);
+    }
+    ret
+}
+
+/// 获取锁时关闭当前任务抢占的自旋锁，`task_management`中的锁都使用该类型。
+pub type SpinNoPreempt<T> = kspin::BaseSpinLock<NoPreemptGuard, T>;
+
+/// [`SpinNoPreempt`]的锁保护引用。
+pub type SpinNoPreemptGuard<'a, T> = kspin::BaseSpinLockGuard<'a, NoPreemptGuard, T>;
+
+/// 关闭当前任务抢占的守卫，用于[`SpinNoPreempt`]。
+///
+/// `kernel_guard`只在`target_os = "none"`时实现`NoPreempt`类守卫，在用户态下它们都是`NoOp`，
+/// 因此`kspin::SpinNoIrq`不会关闭抢占：持锁的任务可能被定时信号抢占，
+/// 之后同一CPU上的任务或时钟中断处理函数在该锁上自旋时将死锁。
+pub struct NoPreemptGuard;
+
+impl kernel_guard::BaseGuard for NoPreemptGuard {
+    type State = ();
+
+    fn acquire() -> Self::State {
+        if let Some(curr) = try_current() {
+            curr.disable_preempt();
+        }
+    }
+
+    fn release(_state: Self::State) {
+        if let Some(curr) = try_current() {
+            curr.enable_preempt(true);
+        }
+    }
+}
+
+/// 使`kspin`等库中的`NoPreempt`类守卫关闭当前任务的抢占。
+#[cfg(feature = "preempt")]
+struct KernelGuardIfImpl;
+
+#[cfg(feature = "preempt")]
+#[crate_interface::impl_interface]
+impl kernel_guard::KernelGuardIf for KernelGuardIfImpl {
+    fn disable_preempt() {
+        if let Some(curr) = try_current() {
+            curr.disable_preempt();
+        }
+    }
+
+    fn enable_preempt() {
+        if let Some(curr) = try_current() {
+            curr.enable_preempt(true);
+        }
+    }
+}
+
+/// 切换回当前任务后，释放上一个任务的Arc引用，并清除其on_cpu标志。
+/// 需要放入其它CPU就绪队列的上一个任务、到期的定时器事件和需要进行的负载均衡也在此时处理。
+///
+/// 所有任务的恢复点都需要调用该函数。
+pub(crate) fn finish_switch() {
+    let prev_task =
+        unsafe { base_to_ext(libvsched::take_prev_task_and_clear_on_cpu(get_cpu_id())) };
+    // 上一任务需要放入其它CPU的就绪队列时（如其CPU亲和性掩码被修改或被迁移时），
+    // 在清除其on_cpu标志后才放入，只有此时才需要唤醒空闲CPU。
+    if libvsched::migrate_prev_task(get_cpu_id()) {
+        kick_idle_cpus();
+    }
+    if prev_task.state() == TaskState::Exited {
+        let _prev_task_to_drop = unsafe { ManuallyDrop::into_inner(prev_task.into_arc()) };
+    }
+    check_events();
+    libvsched::periodic_balance(get_cpu_id());
+}
+
+/// 距上次负载均衡经过的时钟中断时间超过均衡周期时，从最忙的CPU拉取就绪任务到当前CPU。
+///
+/// 时钟中断可能在信号处理函数中到来，因此负载均衡不在时钟中断中进行，
+/// 而在任务切换后（见[`finish_switch`]）和idle任务中进行。
+pub(crate) fn periodic_balance() {
+    without_preempt(|| libvsched::periodic_balance(get_cpu_id()));
+}
+
+/// 当前CPU没有可运行的任务时，通过[`IdleIf`](crate::interface::IdleIf)阻塞当前CPU，
+/// 直到有任务被放入其就绪队列、其下一个定时器事件到期，或经过[`IDLE_MAX_SLEEP`]。
+///
+/// 由idle任务调用。
+pub(crate) fn idle_sleep() {
+    let cpu_id = get_cpu_id();
+    if !without_preempt(|| libvsched::idle_enter(cpu_id)) {
+        return;
+    }
+    let timeout = match next_deadline() {
+        Some(deadline) => deadline.saturating_sub(current_time()).min(IDLE_MAX_SLEEP),
+        None => IDLE_MAX_SLEEP,
+    };
+    let state = libvsched::idle_state(cpu_id);
+    idle_wait(state, CPU_IDLE, timeout);
+    state.store(CPU_RUNNING, Ordering::Release);
+}
+
+/// 唤醒被放入了任务的空闲CPU。
+///
+/// vsched在将任务放入空闲CPU的就绪队列时，只将该CPU标记为[`CPU_KICKED`]，
+/// 因此可能放入任务的操作（创建、唤醒、迁移任务和任务切换）之后都需要调用该函数。
+pub(crate) fn kick_idle_cpus() {
+    for cpu_id in (0..SMP).filter(|&cpu_id| is_vsched_inited(cpu_id)) {
+        let state = libvsched::idle_state(cpu_id);
+        if state
+            .compare_exchange(CPU_KICKED, CPU_RUNNING, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            idle_wake(state);
+        }
+    }
+}
+
+pub(crate) fn init_vsched(policy: SchedPolicy) {
     let main_task = task::new_init("main".into());
     main_task.set_cpumask(AxCpuMask::one_shot(get_cpu_id()));
     let idle_task = task::new(|| run_idle(), "idle".into(), config::TASK_STACK_SIZE);
@@ -32,39 +201,117 @@ pub(crate) fn init_vsched() {
         get_cpu_id(),
         arcext_to_base(idle_task),
         arcext_to_base(main_task),
+        policy,
     );
+    mark_vsched_inited();
 }
 
-pub(crate) fn init_vsched_secondary() {
+pub(crate) fn init_vsched_secondary(policy: SchedPolicy) {
     let idle_task = task::new_init("idle".into());
     idle_task.set_cpumask(AxCpuMask::one_shot(get_cpu_id()));
     libvsched::init_vsched(
         get_cpu_id(),
         arcext_to_base(idle_task.clone()),
         arcext_to_base(idle_task),
+        policy,
     );
+    mark_vsched_inited();
+}
+
+/// 就绪队列已满、无法唤醒任务时，再次尝试唤醒的间隔。
+const UNBLOCK_RETRY_DELAY: Duration = Duration::from_millis(1);
+
+/// 将当前时间设置为CPU `cpu_id`的调度器时钟。
+///
+/// vsched以该时钟统计任务的运行和等待时间，并计算EDF任务的截止时间，
+/// 因此需要在会切换任务或将任务放入就绪队列的vsched操作前调用。
+#[inline]
+pub(crate) fn update_clock(cpu_id: usize) {
+    libvsched::update_clock(cpu_id, current_time().as_nanos() as usize);
+}
+
+/// 将阻塞的任务唤醒到CPU `cpu_id`的就绪队列，`resched`表示被唤醒的任务是否可以抢占该CPU上的当前任务。
+///
+/// 若被唤醒的任务应抢占其它CPU上的当前任务，则通知该CPU处理重新调度请求（见[`resched_ipi`]）。
+///
+/// 目标CPU的就绪队列已满时，vsched会将任务放入其CPU亲和性掩码允许的其它CPU的就绪队列。
+/// 若这些就绪队列都已满，任务保持阻塞状态，并在[`UNBLOCK_RETRY_DELAY`]后由定时器事件再次唤醒，
+/// 因为任务已被移出阻塞队列或定时器列表，不能丢弃这次唤醒。
+pub(crate) fn unblock_task(task: base_task::TaskRef, resched: bool, cpu_id: usize) {
+    let mut resched_cpu_id = cpu_id;
+    let status = without_preempt(|| {
+        update_clock(get_cpu_id());
+        libvsched::unblock_task(
+            task.clone(),
+            resched,
+            cpu_id,
+            get_cpu_id(),
+            &mut resched_cpu_id,
+        )
+    });
+    match status {
+        SchedStatus::RunQueueFull => {
+            log::warn!("the run queues are full, retry waking up the task later");
+            set_alarm_wakeup(current_time() + UNBLOCK_RETRY_DELAY, &unsafe {
+                base_to_ext(task)
+            });
+        }
+        SchedStatus::RemoteResched => send_resched_ipi(resched_cpu_id),
+        SchedStatus::Ok | SchedStatus::Resched | SchedStatus::NotAllowed => {}
+    }
+    kick_idle_cpus();
 }
 
-pub(crate) fn blocked_resched(mut wq_guard: WaitQueueGuard) {
+/// 阻塞当前线程，将其放入持有锁的阻塞队列`wq_guard`中，并切换到其它任务。
+///
+/// 若有`timeout`，则同时为其中的阻塞队列设置定时器事件，在其中的时刻将当前线程移出该阻塞队列并唤醒。
+pub(crate) fn blocked_resched(
+    mut wq_guard: WaitQueueGuard,
+    timeout: Option<(&WaitQueue, Duration)>,
+) {
     let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
     assert!(curr.is_running());
     assert!(!curr.is_idle());
 
+    curr.disable_preempt();
     curr.set_state(base_task::TaskState::Blocked);
     curr.set_in_wait_queue(true);
-    wq_guard.push_back(curr.clone());
+    wq_guard.push_back(Waiter::Task(curr.clone()));
+    // 先将任务标记为阻塞并放入阻塞队列，再在持有阻塞队列的锁时设置定时器，
+    // 使定时器到期时可以唤醒该任务，且唤醒者取出该任务时总能看到其定时器。
+    if let Some((wq, deadline)) = timeout {
+        set_wait_timeout(deadline, &curr, wq);
+    }
     drop(wq_guard);
 
     log::debug!("task blocked {:?}", curr.name());
-    // 所有任务的恢复点都需要释放上一个任务的Arc引用，并清除其on_cpu标志。
-    //
+    update_clock(get_cpu_id());
     // 此处的`libvsched::resched`之后为任务的恢复点之一。
     libvsched::resched(get_cpu_id());
-    let prev_task =
-        unsafe { base_to_ext(libvsched::take_prev_task_and_clear_on_cpu(get_cpu_id())) };
-    if prev_task.state() == TaskState::Exited {
-        let _prev_task_to_drop = unsafe { ManuallyDrop::into_inner(prev_task.into_arc()) };
+    finish_switch();
+    curr.enable_preempt(true);
+}
+
+/// 使当前线程睡眠，直到`deadline`时刻。若`deadline`已过，则直接返回。
+pub(crate) fn sleep_until(deadline: Duration) {
+    let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+    assert!(curr.is_running());
+    assert!(!curr.is_idle());
+    if current_time() >= deadline {
+        return;
     }
+
+    // 先将任务标记为阻塞，再设置定时器，使定时器到期时可以唤醒该任务。
+    curr.disable_preempt();
+    curr.set_state(TaskState::Blocked);
+    set_alarm_wakeup(deadline, &curr);
+
+    log::debug!("task sleep: {}, deadline={:?}", curr.id_name(), deadline);
+    update_clock(get_cpu_id());
+    // 此处的`libvsched::resched`之后为任务的恢复点之一。
+    libvsched::resched(get_cpu_id());
+    finish_switch();
+    curr.enable_preempt(true);
 }
 
 pub(crate) fn exit(exit_code: i32) -> ! {
@@ -72,27 +319,104 @@ pub(crate) fn exit(exit_code: i32) -> ! {
     assert!(curr.is_running());
     assert!(!curr.is_idle());
     log::debug!("{:?} is exited", curr.name());
+    // 退出的任务不会恢复运行，因此不再减少其抢占计数。
+    curr.disable_preempt();
     if curr.is_init() {
         main_task_exit(exit_code) // 原有的代码是返回0而非exit_code，暂不清楚原因。
     } else {
         curr.set_state(base_task::TaskState::Exited);
         curr.notify_exit(exit_code);
     }
+    release_deadline_reservation(&curr);
 
+    update_clock(get_cpu_id());
     libvsched::resched(get_cpu_id());
     unreachable!()
 }
 
+/// 释放退出任务在EDF调度器中预留的带宽，使其能被其它任务使用。
+fn release_deadline_reservation(curr: &ArcTaskRef) {
+    if curr.edf().params().0 > 0 {
+        libvsched::set_deadline_params(0, 0, 0, get_cpu_id());
+    }
+}
+
 #[inline]
 pub(crate) fn yield_now() {
-    // 所有任务的恢复点都需要释放上一个任务的Arc引用，并清除其on_cpu标志。
-    //
+    let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+    curr.disable_preempt();
+    update_clock(get_cpu_id());
     // 此处的`libvsched::yield_now`之后为任务的恢复点之一。
     libvsched::yield_now(get_cpu_id());
-    let prev_task =
-        unsafe { base_to_ext(libvsched::take_prev_task_and_clear_on_cpu(get_cpu_id())) };
-    if prev_task.state() == TaskState::Exited {
-        let _prev_task_to_drop = unsafe { ManuallyDrop::into_inner(prev_task.into_arc()) };
+    finish_switch();
+    curr.enable_preempt(true);
+}
+
+/// 处理其它CPU发送到当前CPU的重新调度请求：若请求仍未被处理，则抢占当前任务，重新选择任务运行。
+///
+/// 若当前任务的抢占计数不为0，则该请求留到下一次时钟中断处理。
+pub(crate) fn resched_ipi() {
+    let Some(curr) = try_current() else {
+        return;
+    };
+    if !curr.can_preempt(0) {
+        return;
+    }
+    curr.disable_preempt();
+    if libvsched::take_resched_pending(get_cpu_id()) {
+        curr.set_preempt_pending(true);
+    }
+    // 抢占计数减为0时，会检查并处理抢占请求。
+    curr.enable_preempt(true);
+}
+
+/// 处理当前CPU的时钟中断：推进调度器的状态，并在需要时抢占当前任务。
+///
+/// 若当前任务的抢占计数不为0，则本次时钟中断被忽略，其经过的时间计入下一次时钟中断。
+///
+/// 时钟中断可能在信号处理函数中到来，而定时器事件的回调可能释放任务、分配内存或输出日志，
+/// 因此到期的定时器事件不在时钟中断中处理，而是抢占当前任务，在任务切换后（见[`finish_switch`]）处理。
+pub(crate) fn timer_tick() {
+    let Some(curr) = try_current() else {
+        return;
+    };
+    if !curr.can_preempt(0) {
+        return;
+    }
+    curr.disable_preempt();
+    let now = current_time().as_nanos() as u64;
+    let elapsed = now.saturating_sub(LAST_TICK[get_cpu_id()].swap(now, Ordering::AcqRel));
+    // 处理未能在`resched_ipi`中处理的重新调度请求。
+    let resched = libvsched::take_resched_pending(get_cpu_id());
+    let expired = next_deadline().is_some_and(|deadline| deadline.as_nanos() as u64 <= now);
+    libvsched::update_clock(get_cpu_id(), now as usize);
+    if libvsched::task_tick(get_cpu_id(), &ext_to_base(curr.clone()), elapsed as usize)
+        || resched
+        || expired
+    {
+        curr.set_preempt_pending(true);
+    }
+    // 抢占计数减为0时，会检查并处理抢占请求。
+    curr.enable_preempt(true);
+}
+
+/// 若当前任务需要被抢占且允许抢占，则抢占当前任务。
+///
+/// 当前任务的抢占计数减为0时，由`base_task::TaskInner::enable_preempt`调用。
+/// 协程只会在poll的过程中被抢占，此时其栈与上下文的保存方式与线程相同。
+#[unsafe(no_mangle)]
+pub extern "C" fn current_check_preempt_pending() {
+    let Some(curr) = try_current() else {
+        return;
+    };
+    if curr.need_resched() && curr.can_preempt(0) && !curr.is_idle() {
+        curr.disable_preempt();
+        log::trace!("task preempted: {}", curr.id_name());
+        update_clock(get_cpu_id());
+        // 此处的`libvsched::preempt_current`之后为任务的恢复点之一。
+        libvsched::preempt_current(get_cpu_id());
+        finish_switch();
+        curr.enable_preempt(false);
     }
 }
 
@@ -135,10 +459,19 @@ impl Future for YieldFuture {
             let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
             log::trace!("task yield: {}", curr.id_name());
             assert!(curr.is_running());
-            if libvsched::yield_f(get_cpu_id()) {
-                Poll::Pending
-            } else {
-                Poll::Ready(())
+            // 切换出的协程的抢占计数在其恢复运行时由`coroutine_schedule`减少。
+            curr.disable_preempt();
+            update_clock(get_cpu_id());
+            // 若就绪队列已满，当前任务无法让出，继续运行。
+            match libvsched::yield_f(get_cpu_id()) {
+                SchedStatus::Resched => Poll::Pending,
+                SchedStatus::Ok
+                | SchedStatus::RunQueueFull
+                | SchedStatus::RemoteResched
+                | SchedStatus::NotAllowed => {
+                    curr.enable_preempt(true);
+                    Poll::Ready(())
+                }
             }
         } else {
             Poll::Ready(())
@@ -189,11 +522,14 @@ impl Future for ExitFuture {
         log::debug!("task exit: {}, exit_code={}", curr.id_name(), exit_code);
         assert!(curr.is_running(), "task is not running: {:?}", curr.state());
         assert!(!curr.is_idle());
+        curr.disable_preempt();
         curr.set_state(TaskState::Exited);
+        release_deadline_reservation(&curr);
 
         // Notify the joiner task.
         curr.notify_exit(exit_code);
 
+        update_clock(get_cpu_id());
         assert!(libvsched::resched_f(get_cpu_id()));
         Poll::Pending
     }
@@ -211,36 +547,74 @@ impl Future for ExitFuture {
 ///     3. When polling this future, the current task is not the idle task.
 ///     4. The lock of the wait queue will be released explicitly after current task is pushed into it.
 ///
+/// If the future is created by `with_condition`, the `condition` is checked
+/// while holding the lock of the wait queue, and the current task is not blocked
+/// if it is true. So a notification between checking the condition and blocking
+/// will not be lost.
+///
 /// SAFETY:
 /// as the same as the `YieldFuture`. Due to the `WaitQueueGuard` is not implemented
 /// the `Send` trait, this future must hold the reference about the `WaitQueue` instead
 /// of the `WaitQueueGuard`.
-pub(crate) struct BlockedReschedFuture<'a> {
+pub(crate) struct BlockedReschedFuture<'a, F = fn() -> bool> {
     wq: &'a WaitQueue,
+    condition: F,
+    deadline: Option<Duration>,
     flag: bool,
 }
 
 impl<'a> BlockedReschedFuture<'a> {
     pub fn new(wq: &'a WaitQueue) -> Self {
-        Self { wq, flag: false }
+        Self::with_condition(wq, || false)
+    }
+
+    /// Also sets a timer event to wake up the task at the `deadline`, after the
+    /// task is put into the wait queue.
+    pub fn with_deadline(wq: &'a WaitQueue, deadline: Duration) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..Self::new(wq)
+        }
+    }
+}
+
+impl<'a, F: Fn() -> bool> BlockedReschedFuture<'a, F> {
+    pub fn with_condition(wq: &'a WaitQueue, condition: F) -> Self {
+        Self {
+            wq,
+            condition,
+            deadline: None,
+            flag: false,
+        }
     }
 }
 
-impl<'a> Unpin for BlockedReschedFuture<'a> {}
+impl<'a, F> Unpin for BlockedReschedFuture<'a, F> {}
 
-impl<'a> Future for BlockedReschedFuture<'a> {
+impl<'a, F: Fn() -> bool> Future for BlockedReschedFuture<'a, F> {
     type Output = ();
     fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let Self { wq, flag } = self.get_mut();
+        let Self {
+            wq,
+            condition,
+            deadline,
+            flag,
+        } = self.get_mut();
         if !(*flag) {
             *flag = !*flag;
-            let mut wq_guard = wq.queue.lock();
             let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+            curr.disable_preempt();
+            let mut wq_guard = wq.queue.lock();
+            if condition() {
+                drop(wq_guard);
+                curr.enable_preempt(true);
+                return Poll::Ready(());
+            }
             assert!(curr.is_running());
             assert!(!curr.is_idle());
             // we must not block current task with preemption disabled.
             // Current expected preempt count is 2.
-            // 1 for `NoPreemptIrqSave`, 1 for wait queue's `SpinNoIrq`.
+            // 1 for the task switching, 1 for wait queue's `SpinNoPreempt`.
             #[cfg(feature = "preempt")]
             assert!(curr.can_preempt(2));
 
@@ -249,7 +623,13 @@ impl<'a> Future for BlockedReschedFuture<'a> {
             curr.set_state(TaskState::Blocked);
             curr.set_in_wait_queue(true);
 
-            wq_guard.push_back(curr.clone());
+            wq_guard.push_back(Waiter::Task(curr.clone()));
+            // Set the timer after the task is blocked and in the wait queue, as
+            // `blocked_resched()` does, so that the timer can always wake it up,
+            // and the wakers always see it when taking the task.
+            if let Some(deadline) = deadline {
+                set_wait_timeout(*deadline, &curr, wq);
+            }
             // Drop the lock of wait queue explictly.
             drop(wq_guard);
 
@@ -258,12 +638,14 @@ impl<'a> Future for BlockedReschedFuture<'a> {
             // see `unblock_task()` for details.
 
             log::debug!("task block: {}", curr.id_name());
+            update_clock(get_cpu_id());
             // assert!(libvsched::resched_f(get_cpu_id()));
             if libvsched::resched_f(get_cpu_id()) {
                 // resched is needed
                 Poll::Pending
             } else {
                 // resched is not needed, which means the task has been unblocked and is ready to run.
+                curr.enable_preempt(true);
                 Poll::Ready(())
             }
             // Poll::Pending
@@ -273,6 +655,69 @@ impl<'a> Future for BlockedReschedFuture<'a> {
     }
 }
 
-impl<'a> Drop for BlockedReschedFuture<'a> {
+impl<'a, F> Drop for BlockedReschedFuture<'a, F> {
+    fn drop(&mut self) {}
+}
+
+/// The `SleepUntilFuture` used when the current coroutine task sleeps until
+/// the `deadline`.
+///
+/// When polling this future, the current task will be marked as `Blocked`,
+/// and a timer event will be set in the timer list of this CPU to wake it up
+/// at the `deadline`. It returns `Poll::Ready` at once if the `deadline` has
+/// passed.
+///
+/// SAFETY: as the same as the `YieldFuture`.
+pub(crate) struct SleepUntilFuture {
+    deadline: Duration,
+    flag: bool,
+}
+
+impl SleepUntilFuture {
+    pub(crate) fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            flag: false,
+        }
+    }
+}
+
+impl Unpin for SleepUntilFuture {}
+
+impl Future for SleepUntilFuture {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Self { deadline, flag } = self.get_mut();
+        if !(*flag) {
+            *flag = !*flag;
+            if current_time() >= *deadline {
+                return Poll::Ready(());
+            }
+            let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+            assert!(curr.is_running());
+            assert!(!curr.is_idle());
+
+            // Mark the task as blocked before setting the timer, so that the
+            // timer event can wake it up.
+            curr.disable_preempt();
+            curr.set_state(TaskState::Blocked);
+            set_alarm_wakeup(*deadline, &curr);
+
+            log::debug!("task sleep: {}, deadline={:?}", curr.id_name(), deadline);
+            update_clock(get_cpu_id());
+            if libvsched::resched_f(get_cpu_id()) {
+                Poll::Pending
+            } else {
+                // The task has been woken up by the timer before switching out.
+                curr.enable_preempt(true);
+                Poll::Ready(())
+            }
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl Drop for SleepUntilFuture {
     fn drop(&mut self) {}
 }
@@ -0,0 +1,42 @@
+//! 可在线程和协程中使用的同步原语。
+//!
+//! 各同步原语均基于[`WaitQueue`](crate::wait_queue::WaitQueue)实现：获取失败时，当前任务阻塞在其等待队列中，
+//! 而不是自旋等待。每个阻塞操作都有线程版本（如[`Mutex::lock`]）和协程版本（如[`Mutex::lock_f`]）。
+//!
+//! 各同步原语的守卫不会关闭抢占，因此可以在持有守卫时让出（如`yield_now_f().await`）或阻塞。
+//!
+//! [`mpsc`]和[`oneshot`]模块提供了在线程和协程之间传递数据的通道。
+//!
+//! [`Mutex`]支持优先级继承：持有者的优先级被提升至其等待者中最高的优先级，直到解锁。
+
+mod barrier;
+mod condvar;
+mod event;
+mod latch;
+pub mod mpsc;
+mod mutex;
+pub mod oneshot;
+pub(crate) mod pi;
+mod rwlock;
+mod semaphore;
+
+pub use barrier::{Barrier, BarrierWaitResult};
+pub use condvar::Condvar;
+pub use event::Event;
+pub use latch::Latch;
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::Semaphore;
+
+use crate::{
+    interface::get_cpu_id,
+    task_inner_ext::{TaskRef, base_to_ext},
+};
+
+/// 获取当前任务，用于记录锁的持有者。
+///
+/// 任务ID从1开始分配，因此锁可以用0表示未被持有。
+#[inline]
+fn current() -> TaskRef {
+    unsafe { base_to_ext(libvsched::current(get_cpu_id())) }
+}
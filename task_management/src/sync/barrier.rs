@@ -0,0 +1,83 @@
+//! 屏障的实现。
+
+use crate::{sched::SpinNoPreempt, wait_queue::WaitQueue};
+
+/// 屏障，使`n`个任务互相等待，直到所有任务都到达屏障后再一起继续运行。
+///
+/// 屏障可以重复使用：所有任务到达后，屏障进入下一轮。
+pub struct Barrier {
+    wq: WaitQueue,
+    state: SpinNoPreempt<BarrierState>,
+    n: usize,
+}
+
+struct BarrierState {
+    /// 本轮已到达屏障的任务数量。
+    count: usize,
+    /// 屏障的轮数，每轮的所有任务到达后增加。
+    generation: usize,
+}
+
+/// [`Barrier::wait`]的返回值，用于从等待的任务中选出一个领导者。
+#[derive(Debug)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// 返回当前任务是否为本轮的领导者，即最后一个到达屏障的任务。
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// 创建一个等待`n`个任务的屏障。`n`为0时与`n`为1相同，任务不会阻塞。
+    pub const fn new(n: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            state: SpinNoPreempt::new(BarrierState {
+                count: 0,
+                generation: 0,
+            }),
+            n,
+        }
+    }
+
+    /// 使当前任务到达屏障。若当前任务是本轮最后一个到达的任务，则唤醒本轮的所有任务，
+    /// 否则返回本轮的轮数，当前任务需要等待轮数改变。
+    fn arrive(&self) -> Result<BarrierWaitResult, usize> {
+        let mut state = self.state.lock();
+        state.count += 1;
+        if state.count < self.n {
+            return Err(state.generation);
+        }
+        state.count = 0;
+        state.generation = state.generation.wrapping_add(1);
+        drop(state);
+        self.wq.notify_all(true);
+        Ok(BarrierWaitResult(true))
+    }
+
+    /// 返回本轮是否已经结束。
+    fn passed(&self, generation: usize) -> bool {
+        self.state.lock().generation != generation
+    }
+
+    /// 阻塞当前线程，直到本轮的所有任务都到达屏障。
+    pub fn wait(&self) -> BarrierWaitResult {
+        self.arrive().unwrap_or_else(|generation| {
+            self.wq.wait_until(|| self.passed(generation));
+            BarrierWaitResult(false)
+        })
+    }
+
+    /// 阻塞当前协程，直到本轮的所有任务都到达屏障。
+    pub async fn wait_f(&self) -> BarrierWaitResult {
+        match self.arrive() {
+            Ok(result) => result,
+            Err(generation) => {
+                self.wq.wait_until_f(|| self.passed(generation)).await;
+                BarrierWaitResult(false)
+            }
+        }
+    }
+}
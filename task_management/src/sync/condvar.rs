@@ -0,0 +1,102 @@
+//! 条件变量的实现。
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::MutexGuard;
+use crate::wait_queue::WaitQueue;
+
+/// 条件变量，与[`Mutex`](super::Mutex)配合使用，阻塞任务直到某个条件成立。
+///
+/// 与标准库相同，等待的任务可能被虚假唤醒，因此应在循环中检查条件，
+/// 或使用[`wait_while`](Condvar::wait_while)等方法。
+pub struct Condvar {
+    wq: WaitQueue,
+    /// 通知的序号，每次通知时增加。
+    ///
+    /// 等待的任务在释放锁前读取序号，并在等待队列的锁中检查序号是否改变，
+    /// 使释放锁与阻塞之间的通知不会丢失。
+    seq: AtomicUsize,
+}
+
+impl Condvar {
+    /// Creates a new condition variable which is ready to be waited on and notified.
+    pub const fn new() -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            seq: AtomicUsize::new(0),
+        }
+    }
+
+    /// 释放`guard`对应的锁，并阻塞当前线程，直到被通知后重新获取锁。
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex();
+        let seq = self.seq.load(Ordering::Acquire);
+        drop(guard);
+        self.wq
+            .wait_until(|| self.seq.load(Ordering::Acquire) != seq);
+        mutex.lock()
+    }
+
+    /// 释放`guard`对应的锁，并阻塞当前协程，直到被通知后重新获取锁。
+    pub async fn wait_f<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex();
+        let seq = self.seq.load(Ordering::Acquire);
+        drop(guard);
+        self.wq
+            .wait_until_f(|| self.seq.load(Ordering::Acquire) != seq)
+            .await;
+        mutex.lock_f().await
+    }
+
+    /// 阻塞当前线程，直到`condition`返回`false`。
+    ///
+    /// `condition`在持有锁时被调用，返回后仍持有锁。
+    pub fn wait_while<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// 阻塞当前协程，直到`condition`返回`false`。
+    ///
+    /// `condition`在持有锁时被调用，返回后仍持有锁。
+    pub async fn wait_while_f<'a, T: ?Sized, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait_f(guard).await;
+        }
+        guard
+    }
+
+    /// 唤醒一个等待在该条件变量上的任务。
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+
+    /// 唤醒所有等待在该条件变量上的任务。
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,80 @@
+//! 事件的实现。
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::wait_queue::WaitQueue;
+
+/// 事件，等待的任务阻塞在事件的等待队列中，直到事件被设置。
+///
+/// - 手动重置的事件被设置后，唤醒所有等待的任务，并保持设置状态，直到被[`reset`](Event::reset)。
+/// - 自动重置的事件被设置后，只唤醒一个等待的任务，该任务在返回时将事件重置。
+pub struct Event {
+    wq: WaitQueue,
+    signaled: AtomicBool,
+    auto_reset: bool,
+}
+
+impl Event {
+    /// 创建一个手动重置的事件，`signaled`为其初始状态。
+    pub const fn new_manual_reset(signaled: bool) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            signaled: AtomicBool::new(signaled),
+            auto_reset: false,
+        }
+    }
+
+    /// 创建一个自动重置的事件，`signaled`为其初始状态。
+    pub const fn new_auto_reset(signaled: bool) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            signaled: AtomicBool::new(signaled),
+            auto_reset: true,
+        }
+    }
+
+    /// 返回事件是否处于设置状态。
+    pub fn is_set(&self) -> bool {
+        self.signaled.load(Ordering::Acquire)
+    }
+
+    /// 设置事件，并唤醒等待的任务。
+    pub fn set(&self) {
+        self.signaled.store(true, Ordering::Release);
+        if self.auto_reset {
+            self.wq.notify_one(true);
+        } else {
+            self.wq.notify_all(true);
+        }
+    }
+
+    /// 重置事件。
+    pub fn reset(&self) {
+        self.signaled.store(false, Ordering::Release);
+    }
+
+    /// 若事件处于设置状态，则返回`true`；对于自动重置的事件，同时将其重置。
+    pub fn try_wait(&self) -> bool {
+        if self.auto_reset {
+            self.signaled
+                .compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        } else {
+            self.is_set()
+        }
+    }
+
+    /// 阻塞当前线程，直到事件被设置。
+    pub fn wait(&self) {
+        while !self.try_wait() {
+            self.wq.wait_until(|| self.is_set());
+        }
+    }
+
+    /// 阻塞当前协程，直到事件被设置。
+    pub async fn wait_f(&self) {
+        while !self.try_wait() {
+            self.wq.wait_until_f(|| self.is_set()).await;
+        }
+    }
+}
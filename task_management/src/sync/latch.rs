@@ -0,0 +1,55 @@
+//! 倒计数门闩的实现。
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::wait_queue::WaitQueue;
+
+/// 倒计数门闩，等待的任务阻塞在门闩的等待队列中，直到计数减为0。
+///
+/// 计数减为0后，门闩保持打开，之后的等待不会阻塞。
+pub struct Latch {
+    wq: WaitQueue,
+    count: AtomicUsize,
+}
+
+impl Latch {
+    /// 创建一个计数为`count`的门闩。
+    pub const fn new(count: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            count: AtomicUsize::new(count),
+        }
+    }
+
+    /// 返回当前的计数。
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// 将计数减1，计数减为0时唤醒所有等待的任务。计数已为0时不做任何操作。
+    pub fn count_down(&self) {
+        let prev = self
+            .count
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |count| {
+                count.checked_sub(1)
+            });
+        if prev == Ok(1) {
+            self.wq.notify_all(true);
+        }
+    }
+
+    /// 返回计数是否已减为0。
+    pub fn try_wait(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// 阻塞当前线程，直到计数减为0。
+    pub fn wait(&self) {
+        self.wq.wait_until(|| self.try_wait());
+    }
+
+    /// 阻塞当前协程，直到计数减为0。
+    pub async fn wait_f(&self) {
+        self.wq.wait_until_f(|| self.try_wait()).await;
+    }
+}
@@ -0,0 +1,273 @@
+//! 多生产者、单消费者的通道，可在线程和协程之间传递数据。
+//!
+//! 线程使用[`Sender::send`]和[`Receiver::recv`]阻塞地发送和接收数据，
+//! 协程使用[`Sender::send_f`]和[`Receiver::recv_f`]。通道两端可以分别是线程或协程。
+//!
+//! [`channel`]创建无界通道，发送总是立即完成；[`bounded`]创建有界通道，
+//! 通道已满时发送者阻塞在等待队列中，直到接收者取出数据。
+
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::{sched::SpinNoPreempt, wait_queue::WaitQueue};
+
+/// 通道两端共享的数据。
+struct Shared<T> {
+    queue: SpinNoPreempt<VecDeque<T>>,
+    /// 通道的容量，为`None`时表示无界通道。
+    cap: Option<usize>,
+    /// 存活的发送者数量。
+    senders: AtomicUsize,
+    /// 接收者是否存活。
+    receiver_alive: AtomicBool,
+    /// 等待数据的接收者。
+    recv_wq: WaitQueue,
+    /// 等待空位的发送者，只用于有界通道。
+    send_wq: WaitQueue,
+}
+
+impl<T> Shared<T> {
+    fn is_full(&self) -> bool {
+        self.cap.is_some_and(|cap| self.queue.lock().len() >= cap)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+
+    fn is_disconnected(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0
+    }
+
+    /// 发送者等待的条件：通道有空位，或接收者已被释放。
+    fn can_send(&self) -> bool {
+        !self.is_full() || !self.receiver_alive.load(Ordering::Acquire)
+    }
+
+    /// 接收者等待的条件：通道中有数据，或所有发送者都已被释放。
+    fn can_recv(&self) -> bool {
+        !self.is_empty() || self.is_disconnected()
+    }
+}
+
+/// 通道的发送端，可以被克隆。
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 通道的接收端。
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 接收者已被释放，发送失败，返回未发送的数据。
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct SendError<T>(pub T);
+
+/// [`Sender::try_send`]的错误。
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum TrySendError<T> {
+    /// 有界通道已满。
+    Full(T),
+    /// 接收者已被释放。
+    Disconnected(T),
+}
+
+/// 所有发送者都已被释放，且通道中没有数据，接收失败。
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct RecvError;
+
+/// [`Receiver::try_recv`]的错误。
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TryRecvError {
+    /// 通道中没有数据。
+    Empty,
+    /// 所有发送者都已被释放，且通道中没有数据。
+    Disconnected,
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => "Full(..)".fmt(f),
+            TrySendError::Disconnected(_) => "Disconnected(..)".fmt(f),
+        }
+    }
+}
+
+/// 创建一个无界通道，返回其发送端和接收端。
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    new_channel(None)
+}
+
+/// 创建一个容量为`cap`的有界通道，返回其发送端和接收端。
+///
+/// # Panics
+///
+/// `cap`为0时panic。
+pub fn bounded<T>(cap: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(
+        cap > 0,
+        "the capacity of a bounded channel must be positive"
+    );
+    new_channel(Some(cap))
+}
+
+fn new_channel<T>(cap: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: SpinNoPreempt::new(match cap {
+            Some(cap) => VecDeque::with_capacity(cap),
+            None => VecDeque::new(),
+        }),
+        cap,
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+        recv_wq: WaitQueue::new(),
+        send_wq: WaitQueue::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+impl<T> Sender<T> {
+    /// 尝试发送数据，通道已满或接收者已被释放时失败。
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let shared = &self.shared;
+        if !shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(TrySendError::Disconnected(value));
+        }
+        {
+            let mut queue = shared.queue.lock();
+            if shared.cap.is_some_and(|cap| queue.len() >= cap) {
+                return Err(TrySendError::Full(value));
+            }
+            queue.push_back(value);
+        }
+        shared.recv_wq.notify_one(true);
+        Ok(())
+    }
+
+    /// 发送数据。有界通道已满时，当前线程阻塞，直到通道有空位。
+    ///
+    /// 接收者已被释放时失败。
+    pub fn send(&self, mut value: T) -> Result<(), SendError<T>> {
+        loop {
+            match self.try_send(value) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(v)) => value = v,
+                Err(TrySendError::Disconnected(v)) => return Err(SendError(v)),
+            }
+            self.shared.send_wq.wait_until(|| self.shared.can_send());
+        }
+    }
+
+    /// 发送数据。有界通道已满时，当前协程阻塞，直到通道有空位。
+    ///
+    /// 接收者已被释放时失败。
+    pub async fn send_f(&self, mut value: T) -> Result<(), SendError<T>> {
+        loop {
+            match self.try_send(value) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(v)) => value = v,
+                Err(TrySendError::Disconnected(v)) => return Err(SendError(v)),
+            }
+            self.shared
+                .send_wq
+                .wait_until_f(|| self.shared.can_send())
+                .await;
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        // 最后一个发送者被释放时，唤醒接收者，使其返回错误。
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.recv_wq.notify_all(true);
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// 尝试接收数据，通道中没有数据时失败。
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let shared = &self.shared;
+        let value = shared.queue.lock().pop_front();
+        match value {
+            Some(value) => {
+                if shared.cap.is_some() {
+                    shared.send_wq.notify_one(true);
+                }
+                Ok(value)
+            }
+            // 检查发送者前再检查一次队列，避免丢失最后一个发送者释放前发送的数据。
+            None if shared.is_disconnected() => shared
+                .queue
+                .lock()
+                .pop_front()
+                .ok_or(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// 接收数据。通道中没有数据时，当前线程阻塞，直到有数据到达。
+    ///
+    /// 所有发送者都已被释放且通道中没有数据时失败。
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+            }
+            self.shared.recv_wq.wait_until(|| self.shared.can_recv());
+        }
+    }
+
+    /// 接收数据。通道中没有数据时，当前协程阻塞，直到有数据到达。
+    ///
+    /// 所有发送者都已被释放且通道中没有数据时失败。
+    pub async fn recv_f(&self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+            }
+            self.shared
+                .recv_wq
+                .wait_until_f(|| self.shared.can_recv())
+                .await;
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        // 唤醒阻塞的发送者，使其返回错误。
+        self.shared.receiver_alive.store(false, Ordering::Release);
+        self.shared.send_wq.notify_all(true);
+    }
+}
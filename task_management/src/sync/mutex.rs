@@ -0,0 +1,264 @@
+//! 互斥锁的实现。
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{current, pi};
+use crate::{
+    sched::SpinNoPreempt,
+    task_inner_ext::TaskRef,
+    wait_queue::{WaitQueue, Waiter},
+};
+
+/// 互斥锁，获取失败的任务阻塞在锁的等待队列中。
+///
+/// 同一任务重复获取同一把锁会导致panic。
+///
+/// 互斥锁支持优先级继承：任务阻塞在锁上时，若其优先级高于持有者且两者调度类别相同，
+/// 则持有者继承其优先级，直到持有者解锁。
+pub struct Mutex<T: ?Sized> {
+    wq: WaitQueue,
+    /// 持有者的任务ID，为0时表示锁未被持有。
+    owner_id: AtomicU64,
+    /// 持有者的任务引用，用于优先级继承。
+    ///
+    /// 该字段只在持有等待队列的锁时修改，使等待者在阻塞前总能看到持有者，
+    /// 或者持有者在获取锁后总能看到已阻塞的等待者。
+    owner: SpinNoPreempt<Option<TaskRef>>,
+    data: UnsafeCell<T>,
+}
+
+/// 互斥锁的守卫，在被释放时解锁。
+///
+/// 守卫可以在协程中跨越`await`持有。
+pub struct MutexGuard<'a, T: ?Sized + 'a> {
+    lock: &'a Mutex<T>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Creates a new [`Mutex`] wrapping the supplied data.
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            owner_id: AtomicU64::new(0),
+            owner: SpinNoPreempt::new(None),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`Mutex`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Returns `true` if the lock is currently held.
+    ///
+    /// This function provides no synchronization guarantees and so its result should be considered 'out of date'
+    /// the instant it is called. Do not use it for synchronization purposes. However, it may be useful as a heuristic.
+    #[inline(always)]
+    pub fn is_locked(&self) -> bool {
+        self.owner_id.load(Ordering::Relaxed) != 0
+    }
+
+    /// 尝试以当前任务的ID获取锁。失败时返回当前持有者的ID。
+    #[inline]
+    fn try_acquire(&self, current_id: u64) -> Result<(), u64> {
+        match self.owner_id.compare_exchange_weak(
+            0,
+            current_id,
+            Ordering::Acquire,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => Ok(()),
+            Err(owner_id) => {
+                assert_ne!(
+                    owner_id, current_id,
+                    "Task({}) tried to acquire mutex it already owns.",
+                    current_id
+                );
+                Err(owner_id)
+            }
+        }
+    }
+
+    /// 以锁的地址作为其在优先级继承中的标识。
+    #[inline]
+    fn key(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
+
+    /// 等待者的阻塞条件，在持有等待队列的锁时被调用。
+    ///
+    /// 锁仍被持有时，使持有者继承等待者`waiter`的优先级。
+    fn unlocked_or_boost(&self, waiter: &TaskRef) -> bool {
+        if !self.is_locked() {
+            return true;
+        }
+        if let Some(owner) = self.owner.lock().as_ref() {
+            pi::boost(owner, self.key(), waiter);
+        }
+        false
+    }
+
+    /// 记录当前任务`curr`为持有者，并继承已阻塞在锁上的等待者的优先级。
+    fn set_owner(&self, curr: TaskRef) -> MutexGuard<'_, T> {
+        let wq = self.wq.queue.lock();
+        for waiter in wq.iter() {
+            // 协程等待者没有可提升的任务
+            if let Waiter::Task(waiter) = waiter {
+                pi::boost(&curr, self.key(), waiter);
+            }
+        }
+        *self.owner.lock() = Some(curr);
+        drop(wq);
+        MutexGuard {
+            lock: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Locks the [`Mutex`] and returns a guard that permits access to the inner data.
+    ///
+    /// 若锁已被其它任务持有，则当前线程阻塞，直到获取锁为止。
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        let curr = current();
+        while self.try_acquire(curr.id().as_u64()).is_err() {
+            // Wait until the lock looks unlocked before retrying
+            self.wq.wait_until(|| self.unlocked_or_boost(&curr));
+        }
+        self.set_owner(curr)
+    }
+
+    /// Locks the [`Mutex`] and returns a guard that permits access to the inner data.
+    ///
+    /// 若锁已被其它任务持有，则当前协程阻塞，直到获取锁为止。
+    pub async fn lock_f(&self) -> MutexGuard<'_, T> {
+        let curr = current();
+        while self.try_acquire(curr.id().as_u64()).is_err() {
+            // Wait until the lock looks unlocked before retrying
+            self.wq.wait_until_f(|| self.unlocked_or_boost(&curr)).await;
+        }
+        self.set_owner(curr)
+    }
+
+    /// Try to lock this [`Mutex`], returning a lock guard if successful.
+    #[inline(always)]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let curr = current();
+        // The reason for using a strong compare_exchange is explained here:
+        // https://github.com/Amanieu/parking_lot/pull/207#issuecomment-575869107
+        self.owner_id
+            .compare_exchange(0, curr.id().as_u64(), Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| self.set_owner(curr))
+    }
+
+    /// Force unlock the [`Mutex`].
+    ///
+    /// # Safety
+    ///
+    /// This is *extremely* unsafe if the lock is not held by the current
+    /// task. However, this can be useful in some instances for exposing
+    /// the lock to FFI that doesn’t know how to deal with RAII.
+    pub unsafe fn force_unlock(&self) {
+        let curr = current();
+        // 先清除持有者，使等待者不再提升当前任务的优先级，再撤销该锁带来的提升。
+        let owner = {
+            let _wq = self.wq.queue.lock();
+            self.owner.lock().take()
+        };
+        if let Some(owner) = owner {
+            pi::unboost(&owner, self.key());
+        }
+        let owner_id = self.owner_id.swap(0, Ordering::Release);
+        assert_eq!(
+            owner_id,
+            curr.id().as_u64(),
+            "Task({}) tried to release mutex it doesn't own",
+            curr.id().as_u64()
+        );
+        // 唤醒一个等待者，由其重新竞争锁。
+        self.wq.notify_one(true);
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the [`Mutex`] mutably, and a mutable reference is guaranteed to be exclusive in
+    /// Rust, no actual locking needs to take place -- the mutable borrow statically guarantees no locks exist. As
+    /// such, this is a 'zero-cost' operation.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        // We know statically that there are no other references to `self`, so
+        // there's no need to lock the inner mutex.
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "Mutex {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "Mutex {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    /// 返回守卫所属的锁，用于[`Condvar`](super::Condvar)重新获取锁。
+    #[inline]
+    pub(crate) fn mutex(&self) -> &'a Mutex<T> {
+        self.lock
+    }
+}
+
+impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        // We know statically that only we are referencing data
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        // We know statically that only we are referencing data
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    /// The dropping of the [`MutexGuard`] will release the lock it was created from.
+    fn drop(&mut self) {
+        unsafe { self.lock.force_unlock() }
+    }
+}
@@ -0,0 +1,118 @@
+//! 只传递一次数据的通道，可在线程和协程之间传递数据。
+//!
+//! 发送总是立即完成；线程使用[`Receiver::recv`]阻塞地接收数据，协程使用[`Receiver::recv_f`]。
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub use super::mpsc::{RecvError, TryRecvError};
+use crate::{sched::SpinNoPreempt, wait_queue::WaitQueue};
+
+/// 通道两端共享的数据。
+struct Shared<T> {
+    value: SpinNoPreempt<Option<T>>,
+    /// 发送者是否已发送数据或已被释放。
+    complete: AtomicBool,
+    /// 接收者是否存活。
+    receiver_alive: AtomicBool,
+    wq: WaitQueue,
+}
+
+impl<T> Shared<T> {
+    fn complete(&self) {
+        self.complete.store(true, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+
+    fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Acquire)
+    }
+}
+
+/// 通道的发送端。
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 通道的接收端。
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 创建一个只传递一次数据的通道，返回其发送端和接收端。
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        value: SpinNoPreempt::new(None),
+        complete: AtomicBool::new(false),
+        receiver_alive: AtomicBool::new(true),
+        wq: WaitQueue::new(),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+impl<T> Sender<T> {
+    /// 发送数据，并唤醒接收者。接收者已被释放时失败，返回未发送的数据。
+    pub fn send(self, value: T) -> Result<(), T> {
+        if !self.shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(value);
+        }
+        *self.shared.value.lock() = Some(value);
+        // 在`drop`中标记完成并唤醒接收者。
+        Ok(())
+    }
+
+    /// 返回接收者是否已被释放。
+    pub fn is_closed(&self) -> bool {
+        !self.shared.receiver_alive.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.shared.complete();
+    }
+}
+
+impl<T> Receiver<T> {
+    /// 尝试接收数据，发送者还未发送数据时失败。
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if !self.shared.is_complete() {
+            return Err(TryRecvError::Empty);
+        }
+        self.shared
+            .value
+            .lock()
+            .take()
+            .ok_or(TryRecvError::Disconnected)
+    }
+
+    /// 接收数据。发送者还未发送数据时，当前线程阻塞，直到数据到达。
+    ///
+    /// 发送者未发送数据就被释放时失败。
+    pub fn recv(mut self) -> Result<T, RecvError> {
+        self.shared.wq.wait_until(|| self.shared.is_complete());
+        self.try_recv().map_err(|_| RecvError)
+    }
+
+    /// 接收数据。发送者还未发送数据时，当前协程阻塞，直到数据到达。
+    ///
+    /// 发送者未发送数据就被释放时失败。
+    pub async fn recv_f(mut self) -> Result<T, RecvError> {
+        self.shared
+            .wq
+            .wait_until_f(|| self.shared.is_complete())
+            .await;
+        self.try_recv().map_err(|_| RecvError)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,106 @@
+//! 互斥锁的优先级继承。
+//!
+//! 任务持有互斥锁时，若有优先级更高的任务等待该锁，则持有者的优先级被提升至等待者的优先级，
+//! 使持有者不会因为优先级低而迟迟不能释放锁。持有者解锁时撤销该锁带来的提升。
+//!
+//! 只有调度类别相同的任务之间才会继承优先级：普通任务继承nice值（越小越优先），
+//! 实时任务继承实时优先级（越大越优先）。
+
+use alloc::vec::Vec;
+
+use crate::{
+    interface::get_cpu_id,
+    sched::without_preempt,
+    task_inner_ext::{TaskRef, ext_to_base},
+};
+
+/// 任务因持有互斥锁而被提升的优先级。
+pub(crate) struct PiState {
+    /// 被提升前的优先级，没有被提升时为`None`。
+    base: Option<isize>,
+    /// 各互斥锁（以其地址标识）对任务的提升。
+    boosts: Vec<(usize, isize)>,
+}
+
+impl PiState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            base: None,
+            boosts: Vec::new(),
+        }
+    }
+
+    /// 被提升后的优先级，即原始优先级与各提升中最高的优先级。
+    fn effective(&self, rt: bool, base: isize) -> isize {
+        self.boosts
+            .iter()
+            .map(|&(_, prio)| prio)
+            .fold(base, |a, b| if is_higher(rt, b, a) { b } else { a })
+    }
+}
+
+/// 返回在调度类别`rt`中，优先级`a`是否高于`b`。
+#[inline]
+fn is_higher(rt: bool, a: isize, b: isize) -> bool {
+    if rt { a > b } else { a < b }
+}
+
+#[inline]
+fn set_task_priority(task: &TaskRef, prio: isize) -> bool {
+    without_preempt(|| libvsched::set_task_priority(&ext_to_base(task.clone()), prio, get_cpu_id()))
+}
+
+/// 以等待者`waiter`的优先级提升互斥锁`key`的持有者`owner`的优先级。
+///
+/// 若两者的调度类别不同，或`waiter`的优先级不高于`owner`当前的优先级，则不做任何操作。
+pub(crate) fn boost(owner: &TaskRef, key: usize, waiter: &TaskRef) {
+    let rt = owner.rt().is_rt();
+    if waiter.rt().is_rt() != rt {
+        return;
+    }
+    let prio = waiter.priority();
+    let mut pi = owner.pi_state().lock();
+    let current = owner.priority();
+    if !is_higher(rt, prio, current) {
+        return;
+    }
+    pi.base.get_or_insert(current);
+    match pi.boosts.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, boost)) => *boost = prio,
+        None => pi.boosts.push((key, prio)),
+    }
+    log::debug!("task {} inherits priority {}", owner.id_name(), prio);
+    set_task_priority(owner, prio);
+}
+
+/// 撤销互斥锁`key`对任务`owner`的提升，`owner`的优先级恢复为原始优先级与其余提升中最高的优先级。
+pub(crate) fn unboost(owner: &TaskRef, key: usize) {
+    let mut pi = owner.pi_state().lock();
+    let Some(base) = pi.base else {
+        return;
+    };
+    let len = pi.boosts.len();
+    pi.boosts.retain(|&(k, _)| k != key);
+    if pi.boosts.len() == len {
+        return;
+    }
+    let prio = pi.effective(owner.rt().is_rt(), base);
+    if pi.boosts.is_empty() {
+        pi.base = None;
+    }
+    set_task_priority(owner, prio);
+}
+
+/// 设置任务的原始优先级。
+///
+/// 若任务正被提升优先级，则其实际优先级为新的原始优先级与各提升中最高的优先级。
+pub(crate) fn set_priority(task: &TaskRef, prio: isize) -> bool {
+    let mut pi = task.pi_state().lock();
+    match pi.base {
+        None => set_task_priority(task, prio),
+        Some(_) => {
+            pi.base = Some(prio);
+            set_task_priority(task, pi.effective(task.rt().is_rt(), prio))
+        }
+    }
+}
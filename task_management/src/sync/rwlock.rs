@@ -0,0 +1,232 @@
+//! 读写锁的实现。
+
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::wait_queue::WaitQueue;
+
+/// 写者持有锁时，`state`的最高位被置位。
+const WRITER: usize = 1 << (usize::BITS - 1);
+
+/// 读写锁，允许多个读者或一个写者同时持有锁。获取失败的任务阻塞在锁的等待队列中。
+///
+/// 读者与写者之间没有优先级，持续到来的读者可能使写者饥饿。
+pub struct RwLock<T: ?Sized> {
+    wq: WaitQueue,
+    /// 最高位表示是否有写者持有锁，其余位为持有锁的读者数量。
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+/// 读写锁的读守卫，在被释放时释放读锁。
+///
+/// 守卫可以在协程中跨越`await`持有。
+pub struct RwLockReadGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    _marker: PhantomData<&'a T>,
+}
+
+/// 读写锁的写守卫，在被释放时释放写锁。
+///
+/// 守卫可以在协程中跨越`await`持有。
+pub struct RwLockWriteGuard<'a, T: ?Sized + 'a> {
+    lock: &'a RwLock<T>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new [`RwLock`] wrapping the supplied data.
+    #[inline(always)]
+    pub const fn new(data: T) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            state: AtomicUsize::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Consumes this [`RwLock`] and unwraps the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Returns `true` if a writer holds the lock.
+    #[inline(always)]
+    pub fn is_write_locked(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & WRITER != 0
+    }
+
+    /// Returns the number of readers holding the lock.
+    #[inline(always)]
+    pub fn reader_count(&self) -> usize {
+        self.state.load(Ordering::Relaxed) & !WRITER
+    }
+
+    /// Try to lock this [`RwLock`] with shared read access, returning a guard if successful.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.load(Ordering::Relaxed);
+        while state & WRITER == 0 {
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(RwLockReadGuard {
+                        lock: self,
+                        _marker: PhantomData,
+                    });
+                }
+                Err(s) => state = s,
+            }
+        }
+        None
+    }
+
+    /// Try to lock this [`RwLock`] with exclusive write access, returning a guard if successful.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| RwLockWriteGuard {
+                lock: self,
+                _marker: PhantomData,
+            })
+    }
+
+    /// Locks this [`RwLock`] with shared read access.
+    ///
+    /// 若写者持有锁，则当前线程阻塞，直到获取读锁为止。
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_read() {
+                return guard;
+            }
+            self.wq.wait_until(|| !self.is_write_locked());
+        }
+    }
+
+    /// Locks this [`RwLock`] with shared read access.
+    ///
+    /// 若写者持有锁，则当前协程阻塞，直到获取读锁为止。
+    pub async fn read_f(&self) -> RwLockReadGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_read() {
+                return guard;
+            }
+            self.wq.wait_until_f(|| !self.is_write_locked()).await;
+        }
+    }
+
+    /// Locks this [`RwLock`] with exclusive write access.
+    ///
+    /// 若其它任务持有锁，则当前线程阻塞，直到获取写锁为止。
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_write() {
+                return guard;
+            }
+            self.wq
+                .wait_until(|| self.state.load(Ordering::Relaxed) == 0);
+        }
+    }
+
+    /// Locks this [`RwLock`] with exclusive write access.
+    ///
+    /// 若其它任务持有锁，则当前协程阻塞，直到获取写锁为止。
+    pub async fn write_f(&self) -> RwLockWriteGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_write() {
+                return guard;
+            }
+            self.wq
+                .wait_until_f(|| self.state.load(Ordering::Relaxed) == 0)
+                .await;
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        // We know statically that there are no other references to `self`, so
+        // there's no need to lock the inner lock.
+        unsafe { &mut *self.data.get() }
+    }
+
+    fn read_unlock(&self) {
+        // 最后一个读者释放锁时，唤醒等待的写者。
+        if self.state.fetch_sub(1, Ordering::Release) == 1 {
+            self.wq.notify_all(true);
+        }
+    }
+
+    fn write_unlock(&self) {
+        self.state.fetch_and(!WRITER, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => write!(f, "RwLock {{ data: ")
+                .and_then(|()| (*guard).fmt(f))
+                .and_then(|()| write!(f, "}}")),
+            None => write!(f, "RwLock {{ <locked> }}"),
+        }
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.read_unlock();
+    }
+}
+
+impl<'a, T: ?Sized> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    #[inline(always)]
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.write_unlock();
+    }
+}
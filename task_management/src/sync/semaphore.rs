@@ -0,0 +1,56 @@
+//! 计数信号量的实现。
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::wait_queue::WaitQueue;
+
+/// 计数信号量，许可不足时获取许可的任务阻塞在信号量的等待队列中。
+pub struct Semaphore {
+    wq: WaitQueue,
+    /// 剩余的许可数量。
+    permits: AtomicUsize,
+}
+
+impl Semaphore {
+    /// 创建一个具有`permits`个许可的信号量。
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            permits: AtomicUsize::new(permits),
+        }
+    }
+
+    /// 返回剩余的许可数量。
+    pub fn available_permits(&self) -> usize {
+        self.permits.load(Ordering::Acquire)
+    }
+
+    /// 尝试获取一个许可，许可不足时返回`false`。
+    pub fn try_acquire(&self) -> bool {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |permits| {
+                permits.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// 获取一个许可。许可不足时，当前线程阻塞，直到获取许可为止。
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            self.wq.wait_until(|| self.available_permits() > 0);
+        }
+    }
+
+    /// 获取一个许可。许可不足时，当前协程阻塞，直到获取许可为止。
+    pub async fn acquire_f(&self) {
+        while !self.try_acquire() {
+            self.wq.wait_until_f(|| self.available_permits() > 0).await;
+        }
+    }
+
+    /// 释放一个许可，并唤醒一个等待许可的任务。
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+}
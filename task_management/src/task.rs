@@ -4,33 +4,36 @@ use core::{
     array,
     mem::{ManuallyDrop, MaybeUninit},
     ops::Deref,
+    sync::atomic::{Ordering, fence},
 };
 
 use crate::{
     interface::get_cpu_id,
-    sched::{exit_f, yield_now},
+    sched::{self, SpinNoPreempt, exit_f, finish_switch, idle_sleep, periodic_balance, yield_now},
     task_inner_ext::{
         ArcTaskRef, AxTask, TaskInner, TaskRef, arcext_to_base, arcext_to_waker,
         arcwaker_to_arcext, base_to_arcext, base_to_ext,
     },
+    timers::check_events,
     wait_queue::WaitQueue,
 };
 use alloc::{
     boxed::Box, collections::vec_deque::VecDeque, string::String, sync::Arc, task::Wake, vec::Vec,
 };
-use base_task::{TaskStack, TaskState};
+use base_task::{SchedStatus, TaskStack, TaskState};
 use config::SMP;
-use kspin::SpinNoIrq;
 
 pub(crate) fn new<F>(entry: F, name: String, stack_size: usize) -> ArcTaskRef
 where
     F: FnOnce() + Send + 'static,
 {
     let t = TaskInner::new(entry, task_entry as usize, name, stack_size);
+    // 新任务的抢占计数为1，在其入口处完成切换后减少。
+    t.disable_preempt();
     Arc::new(AxTask::new(t))
 }
 
-pub(crate) fn new_f<F>(future: F, name: String) -> ArcTaskRef
+pub(crate) fn new_f<F>(future: F, name: String, stack_size: usize) -> ArcTaskRef
 where
     F: Future<Output = ()> + Send + 'static,
 {
@@ -42,7 +45,9 @@ where
         name.clone(),
         alloc_stack_for_coroutine,
         coroutine_schedule,
+        stack_size,
     );
+    t.disable_preempt();
     Arc::new(AxTask::new(t))
 }
 
@@ -53,24 +58,24 @@ pub(crate) fn new_init(name: String) -> ArcTaskRef {
 }
 
 /// 用于idle任务的入口点
+///
+/// 空闲时检查当前CPU的定时器列表，唤醒已到期的任务；
+/// 没有可运行的任务时，通过[`IdleIf`](crate::interface::IdleIf)阻塞当前CPU。
 pub fn run_idle() {
     loop {
+        check_events();
+        periodic_balance();
         yield_now();
+        idle_sleep();
     }
 }
 
 /// 对线程入口函数的包装
 extern "C" fn task_entry() {
-    // 所有任务的恢复点都需要释放上一个任务的Arc引用，并清除其on_cpu标志。
-    //
     // 此处为任务的恢复点之一。
-    let prev_task =
-        unsafe { base_to_ext(libvsched::take_prev_task_and_clear_on_cpu(get_cpu_id())) };
-    if prev_task.state() == TaskState::Exited {
-        let _prev_task_to_drop = unsafe { ManuallyDrop::into_inner(prev_task.into_arc()) };
-    }
-    drop(prev_task);
+    finish_switch();
     let task = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+    task.enable_preempt(true);
     if let Some(entry) = task.entry() {
         unsafe { Box::from_raw(*entry)() };
     }
@@ -78,11 +83,15 @@ extern "C" fn task_entry() {
 }
 
 /// 每个CPU维护一个协程栈池，避免频繁分配和释放栈空间。
-struct PerCPUStackPool([SpinNoIrq<Vec<TaskStack>>; SMP]);
+///
+/// 在poll过程中被抢占的协程会继续占用其栈，因此池中的栈可能暂时少于正在运行的协程数。
+///
+/// 池中的栈大小可以不同，分配时选取与协程所需大小相同的栈。
+struct PerCPUStackPool([SpinNoPreempt<Vec<TaskStack>>; SMP]);
 
 impl PerCPUStackPool {
     pub(crate) const fn new() -> Self {
-        Self([const { SpinNoIrq::new(Vec::new()) }; SMP])
+        Self([const { SpinNoPreempt::new(Vec::new()) }; SMP])
     }
 }
 
@@ -90,7 +99,7 @@ unsafe impl Send for PerCPUStackPool {}
 unsafe impl Sync for PerCPUStackPool {}
 
 impl Deref for PerCPUStackPool {
-    type Target = SpinNoIrq<Vec<TaskStack>>;
+    type Target = SpinNoPreempt<Vec<TaskStack>>;
 
     fn deref(&self) -> &Self::Target {
         &self.0[get_cpu_id()]
@@ -99,15 +108,16 @@ impl Deref for PerCPUStackPool {
 
 static COROUTINE_STACK_POOL: PerCPUStackPool = PerCPUStackPool::new();
 
-/// Alloc a stack for running a coroutine.
-/// If the `COROUTINE_STACK_POOL` is empty,
+/// Alloc a stack of `size` bytes for running a coroutine.
+/// If there is no stack of this size in the `COROUTINE_STACK_POOL`,
 /// it will alloc a new stack on the allocator.
-fn alloc_stack_for_coroutine() -> TaskStack {
+fn alloc_stack_for_coroutine(size: usize) -> TaskStack {
     log::debug!("alloc stack");
-    COROUTINE_STACK_POOL
-        .lock()
-        .pop()
-        .unwrap_or_else(|| TaskStack::alloc(config::TASK_STACK_SIZE))
+    let mut pool = COROUTINE_STACK_POOL.lock();
+    match pool.iter().rposition(|stack| stack.size() == size) {
+        Some(index) => pool.swap_remove(index),
+        None => TaskStack::alloc(size),
+    }
 }
 
 /// Recycle the stack after the coroutine running to a certain stage.
@@ -119,19 +129,19 @@ fn recycle_stack_of_coroutine(stack: TaskStack) {
 /// 协程调度主循环
 fn coroutine_schedule() {
     use core::task::{Context, Waker};
+    // 是否从其它任务切换到了当前协程。
+    // 协程让出后可能再次被选中，此时没有发生切换，不需要完成切换的后续工作。
+    let mut switched = true;
     loop {
-        // 所有任务的恢复点都需要释放上一个任务的Arc引用，并清除其on_cpu标志。
-        //
         // 此处为任务的恢复点之一。
-        let prev_task =
-            unsafe { base_to_ext(libvsched::take_prev_task_and_clear_on_cpu(get_cpu_id())) };
-        if prev_task.state() == TaskState::Exited {
-            let _prev_task_to_drop = unsafe { ManuallyDrop::into_inner(prev_task.into_arc()) };
+        if switched {
+            finish_switch();
         }
-        drop(prev_task);
         // let waker = Waker::noop();
         // let mut cx = Context::from_waker(waker);
         let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+        // 在创建本次poll的Waker前清除唤醒标记，之后的唤醒都会被记录。
+        curr.take_notified();
         let waker = arcext_to_waker(ManuallyDrop::into_inner(curr.into_arc().clone())); // 此处into_arc返回`ManuallyDrop<Arc<AxTask>>`，先clone再into_inner得到`Arc<AxTask>`，因此原有的`Arc<AxTask>`不会被释放。
         let mut cx = Context::from_waker(&waker);
 
@@ -140,7 +150,15 @@ fn coroutine_schedule() {
             .future()
             .as_mut()
             .expect("The task should be a coroutine");
+        // 协程只在poll的过程中可以被抢占。被抢占的协程像线程一样保留其正在使用的栈，
+        // 直到本次poll返回后，其栈才被下一个协程复用或放回`COROUTINE_STACK_POOL`。
+        curr.enable_preempt(true);
         let _res = fut.as_mut().poll(&mut cx);
+        // 协程在poll中被切换出时（见`sched`模块中的各`Future`），其抢占计数为1，且当前任务已经改变；
+        // 否则其抢占计数为0，此处关闭抢占，直到下一个协程开始poll。
+        if curr.can_preempt(0) {
+            curr.disable_preempt();
+        }
         // // 该行要求协程在返回Pending或完全结束时，都需要将state从Running切换到其它状态（Ready, Blocked, Exited）。
         // assert!(!curr.is_running(), "{} is still running", curr.id_name());
 
@@ -152,12 +170,25 @@ fn coroutine_schedule() {
         // - Running: 协程通过`await`一个`Future`而阻塞，因为这些`Future`不会主动维护协程的状态。
         //
         // 对Exited任务的回收下一任务即将运行时进行，因此此处只需特殊处理Running的情况。
+        //
+        // 在Running的情况下，协程可能在poll返回前就已被其它CPU通过Waker唤醒，此时`TaskWaker::wake`
+        // 看到的状态为Running而不会唤醒协程，因此需要通过唤醒标记将本次阻塞转为让出，见`TaskWaker::wake`。
+        switched = true;
         if curr.is_running() {
             // 设置当前任务状态
             curr.set_state(TaskState::Blocked);
-            // 当前任务还未改变，因此在此处调用`libvsched::resched_f`可以正确设置当前任务和上一任务。
-            // 后续代码可以正确处理下一任务和本任务相同的情况，因此此处可以不管`resched_f`的返回值。
-            libvsched::resched_f(get_cpu_id());
+            fence(Ordering::SeqCst);
+            // 当前任务还未改变，因此在此处调用`libvsched::resched_f`或`libvsched::yield_f`
+            // 可以正确设置当前任务和上一任务。
+            sched::update_clock(get_cpu_id());
+            switched = if curr.take_notified()
+                && curr.transition_state(TaskState::Blocked, TaskState::Running)
+            {
+                // 已被唤醒，且唤醒者没有将协程放入就绪队列。
+                libvsched::yield_f(get_cpu_id()) == SchedStatus::Resched
+            } else {
+                libvsched::resched_f(get_cpu_id())
+            };
         }
 
         let prev_task = curr;
@@ -166,10 +197,16 @@ fn coroutine_schedule() {
             .expect("The stack should be taken out after running.");
         let next_task = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
         let next_stack = unsafe { &mut *next_task.kernel_stack() };
-        if next_stack.is_none() && !next_task.is_init() && !next_task.is_idle() {
+        if next_stack.is_none()
+            && !next_task.is_init()
+            && !next_task.is_idle()
+            && next_task.coroutine_stack_size() == stack.size()
+        {
             log::debug!("reuse stack");
             next_stack.replace(stack);
         } else {
+            // 下一协程需要不同大小的栈时，为其分配新栈。
+            next_task.set_kstack();
             unsafe {
                 let prev_ctx_ptr = prev_task.ctx_mut_ptr();
                 let next_ctx_ptr = next_task.ctx_mut_ptr();
@@ -191,12 +228,13 @@ pub struct TaskWaker {
 
 impl Wake for TaskWaker {
     fn wake(self: Arc<Self>) {
-        // 修改任务状态、将任务放入就绪队列
-        libvsched::unblock_task(
-            arcext_to_base(arcwaker_to_arcext(self)),
-            true,
-            get_cpu_id(),
-            get_cpu_id(),
-        );
+        let task = arcwaker_to_arcext(self);
+        // 先标记协程已被唤醒，再检查其状态：若协程仍在poll中（状态为`Running`），
+        // 则由`coroutine_schedule`在poll返回后发现该标记，将其重新放入就绪队列。
+        task.set_notified();
+        fence(Ordering::SeqCst);
+        // 修改任务状态、将任务放入任务的CPU亲和性掩码允许的就绪队列
+        let cpu_id = task.select_run_queue_index();
+        sched::unblock_task(arcext_to_base(task), true, cpu_id);
     }
 }
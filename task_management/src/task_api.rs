@@ -2,28 +2,41 @@
 //!
 //! 本模块不包含任务的阻塞操作，阻塞操作参见[`wait_queue`](crate::wait_queue)模块。
 
+pub use crate::{builder::TaskBuilder, join_handle::JoinHandle};
 use crate::{
-    interface::get_cpu_id,
-    task_inner_ext::{ArcTaskRef, arcext_to_base},
+    interface::{get_cpu_id, send_resched_ipi},
+    sched::{is_vsched_inited, kick_idle_cpus, update_clock, without_preempt},
+    task_inner_ext::{
+        ArcTaskRef, arcext_as_base, arcext_to_base, base_to_arcext, base_to_ext, cpumask_to_bits,
+    },
+};
+use alloc::{boxed::Box, string::String};
+use base_task::SchedStatus;
+pub use base_task::{SchedClass, SchedPolicy, SchedStats};
+use config::{AxCpuMask, SMP};
+use core::{
+    any::{Any, TypeId},
+    time::Duration,
 };
-use alloc::string::String;
 
-/// 在主CPU上初始化调度器。
+/// 在主CPU上初始化调度器，当前CPU的就绪队列使用`policy`调度策略。
 ///
 /// 调用此函数前，应先正确映射好vDSO和vVAR内存区域，并调用[`libvsched::init_vdso_vtable`]函数。
 ///
 /// 调用此函数后，当前执行流也视为一个任务（主任务），并会继续运行当前执行流。
 #[inline]
-pub fn init_vsched() {
-    crate::sched::init_vsched()
+pub fn init_vsched(policy: SchedPolicy) {
+    crate::sched::init_vsched(policy)
 }
 
-/// 在副CPU上初始化调度器。
+/// 在副CPU上初始化调度器，当前CPU的就绪队列使用`policy`调度策略。
+///
+/// 不同CPU可以使用不同的调度策略。
 ///
 /// 调用此函数前，应先正确映射好vDSO和vVAR内存区域，并调用[`libvsched::init_vdso_vtable`]函数。
 #[inline]
-pub fn init_vsched_secondary() {
-    crate::sched::init_vsched_secondary()
+pub fn init_vsched_secondary(policy: SchedPolicy) {
+    crate::sched::init_vsched_secondary(policy)
 }
 
 /// 以`entry`为入口函数创建线程。
@@ -37,21 +50,79 @@ where
     crate::task::new(entry, name, stack_size)
 }
 
-/// 以`future`创建协程。
+/// 以`future`创建协程，协程运行时使用大小为[`config::TASK_STACK_SIZE`]的栈。
 ///
-/// 返回对任务的引用，不运行该任务。
+/// 返回对任务的引用，不运行该任务。需要设置栈大小等参数时，使用[`TaskBuilder`]。
 #[inline]
 pub fn new_f<F>(future: F, name: String) -> ArcTaskRef
 where
     F: Future<Output = ()> + Send + 'static,
 {
-    crate::task::new_f(future, name)
+    crate::task::new_f(future, name, config::TASK_STACK_SIZE)
 }
 
-/// 在当前CPU上运行任务。
+/// 运行任务。
+///
+/// 若当前CPU在任务的CPU亲和性掩码中，则将任务放入当前CPU的就绪队列，否则放入掩码中的其它CPU。
+///
+/// 若就绪队列已满，则返回`Err`并交还该任务。
 #[inline]
-pub fn spawn(task_ref: ArcTaskRef) {
-    libvsched::spawn(get_cpu_id(), arcext_to_base(task_ref));
+pub fn spawn(task_ref: ArcTaskRef) -> Result<(), ArcTaskRef> {
+    let cpu_id = task_ref.select_run_queue_index();
+    let task = arcext_to_base(task_ref);
+    let status = without_preempt(|| {
+        update_clock(cpu_id);
+        libvsched::spawn(cpu_id, task.clone())
+    });
+    match status {
+        SchedStatus::RunQueueFull => Err(unsafe { base_to_arcext(task) }),
+        _ => {
+            kick_idle_cpus();
+            Ok(())
+        }
+    }
+}
+
+/// 以返回`T`的`entry`为入口函数创建线程，并运行该线程。
+///
+/// 与[`spawn`]相同，若当前CPU在任务的CPU亲和性掩码中，则将任务放入当前CPU的就绪队列，
+/// 否则从掩码中轮流选择一个CPU（见[`select_run_queue_index`]）。
+///
+/// 返回任务的句柄，可通过[`JoinHandle::join`]或`.await`获取`entry`的返回值。
+/// 若就绪队列已满，则返回`Err`并交还该任务。
+///
+/// [`select_run_queue_index`]: crate::task_inner_ext::TaskInner::select_run_queue_index
+pub fn spawn_new<F, T>(
+    entry: F,
+    name: String,
+    stack_size: usize,
+) -> Result<JoinHandle<T>, ArcTaskRef>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let handle = JoinHandle::new(entry, name, stack_size);
+    spawn(handle.task().clone())?;
+    Ok(handle)
+}
+
+/// 以输出`T`的`future`创建协程，并运行该协程。
+///
+/// 与[`spawn`]相同，若当前CPU在任务的CPU亲和性掩码中，则将任务放入当前CPU的就绪队列，
+/// 否则从掩码中轮流选择一个CPU（见[`select_run_queue_index`]）。
+///
+/// 返回任务的句柄，可通过[`JoinHandle::join`]或`.await`获取`future`的输出。
+/// 若就绪队列已满，则返回`Err`并交还该任务。
+///
+/// [`select_run_queue_index`]: crate::task_inner_ext::TaskInner::select_run_queue_index
+pub fn spawn_new_f<F, T>(future: F, name: String) -> Result<JoinHandle<T>, ArcTaskRef>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let handle = JoinHandle::new_f(future, name, config::TASK_STACK_SIZE);
+    spawn(handle.task().clone())?;
+    Ok(handle)
 }
 
 /// 以`exit_code`退出当前线程。
@@ -77,3 +148,245 @@ pub fn yield_now() {
 pub async fn yield_now_f() {
     crate::sched::yield_now_f().await
 }
+
+/// 获取当前的单调时间，由[`TimeIf`](crate::interface::TimeIf)接口提供。
+#[inline]
+pub fn current_time() -> Duration {
+    crate::interface::current_time()
+}
+
+/// 使当前线程睡眠`dur`时长。
+#[inline]
+pub fn sleep(dur: Duration) {
+    crate::sched::sleep_until(current_time() + dur)
+}
+
+/// 使当前线程睡眠，直到[`current_time`]达到`deadline`。
+#[inline]
+pub fn sleep_until(deadline: Duration) {
+    crate::sched::sleep_until(deadline)
+}
+
+/// 使当前协程睡眠`dur`时长。
+#[inline]
+pub async fn sleep_f(dur: Duration) {
+    crate::sched::SleepUntilFuture::new(current_time() + dur).await
+}
+
+/// 使当前协程睡眠，直到[`current_time`]达到`deadline`。
+#[inline]
+pub async fn sleep_until_f(deadline: Duration) {
+    crate::sched::SleepUntilFuture::new(deadline).await
+}
+
+/// 设置任意任务的优先级，该任务可以在就绪队列中、阻塞在等待队列中或正在运行。
+///
+/// 普通任务的优先级为nice值（范围为`-20..=19`，仅在[`SchedPolicy::Cfs`]调度策略下有效），
+/// 实时任务的优先级为实时优先级（范围为`0..RT_PRIO_LEVELS`）。
+/// 就绪队列中的任务会被取出，并以新的优先级放回原就绪队列。
+///
+/// 若任务因持有[`Mutex`](crate::sync::Mutex)而继承了其它任务的优先级，则设置的是其原始优先级，
+/// 任务实际的优先级为原始优先级与继承的优先级中较高者。
+///
+/// 返回是否设置成功。
+#[inline]
+pub fn set_priority(task: &ArcTaskRef, prio: isize) -> bool {
+    crate::sync::pi::set_priority(&unsafe { base_to_ext(arcext_as_base(task)) }, prio)
+}
+
+/// 设置任意任务的CPU亲和性掩码，该任务可以在就绪队列中、阻塞在等待队列中或正在运行。
+///
+/// 就绪队列中的任务若位于不允许的CPU上，会被移动到允许的CPU上；
+/// 正在不允许的CPU上运行的任务会被标记为需要调度（若在其它CPU上运行，则通知该CPU），
+/// 在其被切换出时移动到允许的CPU上；阻塞的任务在被唤醒时放入允许的CPU。
+///
+/// 返回是否设置成功；`cpumask`不包含任何CPU，或任务有截止时间预留（只在接纳它的CPU上保证）时返回`false`。
+#[inline]
+pub fn set_cpumask(task: &ArcTaskRef, cpumask: AxCpuMask) -> bool {
+    let bits = cpumask_to_bits(cpumask);
+    let mut resched_cpu_id = get_cpu_id();
+    let status = without_preempt(|| {
+        libvsched::set_task_cpumask(
+            &arcext_as_base(task),
+            bits,
+            get_cpu_id(),
+            &mut resched_cpu_id,
+        )
+    });
+    if status == SchedStatus::RemoteResched {
+        send_resched_ipi(resched_cpu_id);
+    }
+    kick_idle_cpus();
+    status != SchedStatus::NotAllowed
+}
+
+/// 将任意任务迁移到CPU `cpu_id`，该任务可以在就绪队列中、阻塞在等待队列中或正在运行。
+///
+/// 就绪队列中的任务立即被移动到目标CPU的就绪队列；阻塞的任务在被唤醒时放入目标CPU；
+/// 正在运行的任务被标记为需要调度（若在其它CPU上运行，则通知该CPU），在下一个调度点被切换出时放入目标CPU。
+/// 之后，任务仍可能因负载均衡而被移动到其它CPU，如需固定任务所在的CPU，应同时设置其CPU亲和性掩码。
+///
+/// 返回是否迁移成功；若目标CPU不在任务的CPU亲和性掩码中、其调度器未初始化或其就绪队列已满，
+/// 或任务有截止时间预留（只在接纳它的CPU上保证），则返回`false`。
+pub fn migrate(task: &ArcTaskRef, cpu_id: usize) -> bool {
+    if cpu_id >= SMP || !task.cpumask().get(cpu_id) || !is_vsched_inited(cpu_id) {
+        return false;
+    }
+    let mut resched_cpu_id = cpu_id;
+    let status = without_preempt(|| {
+        update_clock(cpu_id);
+        libvsched::migrate(
+            &arcext_as_base(task),
+            cpu_id,
+            get_cpu_id(),
+            &mut resched_cpu_id,
+        )
+    });
+    if status == SchedStatus::RemoteResched {
+        send_resched_ipi(resched_cpu_id);
+    }
+    kick_idle_cpus();
+    matches!(status, SchedStatus::Ok | SchedStatus::RemoteResched)
+}
+
+/// 获取任务的优先级：实时任务返回其实时优先级，普通任务返回其nice值。
+///
+/// 返回的是任务实际的优先级，包括其继承的优先级。
+#[inline]
+pub fn get_priority(task: &ArcTaskRef) -> isize {
+    task.priority()
+}
+
+/// 获取任务的调度统计信息，包括运行时间、在就绪队列中的等待时间、主动与被动切换次数和被唤醒次数。
+///
+/// 时间单位为纳秒。线程与协程的统计方式相同。
+#[inline]
+pub fn task_stats(task: &ArcTaskRef) -> SchedStats {
+    libvsched::task_stats(&arcext_as_base(task))
+}
+
+/// 获取CPU `cpu_id`的调度统计信息，即曾在该CPU上运行或等待的任务（不包括空闲任务）的统计信息之和。
+#[inline]
+pub fn cpu_stats(cpu_id: usize) -> SchedStats {
+    libvsched::cpu_stats(cpu_id)
+}
+
+/// 获取CPU `cpu_id`上时间片轮转任务的时间片长度，单位为纳秒。
+#[inline]
+pub fn time_slice(cpu_id: usize) -> usize {
+    libvsched::time_slice(cpu_id)
+}
+
+/// 设置CPU `cpu_id`上时间片轮转任务（[`SchedPolicy::RoundRobin`]调度策略下的任务和
+/// [`SchedClass::RtRoundRobin`]类别的任务）的时间片长度，单位为纳秒。
+///
+/// 新的时间片长度在任务下次获得时间片时生效。`time_slice`为0时返回`false`。
+#[inline]
+pub fn set_time_slice(cpu_id: usize, time_slice: usize) -> bool {
+    without_preempt(|| libvsched::set_time_slice(time_slice, cpu_id))
+}
+
+/// 设置当前任务的调度类别。
+///
+/// 实时任务总是先于普通任务运行。`prio`为实时优先级（范围为`0..RT_PRIO_LEVELS`，越大越优先），
+/// 当`class`为[`SchedClass::Normal`]时被忽略。
+///
+/// 返回是否设置成功。
+#[inline]
+pub fn set_sched_class(class: SchedClass, prio: isize) -> bool {
+    without_preempt(|| libvsched::set_sched_class(class, prio, get_cpu_id()))
+}
+
+/// 设置任意任务的调度类别，该任务可以在就绪队列中、阻塞在等待队列中或正在运行。
+///
+/// `class`与`prio`的含义与[`set_sched_class`]相同。就绪队列中的任务会被取出，
+/// 并以新的调度类别放回原就绪队列。
+///
+/// 返回是否设置成功。
+#[inline]
+pub fn set_task_sched_class(task: &ArcTaskRef, class: SchedClass, prio: isize) -> bool {
+    without_preempt(|| {
+        libvsched::set_task_sched_class(&arcext_as_base(task), class, prio, get_cpu_id())
+    })
+}
+
+/// 设置当前任务在EDF调度器中的预留参数，时间单位为纳秒。
+///
+/// 当前任务在每`period`纳秒中获得`runtime`纳秒的运行时间，且需在每个周期开始后的
+/// `deadline`纳秒内运行完毕。仅当`0 < runtime <= deadline <= period`且运行队列的
+/// 总带宽不超过100%时才接受该预留。`runtime`为0时取消预留。任务退出时会自动释放其预留。
+///
+/// 返回是否设置成功；若当前CPU的调度策略不是[`SchedPolicy::Edf`]，则返回`false`。
+///
+/// 任务错过截止时间的次数可通过`task.edf().deadline_misses()`查询。
+#[inline]
+pub fn set_deadline_params(runtime: usize, deadline: usize, period: usize) -> bool {
+    without_preempt(|| libvsched::set_deadline_params(runtime, deadline, period, get_cpu_id()))
+}
+
+/// 处理当前CPU的时钟中断，应在时钟中断（或用户态的定时信号）的处理函数中调用。
+///
+/// 该函数以距上次调用经过的时间推进调度器的状态（时间片、虚拟运行时间等）。
+/// 若当前任务需要被调度，或有到期的定时器事件，则在该函数中抢占当前任务，切换到其它任务；
+/// 当前任务恢复运行时，该函数才返回。协程在poll的过程中同样可以被抢占。
+/// 到期的定时器事件在任务切换后处理，而不在该函数中处理。
+///
+/// 若当前任务关闭了抢占（见[`disable_preempt`]），则本次调用被忽略。
+///
+/// 该函数需要启用`preempt`特性，使持有自旋锁的代码关闭抢占，避免该函数在中断处理中重入这些锁。
+/// 由于任务可能在任意位置被抢占，在任务中调用不可重入的函数（如内存分配、标准输出）时，
+/// 也需要关闭抢占。
+#[inline]
+pub fn timer_tick() {
+    crate::sched::timer_tick()
+}
+
+/// 处理其它CPU发送到当前CPU的重新调度请求，应在[`SMPIf::send_resched_ipi`]通知当前CPU后调用。
+///
+/// 其它CPU唤醒任务到当前CPU，且调度器认为被唤醒的任务应抢占当前任务时（如被唤醒的任务优先级更高），
+/// 会发送该请求。该函数与[`timer_tick`]相同，可能抢占当前任务；若当前任务关闭了抢占，则请求在下一次
+/// [`timer_tick`]中处理。
+///
+/// [`SMPIf::send_resched_ipi`]: crate::interface::SMPIf::send_resched_ipi
+#[inline]
+pub fn resched_ipi() {
+    crate::sched::resched_ipi()
+}
+
+/// 关闭当前任务的抢占，可以嵌套调用。
+///
+/// 关闭抢占期间，[`timer_tick`]不会抢占当前任务。
+#[inline]
+pub fn disable_preempt() {
+    unsafe { base_to_ext(libvsched::current(get_cpu_id())) }.disable_preempt()
+}
+
+/// 重新开启当前任务的抢占，与[`disable_preempt`]成对使用。
+///
+/// 若抢占完全开启且当前任务在关闭抢占期间需要被调度，则在此处抢占当前任务。
+#[inline]
+pub fn enable_preempt() {
+    unsafe { base_to_ext(libvsched::current(get_cpu_id())) }.enable_preempt(true)
+}
+
+/// 设置当前任务类型为`T`的局部变量，返回其原有的值。
+///
+/// 任务局部变量也可以在创建任务时通过[`TaskBuilder::local`]设置。
+pub fn set_task_local<T: Any + Send>(value: T) -> Option<T> {
+    let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+    let old = curr
+        .locals()
+        .lock()
+        .insert(TypeId::of::<T>(), Box::new(value));
+    old.map(|old| *old.downcast::<T>().unwrap())
+}
+
+/// 以当前任务类型为`T`的局部变量调用`f`，返回`f`的返回值；若该局部变量不存在，则返回`None`。
+///
+/// 调用`f`期间持有局部变量的锁，因此`f`中不能阻塞或让出，也不能访问当前任务的局部变量。
+pub fn with_task_local<T: Any + Send, R>(f: impl FnOnce(&mut T) -> R) -> Option<R> {
+    let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+    let mut locals = curr.locals().lock();
+    let value = locals.get_mut(&TypeId::of::<T>())?.downcast_mut::<T>()?;
+    Some(f(value))
+}
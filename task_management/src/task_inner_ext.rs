@@ -5,18 +5,25 @@
 
 extern crate alloc;
 
-use crate::{task::TaskWaker, wait_queue::WaitQueue};
-use alloc::{boxed::Box, format, string::String, sync::Arc};
+use crate::{
+    interface::get_cpu_id,
+    sched::{SpinNoPreempt, is_vsched_inited},
+    sync::pi::PiState,
+    task::TaskWaker,
+    wait_queue::WaitQueue,
+};
+use alloc::{boxed::Box, collections::btree_map::BTreeMap, format, string::String, sync::Arc};
 use base_task::{TaskStack, TaskState};
 use config::{AxCpuMask, SMP};
 use core::{
+    any::{Any, TypeId},
     cell::UnsafeCell,
+    fmt,
     mem::ManuallyDrop,
     ops::{Deref, DerefMut},
-    sync::atomic::{AtomicI32, Ordering},
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
     task::Waker,
 };
-use crossbeam::atomic::AtomicCell;
 use log::debug;
 
 /// 任务数据结构，由[`TaskInner`]经过调度器包装得到
@@ -46,14 +53,25 @@ pub struct TaskInnerExt {
     exit_code: AtomicI32,
     wait_for_exit: WaitQueue,
     entry: Option<*mut dyn FnOnce()>,
-    /// CPU affinity mask.
-    cpumask: AtomicCell<AxCpuMask>,
     // #[cfg(feature = "tls")]
     // tls: TlsArea,
     /// The future of coroutine task.
     future: UnsafeCell<Option<core::pin::Pin<Box<dyn Future<Output = ()> + Send + 'static>>>>,
+    /// 任务因持有互斥锁而被继承的优先级。
+    pi_state: SpinNoPreempt<PiState>,
+    /// 任务是否为分离的，分离的任务不能被等待。
+    detached: AtomicBool,
+    /// 任务局部变量，以类型区分。
+    locals: SpinNoPreempt<TaskLocals>,
+    /// 协程在本次poll中是否已被其[`Waker`]唤醒，见`task`模块中的`coroutine_schedule`。
+    notified: AtomicBool,
+    /// 任务带超时的等待是否由其定时器事件唤醒，见`timers`模块。
+    timed_out: AtomicBool,
 }
 
+/// 任务局部变量的存储，每种类型最多保存一个值。
+pub(crate) type TaskLocals = BTreeMap<TypeId, Box<dyn Any + Send>>;
+
 impl TaskInnerExt {
     fn new_common(name: String) -> Self {
         Self {
@@ -61,10 +79,14 @@ impl TaskInnerExt {
             exit_code: AtomicI32::new(0),
             wait_for_exit: WaitQueue::new(),
             entry: None,
-            cpumask: AtomicCell::new(AxCpuMask::full()),
             // #[cfg(feature = "tls")]
             // tls: TlsArea,
             future: UnsafeCell::new(None),
+            pi_state: SpinNoPreempt::new(PiState::new()),
+            detached: AtomicBool::new(false),
+            locals: SpinNoPreempt::new(BTreeMap::new()),
+            notified: AtomicBool::new(false),
+            timed_out: AtomicBool::new(false),
         }
     }
 }
@@ -116,12 +138,13 @@ impl TaskInner {
 
     /// 创建一个协程。
     ///
-    /// 除了`Future`外，还需要提供分配栈的函数和协程调度函数。
+    /// 除了`Future`外，还需要提供分配栈的函数、协程调度函数和协程运行时使用的栈大小。
     pub(crate) fn new_f<F>(
         future: F,
         name: String,
-        alloc_stack: fn() -> TaskStack,
+        alloc_stack: fn(usize) -> TaskStack,
         coroutine_schedule: fn(),
+        stack_size: usize,
     ) -> Self
     where
         F: Future<Output = ()> + Send + 'static,
@@ -134,6 +157,7 @@ impl TaskInner {
         t.ext.future = UnsafeCell::new(Some(Box::pin(future)));
         t.set_alloc_stack_fn(alloc_stack as usize);
         t.set_coroutine_schedule(coroutine_schedule as usize);
+        t.set_coroutine_stack_size(stack_size);
         t
     }
 }
@@ -179,21 +203,28 @@ impl TaskInner {
     /// Gets the cpu affinity mask of the task.
     ///
     /// Returns the cpu affinity mask of the task in type [`AxCpuMask`].
+    ///
+    /// 该字段保存在inner中，以便vsched在任务迁移时检查。
     #[inline]
     pub fn cpumask(&self) -> AxCpuMask {
-        self.ext.cpumask.load()
+        AxCpuMask::from_raw_bits(self.inner.cpumask_bits())
     }
 
     /// Sets the cpu affinity mask of the task.
     ///
+    /// 只修改掩码，不移动任务，用于还未运行的任务。
+    /// 修改已运行的任务的掩码时，使用[`task_api::set_cpumask`](crate::task_api::set_cpumask)。
+    ///
     /// # Arguments
     /// `cpumask` - The cpu affinity mask to be set in type [`AxCpuMask`].
     #[inline]
     pub fn set_cpumask(&self, cpumask: AxCpuMask) {
-        self.ext.cpumask.store(cpumask);
+        self.inner.set_cpumask_bits(cpumask_to_bits(cpumask));
     }
 
     /// 为任务选择一个可运行的CPU。
+    ///
+    /// 若当前CPU在任务的CPU亲和性掩码中，则选择当前CPU；否则在掩码中已初始化调度器的CPU里轮流选择。
     #[inline]
     pub fn select_run_queue_index(&self) -> usize {
         use core::sync::atomic::{AtomicUsize, Ordering};
@@ -201,14 +232,68 @@ impl TaskInner {
 
         let cpumask = self.cpumask();
         assert!(!cpumask.is_empty(), "No available CPU for task execution");
+        if cpumask.get(get_cpu_id()) {
+            return get_cpu_id();
+        }
 
         // Round-robin selection of the run queue index.
-        loop {
-            let index = unsafe { RUN_QUEUE_INDEX.fetch_add(1, Ordering::SeqCst) % SMP };
-            if cpumask.get(index) {
+        for _ in 0..SMP {
+            let index = RUN_QUEUE_INDEX.fetch_add(1, Ordering::SeqCst) % SMP;
+            if cpumask.get(index) && is_vsched_inited(index) {
                 return index;
             }
         }
+        panic!("No available CPU for task execution");
+    }
+
+    /// 获取任务的优先级继承状态。
+    #[inline]
+    pub(crate) fn pi_state(&self) -> &SpinNoPreempt<PiState> {
+        &self.ext.pi_state
+    }
+
+    /// 返回任务是否为分离的。
+    ///
+    /// 分离的任务不能被等待，[`join`](Self::join)和[`join_f`](Self::join_f)会立即返回`None`。
+    #[inline]
+    pub fn is_detached(&self) -> bool {
+        self.ext.detached.load(Ordering::Acquire)
+    }
+
+    /// 将任务设置为分离的，应在任务运行前设置。
+    #[inline]
+    pub(crate) fn set_detached(&self, detached: bool) {
+        self.ext.detached.store(detached, Ordering::Release);
+    }
+
+    /// 标记协程已被其[`Waker`]唤醒。
+    #[inline]
+    pub(crate) fn set_notified(&self) {
+        self.ext.notified.store(true, Ordering::Release);
+    }
+
+    /// 清除并返回协程是否已被其[`Waker`]唤醒。
+    #[inline]
+    pub(crate) fn take_notified(&self) -> bool {
+        self.ext.notified.swap(false, Ordering::AcqRel)
+    }
+
+    /// 标记任务带超时的等待已由其定时器事件唤醒。
+    #[inline]
+    pub(crate) fn set_timed_out(&self) {
+        self.ext.timed_out.store(true, Ordering::Release);
+    }
+
+    /// 清除并返回任务带超时的等待是否已由其定时器事件唤醒。
+    #[inline]
+    pub(crate) fn take_timed_out(&self) -> bool {
+        self.ext.timed_out.swap(false, Ordering::AcqRel)
+    }
+
+    /// 获取任务局部变量的存储。
+    #[inline]
+    pub(crate) fn locals(&self) -> &SpinNoPreempt<TaskLocals> {
+        &self.ext.locals
     }
 
     /// 退出代码
@@ -235,16 +320,22 @@ impl TaskInner {
         self.ext.wait_for_exit.notify_all(false);
     }
 
-    /// 使当前线程等待该任务退出，返回退出代码。
+    /// 使当前线程等待该任务退出，返回退出代码。若任务是分离的，则立即返回`None`。
     pub fn join(&self) -> Option<i32> {
+        if self.is_detached() {
+            return None;
+        }
         self.ext
             .wait_for_exit
             .wait_until(|| self.inner.state() == TaskState::Exited);
         Some(self.ext.exit_code.load(Ordering::Acquire))
     }
 
-    /// 使当前协程等待该任务退出，返回退出代码。
+    /// 使当前协程等待该任务退出，返回退出代码。若任务是分离的，则立即返回`None`。
     pub async fn join_f(&self) -> Option<i32> {
+        if self.is_detached() {
+            return None;
+        }
         self.ext
             .wait_for_exit
             .wait_until_f(|| self.inner.state() == TaskState::Exited)
@@ -253,12 +344,30 @@ impl TaskInner {
     }
 }
 
+impl fmt::Debug for TaskInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskInner")
+            .field("id", &self.inner.id().as_u64())
+            .field("name", &self.ext.name)
+            .field("state", &self.inner.state())
+            .finish()
+    }
+}
+
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("drop task: {}", self.id_name());
     }
 }
 
+/// 将[`AxCpuMask`]转化为vsched使用的掩码，第`i`位表示任务能否在CPU `i`上运行。
+#[inline]
+pub(crate) fn cpumask_to_bits(cpumask: AxCpuMask) -> usize {
+    (0..SMP)
+        .filter(|&i| cpumask.get(i))
+        .fold(0, |bits, i| bits | (1 << i))
+}
+
 /// 将从调度器中获得的`base_task::TaskRef`转化为`TaskRef`引用（从而可访问ext字段）
 ///
 /// 因为两种`TaskRef`内部都以指针方式存储，且除`ext`以外两种任务数据结构相同，因此可以直接使用`core::mem::transmute`转化
@@ -297,6 +406,14 @@ pub fn arcext_to_base(ext_ref: ArcTaskRef) -> base_task::TaskRef {
     ext_to_base(ext)
 }
 
+/// 获取`ArcTaskRef`对应的调度器使用的`TaskRef`，不改变引用计数
+///
+/// 返回的`TaskRef`仅在`ext_ref`对应的任务存活期间有效，适合作为参数临时传给vsched
+#[inline]
+pub fn arcext_as_base(ext_ref: &ArcTaskRef) -> base_task::TaskRef {
+    ext_to_base(TaskRef::new(Arc::as_ptr(ext_ref)))
+}
+
 /// 将Arc<TaskWaker>转化为ArcTaskRef。
 ///
 /// TaskWaker是一个包装了AxTask的结构体，因此可以直接使用`core::mem::transmute`转化。
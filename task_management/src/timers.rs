@@ -0,0 +1,174 @@
+//! 定时器的实现，用于任务的睡眠（[`sleep_until`](crate::task_api::sleep_until)等）和带超时的阻塞
+//! （[`WaitQueue::wait_timeout`](crate::wait_queue::WaitQueue::wait_timeout)等）。
+//!
+//! 每个CPU维护一个定时器列表，任务的定时器事件被加入其所在CPU的列表中，
+//! 并由该CPU在[`check_events`]中检查和触发。
+//!
+//! 定时器事件通过任务中的`timer_ticket_id`标识：设置定时器时为任务分配新的ID，
+//! 任务被其它事件唤醒后将其ID置为过期，此时定时器事件不会从列表中移除，而是在到期时被忽略。
+//!
+//! 带超时地等待阻塞队列的任务（见[`set_wait_timeout`]）同时可由阻塞队列的唤醒者唤醒，
+//! 两者通过比较并替换任务的定时器ID认领任务，只有认领成功的一方唤醒任务。
+
+use core::{
+    cmp::Ordering,
+    mem::ManuallyDrop,
+    ops::Deref,
+    ptr::NonNull,
+    sync::atomic::{self, AtomicU64},
+    time::Duration,
+};
+
+use crate::{
+    interface::{current_time, get_cpu_id},
+    sched::{self, SpinNoPreempt, without_preempt},
+    task_inner_ext::{ArcTaskRef, TaskRef, arcext_as_base},
+    wait_queue::WaitQueue,
+};
+use alloc::{collections::BinaryHeap, sync::Arc};
+use config::SMP;
+
+/// 定时器事件认领带超时等待的任务后，任务的定时器ID，见[`set_wait_timeout`]。
+pub(crate) const TIMER_TICKET_TIMED_OUT: u64 = u64::MAX;
+
+/// 定时器事件，到期时唤醒任务。
+struct TaskWakeupEvent {
+    deadline: Duration,
+    ticket_id: u64,
+    /// 持有任务的引用计数，使任务在事件到期或被忽略前不会被释放。
+    task: ArcTaskRef,
+    /// 任务带超时地等待的阻塞队列。
+    wq: Option<NonNull<WaitQueue>>,
+}
+
+impl TaskWakeupEvent {
+    fn callback(self) {
+        // 带超时的等待中，替换后的定时器ID使阻塞队列的唤醒者不再认领任务。
+        let claimed = if self.wq.is_some() {
+            TIMER_TICKET_TIMED_OUT
+        } else {
+            0
+        };
+        // 任务已被其它事件唤醒，或已设置了新的定时器。
+        if !self.task.replace_timer_ticket(self.ticket_id, claimed) {
+            return;
+        }
+        if let Some(wq) = self.wq {
+            // 认领任务后，任务只能由该事件唤醒，因此在此之前其阻塞队列不会被释放。
+            unsafe { wq.as_ref() }.remove_timed_out(&TaskRef::new(Arc::as_ptr(&self.task)));
+        }
+        sched::unblock_task(arcext_as_base(&self.task), true, get_cpu_id());
+    }
+}
+
+// 按到期时间排序，使`BinaryHeap`的堆顶为最早到期的事件。
+impl Ord for TaskWakeupEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .deadline
+            .cmp(&self.deadline)
+            .then(other.ticket_id.cmp(&self.ticket_id))
+    }
+}
+
+impl PartialOrd for TaskWakeupEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TaskWakeupEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.ticket_id == other.ticket_id
+    }
+}
+
+impl Eq for TaskWakeupEvent {}
+
+/// 每个CPU维护一个定时器列表。
+struct PerCPUTimerList([SpinNoPreempt<BinaryHeap<TaskWakeupEvent>>; SMP]);
+
+impl PerCPUTimerList {
+    const fn new() -> Self {
+        Self([const { SpinNoPreempt::new(BinaryHeap::new()) }; SMP])
+    }
+}
+
+unsafe impl Send for PerCPUTimerList {}
+unsafe impl Sync for PerCPUTimerList {}
+
+impl Deref for PerCPUTimerList {
+    type Target = SpinNoPreempt<BinaryHeap<TaskWakeupEvent>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0[get_cpu_id()]
+    }
+}
+
+static TIMER_LIST: PerCPUTimerList = PerCPUTimerList::new();
+
+/// 定时器事件的ID，从1开始分配，因为0表示事件已过期。
+static TIMER_TICKET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 在当前CPU的定时器列表中设置一个定时器事件，在`deadline`时唤醒任务`task`。
+///
+/// 任务之前设置的定时器事件将被忽略。
+pub(crate) fn set_alarm_wakeup(deadline: Duration, task: &TaskRef) {
+    set_event(deadline, task, None);
+}
+
+/// 为带超时地阻塞在`wq`中的任务`task`设置定时器事件，在`deadline`时将其从`wq`中移除并唤醒。
+///
+/// 应在持有`wq`的锁、且已将任务放入`wq`时调用，使唤醒者取出任务时总能看到其定时器ID。
+/// 定时器事件将任务的定时器ID替换为[`TIMER_TICKET_TIMED_OUT`]，以认领任务，
+/// 唤醒者则将其替换为0（见[`WaitQueue::notify_one`]），认领失败的一方不唤醒任务。
+/// 因此任务只能由认领成功的一方唤醒，在定时器事件认领任务后，`wq`在事件处理完成前不会被释放。
+pub(crate) fn set_wait_timeout(deadline: Duration, task: &TaskRef, wq: &WaitQueue) {
+    set_event(deadline, task, Some(NonNull::from(wq)));
+}
+
+fn set_event(deadline: Duration, task: &TaskRef, wq: Option<NonNull<WaitQueue>>) {
+    let ticket_id = TIMER_TICKET_ID.fetch_add(1, atomic::Ordering::AcqRel);
+    task.set_timer_ticket(ticket_id);
+    // `into_arc`返回的`ManuallyDrop`不会被释放，clone后的引用计数由事件持有。
+    let task = ManuallyDrop::into_inner(task.into_arc().clone());
+    without_preempt(|| {
+        TIMER_LIST.lock().push(TaskWakeupEvent {
+            deadline,
+            ticket_id,
+            task,
+            wq,
+        })
+    });
+}
+
+/// 返回当前CPU上最早的定时器事件的到期时间，该事件可能已被忽略。
+pub(crate) fn next_deadline() -> Option<Duration> {
+    without_preempt(|| TIMER_LIST.lock().peek().map(|event| event.deadline))
+}
+
+/// 检查当前CPU的定时器列表，唤醒所有已到期的任务。
+///
+/// 应在任务切换后、以及CPU空闲时调用。当前CPU的空闲任务会在每次让出前调用该函数。
+/// 事件的回调可能释放任务、分配内存或输出日志，因此不能在时钟中断（或用户态的定时信号）中调用。
+///
+/// 该函数在关闭当前任务抢占的情况下执行，使时钟中断处理函数不会在持有定时器列表的锁时重入。
+pub fn check_events() {
+    without_preempt(|| {
+        loop {
+            let now = current_time();
+            let event = {
+                let mut timer_list = TIMER_LIST.lock();
+                match timer_list.peek() {
+                    Some(event) if event.deadline <= now => timer_list.pop(),
+                    _ => None,
+                }
+            };
+            // 在释放定时器列表的锁后再唤醒任务。
+            match event {
+                Some(event) => event.callback(),
+                None => break,
+            }
+        }
+    })
+}
@@ -1,34 +1,83 @@
 //! 阻塞队列的实现
 
 use crate::{
-    interface::get_cpu_id,
-    sched::{BlockedReschedFuture, blocked_resched},
+    interface::{current_time, get_cpu_id},
+    sched::{self, BlockedReschedFuture, SpinNoPreempt, SpinNoPreemptGuard, blocked_resched},
     task_inner_ext::{TaskRef, base_to_ext, ext_to_base},
+    timers::TIMER_TICKET_TIMED_OUT,
 };
 use alloc::{collections::VecDeque, vec::Vec};
-use kspin::{SpinNoIrq, SpinNoIrqGuard};
+use core::{
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// 阻塞队列中的等待者。
+pub enum Waiter {
+    /// 阻塞的线程或协程，被唤醒时由vsched直接放入就绪队列。
+    Task(TaskRef),
+    /// 任意的[`Waker`]，被唤醒时调用其[`wake`](Waker::wake)。
+    ///
+    /// 使遵循标准`Waker`协议的`Future`（如`task_management`之外实现的`Future`）也可以等待阻塞队列。
+    Waker(Waker),
+    /// 通过[`Waker`]等待阻塞队列的`Future`（见[`WaitQueue::wait_waker`]），被唤醒时调用其`wake`。
+    ///
+    /// 同一任务的多个`Future`的`Waker`可能相同，因此以唯一的`id`区分各`Future`的等待项。
+    Entry {
+        /// 等待项的编号
+        id: u64,
+        /// 等待项的`Waker`
+        waker: Waker,
+    },
+}
+
+/// 为每个[`Waiter::Entry`]分配唯一的编号。
+static WAITER_ENTRY_ID: AtomicU64 = AtomicU64::new(1);
 
-/// 阻塞队列，可用于阻塞线程或协程。
+impl Waiter {
+    fn is_task(&self, task: &TaskRef) -> bool {
+        matches!(self, Self::Task(t) if t.ptr_eq(task))
+    }
+
+    fn will_wake(&self, waker: &Waker) -> bool {
+        matches!(self, Self::Waker(w) if w.will_wake(waker))
+    }
+
+    fn is_entry(&self, entry_id: u64) -> bool {
+        matches!(self, Self::Entry { id, .. } if *id == entry_id)
+    }
+
+    fn entry_waker(&mut self, entry_id: u64) -> Option<&mut Waker> {
+        match self {
+            Self::Entry { id, waker } if *id == entry_id => Some(waker),
+            _ => None,
+        }
+    }
+}
+
+/// 阻塞队列，可用于阻塞线程或协程，也可用于等待任意的[`Waker`]。
 pub struct WaitQueue {
     /// 队列
-    pub queue: SpinNoIrq<VecDeque<TaskRef>>,
+    pub queue: SpinNoPreempt<VecDeque<Waiter>>,
 }
 
 /// 阻塞队列的锁保护引用。
-pub type WaitQueueGuard<'a> = SpinNoIrqGuard<'a, VecDeque<TaskRef>>;
+pub type WaitQueueGuard<'a> = SpinNoPreemptGuard<'a, VecDeque<Waiter>>;
 
 impl WaitQueue {
     /// Creates an empty wait queue.
     pub const fn new() -> Self {
         Self {
-            queue: SpinNoIrq::new(VecDeque::new()),
+            queue: SpinNoPreempt::new(VecDeque::new()),
         }
     }
 
     /// Creates an empty wait queue with space for at least `capacity` elements.
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            queue: SpinNoIrq::new(VecDeque::with_capacity(capacity)),
+            queue: SpinNoPreempt::new(VecDeque::with_capacity(capacity)),
         }
     }
 
@@ -38,14 +87,14 @@ impl WaitQueue {
         // A task can be wake up only one events (timer or `notify()`), remove
         // the event from another queue.
         if curr.in_wait_queue() {
-            // wake up by timer (timeout).
-            self.queue.lock().retain(|t| !curr.ptr_eq(t));
+            // woken up without being removed from the wait queue.
+            self.queue.lock().retain(|w| !w.is_task(curr));
             curr.set_in_wait_queue(false);
         }
 
         // Try to cancel a timer event from timer lists.
         // Just mark task's current timer ticket ID as expired.
-        #[cfg(feature = "irq")]
+        // #[cfg(feature = "irq")]
         if _from_timer_list {
             curr.timer_ticket_expired();
             // Note:
@@ -61,10 +110,21 @@ impl WaitQueue {
     pub fn wait(&self) {
         let wq = self.queue.lock();
         let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
-        blocked_resched(wq);
+        blocked_resched(wq, None);
         self.cancel_events(&curr, false);
     }
 
+    /// 由定时器事件调用：将等待超时的任务`task`移出阻塞队列，并标记其等待已超时。
+    ///
+    /// 任务可能已被[`requeue`](Self::requeue)移到其它阻塞队列，此时它留在那里，
+    /// 并被那里的唤醒者跳过。
+    pub(crate) fn remove_timed_out(&self, task: &TaskRef) {
+        let mut wq = self.queue.lock();
+        wq.retain(|w| !w.is_task(task));
+        task.set_in_wait_queue(false);
+        task.set_timed_out();
+    }
+
     /// Blocks the current coroutine task and put it into the wait queue, until other task
     /// notifies it.
     pub async fn wait_f(&self) {
@@ -73,6 +133,47 @@ impl WaitQueue {
         self.cancel_events(&curr, false);
     }
 
+    /// Blocks the current task and put it into the wait queue, until other tasks
+    /// notify it, or the given duration has elapsed.
+    ///
+    /// Returns `true` if the task is woken up by the timer (timeout).
+    pub fn wait_timeout(&self, dur: Duration) -> bool {
+        let wq = self.queue.lock();
+        let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+        let deadline = current_time() + dur;
+        log::debug!(
+            "task wait_timeout: {}, deadline={:?}",
+            curr.id_name(),
+            deadline
+        );
+        blocked_resched(wq, Some((self, deadline)));
+        // The timer event claims the task against other tasks notifying it,
+        // see `set_wait_timeout()`.
+        let timeout = curr.take_timed_out();
+        self.cancel_events(&curr, true);
+        timeout
+    }
+
+    /// Blocks the current coroutine task and put it into the wait queue, until
+    /// other tasks notify it, or the given duration has elapsed.
+    ///
+    /// Returns `true` if the task is woken up by the timer (timeout).
+    pub async fn wait_timeout_f(&self, dur: Duration) -> bool {
+        let curr = unsafe { base_to_ext(libvsched::current(get_cpu_id())) };
+        let deadline = current_time() + dur;
+        log::debug!(
+            "task wait_timeout: {}, deadline={:?}",
+            curr.id_name(),
+            deadline
+        );
+        BlockedReschedFuture::with_deadline(self, deadline).await;
+        // The timer event claims the task against other tasks notifying it,
+        // see `set_wait_timeout()`.
+        let timeout = curr.take_timed_out();
+        self.cancel_events(&curr, true);
+        timeout
+    }
+
     /// Blocks the current task and put it into the wait queue, until the given
     /// `condition` becomes true.
     ///
@@ -88,7 +189,7 @@ impl WaitQueue {
             if condition() {
                 break;
             }
-            blocked_resched(wq);
+            blocked_resched(wq, None);
             // Preemption may occur here.
         }
         self.cancel_events(&curr, false);
@@ -108,27 +209,82 @@ impl WaitQueue {
             if condition() {
                 break;
             }
-            BlockedReschedFuture::new(self).await;
+            // Check the condition again while holding the lock of the wait queue,
+            // so that the notification before blocking will not be lost.
+            BlockedReschedFuture::with_condition(self, &condition).await;
             // Preemption may occur here.
         }
         self.cancel_events(&curr, false);
     }
 
-    /// Wakes up one task in the wait queue, usually the first one.
+    /// Puts `waker` into the wait queue, which is woken up by the following
+    /// [`notify_one`](Self::notify_one) or [`notify_all`](Self::notify_all).
+    ///
+    /// Nothing is done if a waker which wakes the same task is already in the
+    /// queue, so it can be called in each poll of a future.
+    pub fn register_waker(&self, waker: &Waker) {
+        let mut wq = self.queue.lock();
+        if !wq.iter().any(|w| w.will_wake(waker)) {
+            wq.push_back(Waiter::Waker(waker.clone()));
+        }
+    }
+
+    /// Waits in the wait queue through the [`Waker`] of the future, until other
+    /// tasks notify it.
+    ///
+    /// Unlike [`wait_f`](Self::wait_f), it does not require the future to be
+    /// polled by a coroutine of vsched, so it can be used by any executor.
+    pub async fn wait_waker(&self) {
+        WakerWaitFuture::new(self).await
+    }
+
+    /// Waits in the wait queue through the [`Waker`] of the future, until the
+    /// given `condition` becomes true.
+    ///
+    /// Note that even other tasks notify this future, it will not be ready
+    /// until the condition becomes true.
+    pub async fn wait_until_waker<F>(&self, condition: F)
+    where
+        F: Fn() -> bool,
+    {
+        loop {
+            // Check the condition while holding the lock of the wait queue, so
+            // that the notification before waiting will not be lost.
+            if WakerWaitFuture::with_condition(self, &condition).await {
+                break;
+            }
+        }
+    }
+
+    /// Wakes up one task or waker in the wait queue, usually the first one.
+    ///
+    /// A task whose wait has timed out is skipped, and the next one is woken
+    /// up instead.
     ///
     /// If `resched` is true, the current task will be preempted when the
-    /// preemption is enabled.
+    /// preemption is enabled. It has no effect on the wakers.
     pub fn notify_one(&self, resched: bool) -> bool {
         let mut wq = self.queue.lock();
-        if let Some(task) = wq.pop_front() {
-            unblock_one_task(task, resched);
-            true
-        } else {
-            false
+        loop {
+            match wq.pop_front() {
+                Some(Waiter::Task(task)) => {
+                    if claim_waiter(&task) {
+                        unblock_one_task(task, resched);
+                        return true;
+                    }
+                }
+                Some(Waiter::Waker(waker) | Waiter::Entry { waker, .. }) => {
+                    // The waker may do anything, e.g. wait on this queue again.
+                    drop(wq);
+                    waker.wake();
+                    return true;
+                }
+                None => return false,
+            }
         }
     }
 
-    /// Wakes all tasks in the wait queue.
+    /// Wakes all tasks and wakers in the wait queue.
     ///
     /// If `resched` is true, the current task will be preempted when the
     /// preemption is enabled.
@@ -144,8 +300,14 @@ impl WaitQueue {
     /// preemption is enabled.
     pub fn notify_task(&mut self, resched: bool, task: &TaskRef) -> bool {
         let mut wq = self.queue.lock();
-        if let Some(index) = wq.iter().position(|t| TaskRef::ptr_eq(t, task)) {
-            unblock_one_task(wq.remove(index).unwrap(), resched);
+        if let Some(index) = wq.iter().position(|w| w.is_task(task)) {
+            let Some(Waiter::Task(task)) = wq.remove(index) else {
+                unreachable!()
+            };
+            if !claim_waiter(&task) {
+                return false;
+            }
+            unblock_one_task(task, resched);
             true
         } else {
             false
@@ -186,6 +348,18 @@ impl WaitQueue {
     }
 }
 
+/// 唤醒者认领从阻塞队列中取出的任务`task`，返回是否可以唤醒该任务。
+///
+/// 带超时地等待的任务可能已被其定时器事件认领，此时其定时器ID为[`TIMER_TICKET_TIMED_OUT`]，
+/// 唤醒者不能再唤醒它（见[`set_wait_timeout`](crate::timers::set_wait_timeout)）。
+fn claim_waiter(task: &TaskRef) -> bool {
+    match task.timer_ticket() {
+        TIMER_TICKET_TIMED_OUT => false,
+        0 => true,
+        ticket => task.replace_timer_ticket(ticket, 0),
+    }
+}
+
 fn unblock_one_task(task: TaskRef, resched: bool) {
     // Mark task as not in wait queue.
     task.set_in_wait_queue(false);
@@ -197,5 +371,98 @@ fn unblock_one_task(task: TaskRef, resched: bool) {
     // Select run queue by the CPU set of the task.
     // Use `NoOp` kernel guard here because the function is called with holding the
     // lock of wait queue, where the irq and preemption are disabled.
-    libvsched::unblock_task(ext_to_base(task), resched, get_cpu_id(), get_cpu_id());
+    let cpu_id = task.select_run_queue_index();
+    sched::unblock_task(ext_to_base(task), resched, cpu_id);
+}
+
+/// The future used when waiting in a [`WaitQueue`] through a [`Waker`].
+///
+/// An entry with a unique id is put into the wait queue at the first poll, and
+/// the future is ready after the entry is removed from the queue by the
+/// notification. Other futures of the same task may share the same waker, so
+/// the entry is always found by its id. If there is a `condition`, it is
+/// checked while holding the lock of the wait queue before waiting, and the
+/// future outputs `true` at once if it is true.
+struct WakerWaitFuture<'a, F = fn() -> bool> {
+    wq: &'a WaitQueue,
+    condition: F,
+    entry: Option<u64>,
+}
+
+impl<'a> WakerWaitFuture<'a> {
+    fn new(wq: &'a WaitQueue) -> Self {
+        Self::with_condition(wq, || false)
+    }
+}
+
+impl<'a, F: Fn() -> bool> WakerWaitFuture<'a, F> {
+    fn with_condition(wq: &'a WaitQueue, condition: F) -> Self {
+        Self {
+            wq,
+            condition,
+            entry: None,
+        }
+    }
+}
+
+impl<'a, F> Unpin for WakerWaitFuture<'a, F> {}
+
+impl<'a, F: Fn() -> bool> Future for WakerWaitFuture<'a, F> {
+    type Output = bool;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Self {
+            wq,
+            condition,
+            entry,
+        } = self.get_mut();
+        let mut wq_guard = wq.queue.lock();
+        match *entry {
+            None => {
+                if condition() {
+                    return Poll::Ready(true);
+                }
+                let id = WAITER_ENTRY_ID.fetch_add(1, Ordering::Relaxed);
+                wq_guard.push_back(Waiter::Entry {
+                    id,
+                    waker: cx.waker().clone(),
+                });
+                *entry = Some(id);
+                Poll::Pending
+            }
+            Some(id) => match wq_guard.iter_mut().find_map(|w| w.entry_waker(id)) {
+                // Polled without being notified, the waker may be changed.
+                Some(waker) => {
+                    if !waker.will_wake(cx.waker()) {
+                        *waker = cx.waker().clone();
+                    }
+                    Poll::Pending
+                }
+                None => {
+                    *entry = None;
+                    Poll::Ready(false)
+                }
+            },
+        }
+    }
+}
+
+impl<'a, F> Drop for WakerWaitFuture<'a, F> {
+    fn drop(&mut self) {
+        let Some(id) = self.entry else {
+            return;
+        };
+        let mut wq_guard = self.wq.queue.lock();
+        match wq_guard.iter().position(|w| w.is_entry(id)) {
+            // Cancel the waiting if the future is dropped before being notified.
+            Some(index) => {
+                wq_guard.remove(index);
+            }
+            // The future is notified but dropped before being polled, so pass
+            // the notification to another waiter, or it would be lost.
+            None => {
+                drop(wq_guard);
+                self.wq.notify_one(false);
+            }
+        }
+    }
 }
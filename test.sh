@@ -21,6 +21,104 @@ if [ $? -ne 0 ]; then
     exit 1
 fi
 
+ARCH=riscv64 LOG=warn UTEST=sync SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] sync test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=priority_inherit SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] priority_inherit test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=sched_class SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] sched_class test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=channel SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] channel test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=join_handle SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] join_handle test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=task_builder SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] task_builder test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=preempt SCHED=rr FEATURES=preempt SMP=1 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] preempt test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=affinity SMP=4 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] affinity test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=migrate SMP=4 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] migrate test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=migrate SCHED=edf SMP=4 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] migrate test with EDF failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=resched_ipi FEATURES=preempt SMP=2 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] resched_ipi test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=idle SMP=4 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] idle test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=waker_stress SMP=4 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] waker_stress test failed!"
+    exit 1
+fi
+
+ARCH=riscv64 LOG=warn UTEST=preempt_lock SCHED=rr FEATURES=preempt SMP=2 make utest
+
+if [ $? -ne 0 ]; then
+    echo "[test script] preempt_lock test failed!"
+    exit 1
+fi
+
 ARCH=riscv64 LOG=warn UTEST=all SMP=4 make utest
 
 if [ $? -ne 0 ]; then
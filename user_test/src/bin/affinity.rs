@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use config::AxCpuMask;
+use task_management::{task::run_idle, task_api::*, wait_queue::WaitQueue};
+use user_test::*;
+
+const ROUNDS: usize = 16;
+
+static WQ: WaitQueue = WaitQueue::new();
+static READY: AtomicBool = AtomicBool::new(false);
+static WOKEN: AtomicBool = AtomicBool::new(false);
+static MOVED: AtomicBool = AtomicBool::new(false);
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    assert!(config::SMP >= 4, "the affinity test needs at least 4 CPUs");
+    static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
+    for _ in 0..(config::SMP - 1) {
+        let _thread_handle = std::thread::spawn(|| {
+            init_cpu_id();
+            init_vsched_secondary(sched_policy());
+            BOOT_COUNT.fetch_add(1, Ordering::Relaxed);
+            run_idle();
+        });
+    }
+
+    init_cpu_id();
+    init_vsched(sched_policy());
+    while BOOT_COUNT.load(Ordering::Relaxed) < config::SMP {
+        core::hint::spin_loop();
+    }
+
+    // 创建时设置的掩码：任务只在CPU 2上运行
+    let pinned = new(
+        || {
+            for _ in 0..ROUNDS {
+                assert_eq!(get_cpu_id(), 2);
+                yield_now();
+            }
+        },
+        "pinned".into(),
+        config::TASK_STACK_SIZE,
+    );
+    pinned.set_cpumask(AxCpuMask::one_shot(2));
+    spawn(pinned.clone()).unwrap();
+    pinned.join();
+    println!("(1) spawned task ran on its only allowed CPU");
+
+    // 修改运行中任务的掩码：任务在被切换出时被移动到CPU 3
+    let moving = new_f(
+        async {
+            while !MOVED.load(Ordering::Acquire) {
+                yield_now_f().await;
+            }
+            yield_now_f().await;
+            for _ in 0..ROUNDS {
+                assert_eq!(get_cpu_id(), 3);
+                yield_now_f().await;
+            }
+        },
+        "moving".into(),
+    );
+    spawn(moving.clone()).unwrap();
+    assert!(set_cpumask(&moving, AxCpuMask::one_shot(3)));
+    MOVED.store(true, Ordering::Release);
+    moving.join();
+    assert!(!set_cpumask(&moving, AxCpuMask::new()));
+    println!("(2) running task was moved off the forbidden CPUs");
+
+    // 唤醒：在CPU 0上唤醒的任务被放入其允许的CPU 1
+    let waiter = new(
+        || {
+            READY.store(true, Ordering::Release);
+            WQ.wait_until(|| WOKEN.load(Ordering::Acquire));
+            assert_eq!(get_cpu_id(), 1);
+        },
+        "waiter".into(),
+        config::TASK_STACK_SIZE,
+    );
+    waiter.set_cpumask(AxCpuMask::one_shot(1));
+    spawn(waiter.clone()).unwrap();
+    while !READY.load(Ordering::Acquire) {
+        yield_now();
+    }
+    WOKEN.store(true, Ordering::Release);
+    WQ.notify_all(true);
+    waiter.join();
+    println!("(3) woken task was put into its allowed CPU");
+
+    println!("back to main task");
+    exit(0)
+}
@@ -16,14 +16,14 @@ fn main() {
         println!("spawn kernel thread {}", i);
         let _thread_handle = std::thread::spawn(|| {
             init_cpu_id();
-            init_vsched_secondary();
+            init_vsched_secondary(sched_policy());
             BOOT_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             run_idle();
         });
     }
 
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
     while BOOT_COUNT.load(std::sync::atomic::Ordering::Relaxed) < config::SMP {
         core::hint::spin_loop();
     }
@@ -76,7 +76,7 @@ fn main() {
     }
 
     for task in tasks.iter() {
-        spawn(task.as_ref().unwrap().clone());
+        spawn(task.as_ref().unwrap().clone()).unwrap();
     }
 
     tasks[0].as_ref().unwrap().join();
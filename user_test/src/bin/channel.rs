@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use task_management::{
+    sync::{mpsc, oneshot},
+    task_api::*,
+};
+use user_test::*;
+
+const ROUNDS: usize = 8;
+const CAP: usize = 2;
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    init_vsched(sched_policy());
+
+    // 无界通道：线程和协程同时发送，协程接收，所有发送者释放后接收者返回错误
+    let (tx, rx) = mpsc::channel::<usize>();
+    let mut tasks = Vec::new();
+    let thread_tx = tx.clone();
+    tasks.push(new(
+        move || {
+            for i in 0..ROUNDS {
+                thread_tx.send(i).unwrap();
+                yield_now();
+            }
+        },
+        "thread_sender".into(),
+        config::TASK_STACK_SIZE,
+    ));
+    tasks.push(new_f(
+        async move {
+            for i in 0..ROUNDS {
+                tx.send_f(ROUNDS + i).await.unwrap();
+                yield_now_f().await;
+            }
+        },
+        "coroutine_sender".into(),
+    ));
+    tasks.push(new_f(
+        async move {
+            let mut sum = 0;
+            let mut count = 0;
+            while let Ok(value) = rx.recv_f().await {
+                sum += value;
+                count += 1;
+            }
+            assert_eq!(count, 2 * ROUNDS);
+            assert_eq!(sum, (0..2 * ROUNDS).sum::<usize>());
+        },
+        "coroutine_receiver".into(),
+    ));
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    println!("(1) mpsc channel delivered {} messages", 2 * ROUNDS);
+
+    // 有界通道：通道中的数据不超过容量，发送者阻塞直到接收者取出数据
+    let (tx, rx) = mpsc::bounded::<usize>(CAP);
+    tasks.push(new_f(
+        async move {
+            for i in 0..ROUNDS {
+                IN_FLIGHT.fetch_add(1, Ordering::AcqRel);
+                tx.send_f(i).await.unwrap();
+                assert!(IN_FLIGHT.load(Ordering::Acquire) <= CAP + 1);
+            }
+        },
+        "bounded_sender".into(),
+    ));
+    tasks.push(new(
+        move || {
+            for i in 0..ROUNDS {
+                // 让发送者先填满通道
+                yield_now();
+                assert_eq!(rx.recv().unwrap(), i);
+                IN_FLIGHT.fetch_sub(1, Ordering::AcqRel);
+            }
+            assert_eq!(rx.recv(), Err(mpsc::RecvError));
+        },
+        "bounded_receiver".into(),
+        config::TASK_STACK_SIZE,
+    ));
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    println!("(2) bounded channel applied backpressure");
+
+    // 接收者被释放后，发送失败并返回数据
+    let (tx, rx) = mpsc::bounded::<usize>(1);
+    tx.send(1).unwrap();
+    assert!(matches!(tx.try_send(2), Err(mpsc::TrySendError::Full(2))));
+    drop(rx);
+    assert_eq!(tx.send(3), Err(mpsc::SendError(3)));
+    println!("(3) sending to a dropped receiver failed");
+
+    // oneshot：协程发送，线程接收；发送者未发送就被释放时，接收失败
+    let (tx, rx) = oneshot::channel::<usize>();
+    let (drop_tx, drop_rx) = oneshot::channel::<usize>();
+    tasks.push(new(
+        move || {
+            assert_eq!(rx.recv(), Ok(42));
+            assert_eq!(drop_rx.recv(), Err(oneshot::RecvError));
+        },
+        "oneshot_receiver".into(),
+        config::TASK_STACK_SIZE,
+    ));
+    tasks.push(new_f(
+        async move {
+            yield_now_f().await;
+            tx.send(42).unwrap();
+            yield_now_f().await;
+            drop(drop_tx);
+        },
+        "oneshot_sender".into(),
+    ));
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    println!("(4) oneshot channel delivered the value");
+
+    println!("back to main task");
+    exit(0)
+}
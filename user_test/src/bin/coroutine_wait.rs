@@ -4,7 +4,7 @@ fn main() {
     env_logger::init();
     libvsched::load_and_init();
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
     let task1 = new_f(
         async {
             println!("into spawned task inner");
@@ -20,8 +20,8 @@ fn main() {
         },
         "task__2".into(),
     );
-    spawn(task2.clone());
-    spawn(task1.clone());
+    spawn(task2.clone()).unwrap();
+    spawn(task1.clone()).unwrap();
 
     yield_now();
 
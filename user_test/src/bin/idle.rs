@@ -0,0 +1,88 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use config::AxCpuMask;
+use task_management::{task::run_idle, task_api::*};
+use user_test::*;
+
+/// 返回进程的所有OS线程使用的CPU时间。
+fn process_cpu_time() -> Duration {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    assert_eq!(
+        unsafe { libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut ts) },
+        0
+    );
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    assert!(config::SMP >= 4, "the idle test needs at least 4 CPUs");
+    static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
+    for _ in 0..(config::SMP - 1) {
+        let _thread_handle = std::thread::spawn(|| {
+            init_cpu_id();
+            init_vsched_secondary(sched_policy());
+            BOOT_COUNT.fetch_add(1, Ordering::Relaxed);
+            run_idle();
+        });
+    }
+
+    init_cpu_id();
+    init_vsched(sched_policy());
+    while BOOT_COUNT.load(Ordering::Relaxed) < config::SMP {
+        core::hint::spin_loop();
+    }
+
+    // 没有可运行的任务时，所有CPU都睡眠，而不是忙等
+    let wall = Instant::now();
+    let cpu = process_cpu_time();
+    sleep(Duration::from_millis(200));
+    let (wall, cpu) = (wall.elapsed(), process_cpu_time() - cpu);
+    println!("(1) {:?} of CPU time used in {:?} while idle", cpu, wall);
+    assert!(cpu < wall / 2);
+
+    // 创建任务时唤醒其目标CPU
+    let handle = TaskBuilder::new()
+        .name("kicked".into())
+        .cpumask(AxCpuMask::one_shot(3))
+        .spawn(get_cpu_id)
+        .unwrap();
+    assert_eq!(handle.join(), Some(3));
+    println!("(2) idle CPU woken up by spawn");
+
+    // 睡眠的CPU在其定时器到期时醒来，唤醒在其上睡眠的任务
+    let handle = TaskBuilder::new()
+        .name("sleeper".into())
+        .cpumask(AxCpuMask::one_shot(2))
+        .spawn(|| {
+            let start = current_time();
+            sleep(Duration::from_millis(50));
+            (get_cpu_id(), current_time() - start)
+        })
+        .unwrap();
+    let (cpu_id, slept) = handle.join().unwrap();
+    assert_eq!(cpu_id, 2);
+    assert!(slept >= Duration::from_millis(50));
+    println!("(3) idle CPU woken up by its timer after {:?}", slept);
+
+    // 修改任务的CPU亲和性掩码后，任务被唤醒到新的CPU，并唤醒该CPU
+    let handle = TaskBuilder::new()
+        .name("migrated".into())
+        .cpumask(AxCpuMask::one_shot(1))
+        .spawn(|| {
+            sleep(Duration::from_millis(20));
+            get_cpu_id()
+        })
+        .unwrap();
+    let task = handle.task().clone();
+    assert!(set_cpumask(&task, AxCpuMask::one_shot(3)));
+    assert_eq!(handle.join(), Some(3));
+    println!("(4) idle CPU woken up by a task moved to it");
+
+    println!("idle test passed");
+    exit(0)
+}
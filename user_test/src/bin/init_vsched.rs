@@ -4,7 +4,7 @@ fn main() {
     env_logger::init();
     libvsched::load_and_init();
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
     println!("{:?}", vsched_map.percpu(get_cpu_id()).idle_task);
     exit(0)
 }
@@ -0,0 +1,67 @@
+use task_management::task_api::*;
+use user_test::*;
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    init_vsched(sched_policy());
+
+    // 线程返回值，由主任务通过join获取
+    let thread = spawn_new(
+        || {
+            yield_now();
+            (1..=10).sum::<usize>()
+        },
+        "thread".into(),
+        config::TASK_STACK_SIZE,
+    )
+    .unwrap();
+    assert_eq!(thread.join(), Some(55));
+    println!("(1) thread returned 55");
+
+    // 协程直接await另一个线程和另一个协程的句柄
+    let waiter = spawn_new_f(
+        async {
+            let thread = spawn_new(
+                || String::from("from thread"),
+                "inner_thread".into(),
+                config::TASK_STACK_SIZE,
+            )
+            .unwrap();
+            let coroutine = spawn_new_f(
+                async {
+                    yield_now_f().await;
+                    String::from("from coroutine")
+                },
+                "inner_coroutine".into(),
+            )
+            .unwrap();
+            let a = thread.await.unwrap();
+            let b = coroutine.await.unwrap();
+            format!("{} and {}", a, b)
+        },
+        "waiter".into(),
+    )
+    .unwrap();
+    assert_eq!(
+        waiter.join().as_deref(),
+        Some("from thread and from coroutine")
+    );
+    println!("(2) coroutine awaited the handles");
+
+    // 提前退出的任务没有返回值
+    let exited = spawn_new(
+        || -> usize { exit(1) },
+        "exited".into(),
+        config::TASK_STACK_SIZE,
+    )
+    .unwrap();
+    let task = exited.task().clone();
+    assert_eq!(exited.join(), None);
+    assert_eq!(task.join(), Some(1));
+    println!("(3) exited task returned nothing");
+
+    println!("back to main task");
+    exit(0)
+}
@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use config::AxCpuMask;
+use task_management::{task::run_idle, task_api::*, wait_queue::WaitQueue};
+use user_test::*;
+
+const ROUNDS: usize = 16;
+
+static WQ: WaitQueue = WaitQueue::new();
+static READY: AtomicBool = AtomicBool::new(false);
+static WOKEN: AtomicBool = AtomicBool::new(false);
+static MOVED: AtomicBool = AtomicBool::new(false);
+static DONE: AtomicBool = AtomicBool::new(false);
+static RESERVED: AtomicBool = AtomicBool::new(false);
+static CHECKED: AtomicBool = AtomicBool::new(false);
+
+/// 主任务让出而不阻塞，使CPU 0的空闲任务不会从CPU 2窃取任务。
+fn join_busily<T>(handle: JoinHandle<T>) -> T {
+    while !handle.is_finished() {
+        yield_now();
+    }
+    handle.join().unwrap()
+}
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    assert!(config::SMP >= 3, "the migrate test needs at least 3 CPUs");
+    static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
+    for _ in 0..(config::SMP - 1) {
+        let _thread_handle = std::thread::spawn(|| {
+            init_cpu_id();
+            init_vsched_secondary(sched_policy());
+            BOOT_COUNT.fetch_add(1, Ordering::Relaxed);
+            // 只有CPU 2运行任务，避免其它CPU窃取任务
+            if get_cpu_id() == 2 {
+                run_idle();
+            }
+            loop {
+                std::thread::park();
+            }
+        });
+    }
+
+    init_cpu_id();
+    init_vsched(sched_policy());
+    while BOOT_COUNT.load(Ordering::Relaxed) < config::SMP {
+        core::hint::spin_loop();
+    }
+
+    // 阻塞的任务：在被唤醒时放入目标CPU
+    let waiter = spawn_new(
+        || {
+            READY.store(true, Ordering::Release);
+            WQ.wait_until(|| WOKEN.load(Ordering::Acquire));
+            get_cpu_id()
+        },
+        "waiter".into(),
+        config::TASK_STACK_SIZE,
+    )
+    .unwrap();
+    while !READY.load(Ordering::Acquire) {
+        yield_now();
+    }
+    assert!(migrate(waiter.task(), 2));
+    WOKEN.store(true, Ordering::Release);
+    WQ.notify_all(true);
+    assert_eq!(join_busily(waiter), 2);
+    println!("(1) blocked task woke up on the destination CPU");
+
+    // 就绪的任务：立即被移动到目标CPU的就绪队列
+    let ready = spawn_new(get_cpu_id, "ready".into(), config::TASK_STACK_SIZE).unwrap();
+    assert!(migrate(ready.task(), 2));
+    assert_eq!(join_busily(ready), 2);
+    println!("(2) ready task ran on the destination CPU");
+
+    // 正在运行的任务：在下一个调度点被移动到目标CPU
+    // CPU 2上总有一个就绪的任务，因此CPU 2不会从CPU 0窃取任务
+    let busy = TaskBuilder::new()
+        .name("busy".into())
+        .cpumask(AxCpuMask::one_shot(2))
+        .spawn(|| {
+            while !DONE.load(Ordering::Acquire) {
+                yield_now();
+            }
+        })
+        .unwrap();
+    let running = TaskBuilder::new()
+        .name("running".into())
+        .cpu(2)
+        .spawn_f(async {
+            assert_eq!(get_cpu_id(), 2);
+            while !MOVED.load(Ordering::Acquire) {
+                yield_now_f().await;
+            }
+            yield_now_f().await;
+            for _ in 0..ROUNDS {
+                assert_eq!(get_cpu_id(), 0);
+                yield_now_f().await;
+            }
+        })
+        .unwrap();
+    yield_now();
+    assert!(migrate(running.task(), 0));
+    MOVED.store(true, Ordering::Release);
+    running.join();
+    DONE.store(true, Ordering::Release);
+    busy.join();
+    println!("(3) running task was moved at its next scheduling point");
+
+    // 目标CPU不在任务的CPU亲和性掩码中时，迁移失败
+    let pinned = new(|| {}, "pinned".into(), config::TASK_STACK_SIZE);
+    pinned.set_cpumask(AxCpuMask::one_shot(0));
+    assert!(!migrate(&pinned, 2));
+    spawn(pinned.clone()).unwrap();
+    pinned.join();
+    println!("(4) migrating to a forbidden CPU failed");
+
+    // 有截止时间预留的任务只在接纳它的CPU上得到保证，不能被迁移或移出该CPU
+    if sched_policy() == SchedPolicy::Edf {
+        let reserved = TaskBuilder::new()
+            .name("reserved".into())
+            .cpumask(AxCpuMask::one_shot(0))
+            .spawn(|| {
+                assert!(set_deadline_params(1_000_000, 10_000_000, 10_000_000));
+                RESERVED.store(true, Ordering::Release);
+                // 预留任务的截止时间总是早于主任务，因此阻塞而不是让出，使主任务可以运行
+                WQ.wait_until(|| CHECKED.load(Ordering::Acquire));
+                get_cpu_id()
+            })
+            .unwrap();
+        while !RESERVED.load(Ordering::Acquire) {
+            yield_now();
+        }
+        assert!(!migrate(reserved.task(), 2));
+        assert!(!set_cpumask(reserved.task(), AxCpuMask::one_shot(2)));
+        CHECKED.store(true, Ordering::Release);
+        WQ.notify_all(true);
+        assert_eq!(join_busily(reserved), 0);
+        println!("(5) migrating a reserved task failed");
+    }
+
+    println!("back to main task");
+    exit(0)
+}
@@ -7,13 +7,13 @@ fn main() {
     static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
     let _thread_handle = std::thread::spawn(|| {
         init_cpu_id();
-        init_vsched_secondary();
+        init_vsched_secondary(sched_policy());
         BOOT_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         run_idle();
     });
 
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
     while BOOT_COUNT.load(std::sync::atomic::Ordering::Relaxed) < config::SMP {
         core::hint::spin_loop();
     }
@@ -24,7 +24,7 @@ fn main() {
         "main spawn_test".into(),
         config::TASK_STACK_SIZE,
     );
-    spawn(task.clone());
+    spawn(task.clone()).unwrap();
     task.join().unwrap();
     println!("main task wait ok");
     exit(0)
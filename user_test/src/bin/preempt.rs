@@ -0,0 +1,79 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use task_management::task_api::*;
+use user_test::*;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static COUNTERS: [AtomicUsize; 4] = [const { AtomicUsize::new(0) }; 4];
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    // FIFO调度策略不会抢占任务，因此默认使用时间片轮转。
+    let policy = match sched_policy() {
+        SchedPolicy::Fifo => SchedPolicy::RoundRobin,
+        policy => policy,
+    };
+    init_vsched(policy);
+
+    // 两个线程和两个协程均不主动让出，只能依靠时钟中断的抢占轮流运行
+    let tasks: Vec<_> = (0..COUNTERS.len())
+        .map(|i| {
+            let name = format!("task__{}", i + 1);
+            if i % 2 == 0 {
+                new(
+                    move || {
+                        while !STOP.load(Ordering::Acquire) {
+                            COUNTERS[i].fetch_add(1, Ordering::Relaxed);
+                        }
+                    },
+                    name,
+                    config::TASK_STACK_SIZE,
+                )
+            } else {
+                // 协程的一次poll不会返回，只能在poll的过程中被抢占
+                new_f(
+                    async move {
+                        while !STOP.load(Ordering::Acquire) {
+                            COUNTERS[i].fetch_add(1, Ordering::Relaxed);
+                        }
+                    },
+                    name,
+                )
+            }
+        })
+        .collect();
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    init_preempt_timer(Duration::from_millis(1));
+
+    // 主任务睡眠期间，各任务被抢占多次
+    sleep(Duration::from_millis(100));
+    STOP.store(true, Ordering::Release);
+    for task in &tasks {
+        task.join();
+    }
+
+    disable_preempt();
+    for (i, task) in tasks.iter().enumerate() {
+        let count = COUNTERS[i].load(Ordering::Relaxed);
+        let stats = task_stats(task);
+        println!(
+            "{}{} counted to {}, preempted {} times",
+            if i % 2 == 0 { "thread" } else { "coroutine" },
+            i + 1,
+            count,
+            stats.involuntary_switches
+        );
+        assert!(count > 0);
+        assert!(stats.involuntary_switches > 0);
+    }
+    println!("back to main task");
+    enable_preempt();
+    exit(0)
+}
@@ -0,0 +1,135 @@
+use std::{
+    hint::black_box,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use config::AxCpuMask;
+use task_management::{task::run_idle, task_api::*, wait_queue::WaitQueue};
+use user_test::*;
+
+/// 持锁或不持锁时空转的次数，使时钟中断常常在持有阻塞队列的锁时到来。
+const SPINS: usize = 2000;
+const RUN_TIME: Duration = Duration::from_millis(300);
+/// 所有任务都没有进展的时间超过该值时，认为持锁的任务被抢占而导致死锁。
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+static WQ: WaitQueue = WaitQueue::new();
+static STOP: AtomicBool = AtomicBool::new(false);
+static COUNTERS: [AtomicUsize; 4] = [const { AtomicUsize::new(0) }; 4];
+
+fn spin() {
+    for i in 0..SPINS {
+        black_box(i);
+    }
+}
+
+fn progress() -> usize {
+    COUNTERS
+        .iter()
+        .map(|counter| counter.load(Ordering::Relaxed))
+        .sum()
+}
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    assert!(
+        config::SMP >= 2,
+        "the preempt_lock test needs at least 2 CPUs"
+    );
+    // FIFO调度策略不会抢占任务，因此默认使用时间片轮转。
+    let policy = match sched_policy() {
+        SchedPolicy::Fifo => SchedPolicy::RoundRobin,
+        policy => policy,
+    };
+    static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
+    for _ in 0..(config::SMP - 1) {
+        let _thread_handle = std::thread::spawn(move || {
+            init_cpu_id();
+            init_vsched_secondary(policy);
+            init_preempt_timer(Duration::from_millis(1));
+            BOOT_COUNT.fetch_add(1, Ordering::Relaxed);
+            run_idle();
+        });
+    }
+
+    init_cpu_id();
+    init_vsched(policy);
+    while BOOT_COUNT.load(Ordering::Relaxed) < config::SMP {
+        core::hint::spin_loop();
+    }
+
+    // 所有任务都在CPU 1上争用同一个阻塞队列的锁，且都不主动让出，只能依靠时钟中断的抢占轮流运行。
+    // 若持锁的任务被抢占，其它任务在该锁上自旋，CPU 1将不再有进展。
+    let tasks: Vec<_> = (0..COUNTERS.len())
+        .map(|i| {
+            let task = new(
+                move || {
+                    while !STOP.load(Ordering::Acquire) {
+                        match i {
+                            0 | 1 => {
+                                let wq = WQ.queue.lock();
+                                spin();
+                                drop(wq);
+                            }
+                            2 => {
+                                WQ.wait_timeout(Duration::from_millis(1));
+                            }
+                            _ => {
+                                WQ.notify_one(true);
+                            }
+                        }
+                        spin();
+                        COUNTERS[i].fetch_add(1, Ordering::Relaxed);
+                    }
+                },
+                format!("task__{}", i + 1),
+                config::TASK_STACK_SIZE,
+            );
+            task.set_cpumask(AxCpuMask::one_shot(1));
+            spawn(task.clone()).unwrap();
+            task
+        })
+        .collect();
+
+    // CPU 0上的看门狗：运行至少`RUN_TIME`，且直到CPU 1上的任务仍有进展
+    let mut elapsed = Duration::ZERO;
+    let mut stalled = Duration::ZERO;
+    let mut last = progress();
+    while elapsed < RUN_TIME || stalled > Duration::ZERO {
+        sleep(Duration::from_millis(50));
+        elapsed += Duration::from_millis(50);
+        let now = progress();
+        if now == last {
+            stalled += Duration::from_millis(50);
+            assert!(
+                stalled < STALL_TIMEOUT,
+                "deadlock: no progress for {:?}",
+                stalled
+            );
+        } else {
+            stalled = Duration::ZERO;
+        }
+        last = now;
+    }
+    STOP.store(true, Ordering::Release);
+    WQ.notify_all(true);
+    for task in &tasks {
+        task.join();
+    }
+
+    for (i, task) in tasks.iter().enumerate() {
+        let count = COUNTERS[i].load(Ordering::Relaxed);
+        let stats = task_stats(task);
+        println!(
+            "task{} counted to {}, preempted {} times",
+            i + 1,
+            count,
+            stats.involuntary_switches
+        );
+        assert!(count > 0);
+    }
+    println!("preempt lock test ok");
+    exit(0)
+}
@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use task_management::{sync::Mutex, task_api::*};
+use user_test::*;
+
+static LOCK: Mutex<()> = Mutex::new(());
+static LOCKED: AtomicBool = AtomicBool::new(false);
+static RELEASE: AtomicBool = AtomicBool::new(false);
+
+const LOW_NICE: isize = 10;
+const HIGH_NICE: isize = -10;
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    // 只有CFS调度策略支持普通任务的优先级
+    init_vsched(SchedPolicy::Cfs);
+
+    // 低优先级线程持有锁时，高优先级协程阻塞在锁上，低优先级线程继承其优先级
+    // 打印顺序：(1) -> (2) -> (3) -> (4)
+    let low = new(
+        || {
+            let guard = LOCK.lock();
+            println!("(1) low priority thread locks the mutex");
+            LOCKED.store(true, Ordering::Release);
+            while !RELEASE.load(Ordering::Acquire) {
+                yield_now();
+            }
+            drop(guard);
+        },
+        "low".into(),
+        config::TASK_STACK_SIZE,
+    );
+    let high = new_f(
+        async {
+            let _guard = LOCK.lock_f().await;
+            println!("(3) high priority coroutine locks the mutex");
+        },
+        "high".into(),
+    );
+    assert!(set_priority(&low, LOW_NICE));
+    assert!(set_priority(&high, HIGH_NICE));
+
+    spawn(low.clone()).unwrap();
+    while !LOCKED.load(Ordering::Acquire) {
+        yield_now();
+    }
+    spawn(high.clone()).unwrap();
+    for _ in 0..100 {
+        if get_priority(&low) == HIGH_NICE {
+            break;
+        }
+        yield_now();
+    }
+    assert_eq!(get_priority(&low), HIGH_NICE);
+    println!("(2) low priority thread inherits nice {}", HIGH_NICE);
+
+    RELEASE.store(true, Ordering::Release);
+    high.join();
+    low.join();
+    assert_eq!(get_priority(&low), LOW_NICE);
+    println!("(4) low priority thread restores nice {}", LOW_NICE);
+
+    println!("back to main task");
+    exit(0)
+}
@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use config::AxCpuMask;
+use task_management::{task::run_idle, task_api::*, wait_queue::WaitQueue};
+use user_test::*;
+
+const ROUNDS: usize = 16;
+
+static WQ: WaitQueue = WaitQueue::new();
+static READY: AtomicBool = AtomicBool::new(false);
+static BUSY: AtomicBool = AtomicBool::new(false);
+static STOP: AtomicBool = AtomicBool::new(false);
+static ROUND: AtomicUsize = AtomicUsize::new(0);
+static ACK: AtomicUsize = AtomicUsize::new(0);
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    assert!(
+        config::SMP >= 2,
+        "the resched_ipi test needs at least 2 CPUs"
+    );
+    static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
+    for _ in 0..(config::SMP - 1) {
+        let _thread_handle = std::thread::spawn(|| {
+            init_cpu_id();
+            init_vsched_secondary(sched_policy());
+            // 不设置时钟中断，使CPU 1上的任务只能被重新调度请求抢占
+            init_resched_ipi();
+            BOOT_COUNT.fetch_add(1, Ordering::Relaxed);
+            run_idle();
+        });
+    }
+
+    init_cpu_id();
+    init_vsched(sched_policy());
+    while BOOT_COUNT.load(Ordering::Relaxed) < config::SMP {
+        core::hint::spin_loop();
+    }
+
+    // 实时任务在CPU 1上阻塞，每次被CPU 0唤醒时都应立即抢占CPU 1上的普通任务
+    let urgent = new(
+        || {
+            assert!(set_sched_class(SchedClass::RtFifo, 50));
+            READY.store(true, Ordering::Release);
+            for round in 1..=ROUNDS {
+                WQ.wait_until(|| ROUND.load(Ordering::Acquire) >= round);
+                assert_eq!(get_cpu_id(), 1);
+                ACK.store(round, Ordering::Release);
+            }
+        },
+        "urgent".into(),
+        config::TASK_STACK_SIZE,
+    );
+    urgent.set_cpumask(AxCpuMask::one_shot(1));
+    spawn(urgent.clone()).unwrap();
+    while !READY.load(Ordering::Acquire) {
+        yield_now();
+    }
+
+    // 普通任务从不让出CPU 1
+    let busy = new(
+        || {
+            BUSY.store(true, Ordering::Release);
+            while !STOP.load(Ordering::Acquire) {
+                core::hint::spin_loop();
+            }
+        },
+        "busy".into(),
+        config::TASK_STACK_SIZE,
+    );
+    busy.set_cpumask(AxCpuMask::one_shot(1));
+    spawn(busy.clone()).unwrap();
+    while !BUSY.load(Ordering::Acquire) {
+        yield_now();
+    }
+
+    for round in 1..=ROUNDS {
+        // 等待实时任务阻塞，使每一轮的唤醒都需要抢占普通任务
+        while WQ.queue.lock().is_empty() {
+            yield_now();
+        }
+        ROUND.store(round, Ordering::Release);
+        WQ.notify_one(true);
+        while ACK.load(Ordering::Acquire) < round {
+            yield_now();
+        }
+    }
+    println!("urgent task preempted the busy task {} times", ROUNDS);
+    urgent.join();
+
+    STOP.store(true, Ordering::Release);
+    busy.join();
+    disable_preempt();
+    let stats = task_stats(&busy);
+    println!(
+        "busy task was preempted {} times",
+        stats.involuntary_switches
+    );
+    assert!(stats.involuntary_switches >= ROUNDS);
+    enable_preempt();
+    exit(0)
+}
@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use task_management::task_api::*;
+use user_test::*;
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+static NORMAL_POS: AtomicUsize = AtomicUsize::new(usize::MAX);
+static RT_POS: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    init_vsched(sched_policy());
+
+    // 两个普通任务先后进入就绪队列，后者在就绪队列中被改为实时任务，
+    // 因此先于前者运行
+    let normal = new(
+        || NORMAL_POS.store(NEXT.fetch_add(1, Ordering::AcqRel), Ordering::Release),
+        "normal".into(),
+        config::TASK_STACK_SIZE,
+    );
+    let rt = new(
+        || RT_POS.store(NEXT.fetch_add(1, Ordering::AcqRel), Ordering::Release),
+        "rt".into(),
+        config::TASK_STACK_SIZE,
+    );
+    spawn(normal.clone()).unwrap();
+    spawn(rt.clone()).unwrap();
+    assert!(set_task_sched_class(&rt, SchedClass::RtFifo, 10));
+    assert!(rt.rt().is_rt());
+    assert!(!set_task_sched_class(
+        &normal,
+        SchedClass::RtFifo,
+        isize::MAX
+    ));
+
+    normal.join();
+    rt.join();
+    println!(
+        "rt task ran at {}, normal task ran at {}",
+        RT_POS.load(Ordering::Acquire),
+        NORMAL_POS.load(Ordering::Acquire)
+    );
+    assert_eq!(RT_POS.load(Ordering::Acquire), 0);
+    assert_eq!(NORMAL_POS.load(Ordering::Acquire), 1);
+    exit(0)
+}
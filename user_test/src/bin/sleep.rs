@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use task_management::{task_api::*, wait_queue::WaitQueue};
+use user_test::*;
+
+static WQ: WaitQueue = WaitQueue::new();
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    init_vsched(sched_policy());
+
+    // 按睡眠时长依次唤醒
+    // 打印顺序：(1) -> (2) -> (3) -> (4) -> (5)
+    let task1 = new(
+        || {
+            let start = current_time();
+            sleep(Duration::from_millis(40));
+            assert!(current_time() - start >= Duration::from_millis(40));
+            println!("(5) thread1 wakes up after 40ms");
+        },
+        "task__1".into(),
+        config::TASK_STACK_SIZE,
+    );
+    let task2 = new_f(
+        async {
+            let start = current_time();
+            sleep_f(Duration::from_millis(10)).await;
+            assert!(current_time() - start >= Duration::from_millis(10));
+            println!("(2) coroutine2 wakes up after 10ms");
+        },
+        "task__2".into(),
+    );
+    let task3 = new(
+        || {
+            let deadline = current_time() + Duration::from_millis(30);
+            sleep_until(deadline);
+            assert!(current_time() >= deadline);
+            println!("(4) thread3 wakes up at its deadline");
+        },
+        "task__3".into(),
+        config::TASK_STACK_SIZE,
+    );
+    // 等待超时
+    let task4 = new(
+        || {
+            let timeout = WQ.wait_timeout(Duration::from_millis(20));
+            assert!(timeout);
+            println!("(3) thread4 times out after 20ms");
+        },
+        "task__4".into(),
+        config::TASK_STACK_SIZE,
+    );
+    // 在超时前被唤醒
+    let task5 = new_f(
+        async {
+            let timeout = WQ.wait_timeout_f(Duration::from_secs(10)).await;
+            assert!(!timeout);
+            println!("(1) coroutine5 is notified before timeout");
+        },
+        "task__5".into(),
+    );
+    spawn(task5.clone()).unwrap();
+    spawn(task1.clone()).unwrap();
+    spawn(task2.clone()).unwrap();
+    spawn(task3.clone()).unwrap();
+
+    // 等待task5阻塞在等待队列中后再唤醒它，之后task4才进入等待队列
+    yield_now();
+    assert!(WQ.notify_one(false));
+    spawn(task4.clone()).unwrap();
+
+    task1.join();
+    task2.join();
+    task3.join();
+    task4.join();
+    task5.join();
+    println!("back to main task");
+    exit(0)
+}
@@ -1,20 +1,39 @@
+use std::sync::Arc;
+
 use task_management::task_api::*;
 use user_test::*;
 fn main() {
     env_logger::init();
     libvsched::load_and_init();
     init_cpu_id();
-    init_vsched();
-    // Due to the init_vsched will spawn the `gc` and `idle` task to the scheduler,
-    // the number must be not greater than `RQ_CAP - 2`.
-    for _ in 0..(config::RQ_CAP - 2) {
+    init_vsched(sched_policy());
+    // 当前任务正在运行，idle任务不在就绪队列中，因此就绪队列初始为空。
+    // FIFO和RR调度器的环形队列保留一个空槽以区分队列的满与空，因此少容纳一个任务。
+    let capacity = match sched_policy() {
+        SchedPolicy::Fifo | SchedPolicy::RoundRobin => config::RQ_CAP - 1,
+        SchedPolicy::Cfs | SchedPolicy::Edf => config::RQ_CAP,
+    };
+    for _ in 0..capacity {
         spawn(new(
             || {
                 println!("into spawned task inner");
             },
             "spawn_test".into(),
             config::TASK_STACK_SIZE,
-        ));
+        ))
+        .unwrap();
+    }
+    // 就绪队列已满时，spawn应返回错误并交还被拒绝的任务，而不是丢弃任务。
+    for _ in 0..4 {
+        let task = new(
+            || {
+                println!("into spawned task inner");
+            },
+            "spawn_overflow_test".into(),
+            config::TASK_STACK_SIZE,
+        );
+        let rejected = spawn(task.clone()).unwrap_err();
+        assert!(Arc::ptr_eq(&rejected, &task));
     }
     println!("spawn test ok");
     exit(0)
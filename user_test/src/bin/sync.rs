@@ -0,0 +1,273 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use task_management::{
+    sync::{Barrier, Condvar, Event, Latch, Mutex, RwLock, Semaphore},
+    task_api::*,
+};
+use user_test::*;
+
+const ROUNDS: usize = 3;
+
+static COUNTER: Mutex<usize> = Mutex::new(0);
+static TABLE: RwLock<Vec<usize>> = RwLock::new(Vec::new());
+static READY: Mutex<bool> = Mutex::new(false);
+static CV: Condvar = Condvar::new();
+static SEM: Semaphore = Semaphore::new(2);
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static BARRIER: Barrier = Barrier::new(4);
+static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+static LEADERS: AtomicUsize = AtomicUsize::new(0);
+static LATCH: Latch = Latch::new(3);
+static MANUAL: Event = Event::new_manual_reset(false);
+static AUTO: Event = Event::new_auto_reset(false);
+static WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    init_vsched(sched_policy());
+
+    // Mutex：持有锁时让出，其它任务无法进入临界区，因此计数不会丢失
+    let mut tasks = Vec::new();
+    for i in 0..2 {
+        tasks.push(new(
+            || {
+                for _ in 0..ROUNDS {
+                    let mut counter = COUNTER.lock();
+                    let value = *counter;
+                    yield_now();
+                    *counter = value + 1;
+                }
+            },
+            format!("thread_{}", i),
+            config::TASK_STACK_SIZE,
+        ));
+        tasks.push(new_f(
+            async {
+                for _ in 0..ROUNDS {
+                    let mut counter = COUNTER.lock_f().await;
+                    let value = *counter;
+                    yield_now_f().await;
+                    *counter = value + 1;
+                }
+            },
+            format!("coroutine_{}", i),
+        ));
+    }
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    assert_eq!(*COUNTER.lock(), 4 * ROUNDS);
+    println!("(1) mutex counted to {}", 4 * ROUNDS);
+
+    // RwLock：写者每次写入两个元素，读者总是看到偶数个元素
+    tasks.push(new_f(
+        async {
+            for i in 0..ROUNDS {
+                let mut table = TABLE.write_f().await;
+                table.push(i);
+                yield_now_f().await;
+                table.push(i);
+            }
+        },
+        "writer".into(),
+    ));
+    tasks.push(new(
+        || {
+            for _ in 0..ROUNDS {
+                let table = TABLE.read();
+                assert_eq!(table.len() % 2, 0);
+                yield_now();
+            }
+        },
+        "reader_thread".into(),
+        config::TASK_STACK_SIZE,
+    ));
+    tasks.push(new_f(
+        async {
+            for _ in 0..ROUNDS {
+                let table = TABLE.read_f().await;
+                assert_eq!(table.len() % 2, 0);
+                yield_now_f().await;
+            }
+        },
+        "reader_coroutine".into(),
+    ));
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    assert_eq!(TABLE.read().len(), 2 * ROUNDS);
+    println!("(2) rwlock wrote {} items", 2 * ROUNDS);
+
+    // Condvar：等待者在条件成立前阻塞，被主任务唤醒
+    tasks.push(new(
+        || {
+            let ready = CV.wait_while(READY.lock(), |ready| !*ready);
+            assert!(*ready);
+        },
+        "waiter_thread".into(),
+        config::TASK_STACK_SIZE,
+    ));
+    tasks.push(new_f(
+        async {
+            let ready = CV.wait_while_f(READY.lock_f().await, |ready| !*ready).await;
+            assert!(*ready);
+        },
+        "waiter_coroutine".into(),
+    ));
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    yield_now();
+    *READY.lock() = true;
+    CV.notify_all();
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    println!("(3) condvar waiters are notified");
+
+    // Semaphore：最多两个任务同时持有许可
+    for i in 0..2 {
+        tasks.push(new(
+            || {
+                for _ in 0..ROUNDS {
+                    SEM.acquire();
+                    assert!(ACTIVE.fetch_add(1, Ordering::AcqRel) < 2);
+                    yield_now();
+                    ACTIVE.fetch_sub(1, Ordering::AcqRel);
+                    SEM.release();
+                }
+            },
+            format!("sem_thread_{}", i),
+            config::TASK_STACK_SIZE,
+        ));
+        tasks.push(new_f(
+            async {
+                for _ in 0..ROUNDS {
+                    SEM.acquire_f().await;
+                    assert!(ACTIVE.fetch_add(1, Ordering::AcqRel) < 2);
+                    yield_now_f().await;
+                    ACTIVE.fetch_sub(1, Ordering::AcqRel);
+                    SEM.release();
+                }
+            },
+            format!("sem_coroutine_{}", i),
+        ));
+    }
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    assert_eq!(SEM.available_permits(), 2);
+    println!("(4) semaphore limits the concurrency to 2");
+
+    // Barrier：所有任务到达后才继续运行，且只有一个领导者
+    for i in 0..2 {
+        tasks.push(new(
+            || {
+                for round in 1..=ROUNDS {
+                    ARRIVED.fetch_add(1, Ordering::AcqRel);
+                    if BARRIER.wait().is_leader() {
+                        LEADERS.fetch_add(1, Ordering::AcqRel);
+                    }
+                    assert!(ARRIVED.load(Ordering::Acquire) >= 4 * round);
+                    // 等待所有任务检查完毕后，再进入下一轮
+                    BARRIER.wait();
+                }
+            },
+            format!("barrier_thread_{}", i),
+            config::TASK_STACK_SIZE,
+        ));
+        tasks.push(new_f(
+            async {
+                for round in 1..=ROUNDS {
+                    ARRIVED.fetch_add(1, Ordering::AcqRel);
+                    if BARRIER.wait_f().await.is_leader() {
+                        LEADERS.fetch_add(1, Ordering::AcqRel);
+                    }
+                    assert!(ARRIVED.load(Ordering::Acquire) >= 4 * round);
+                    BARRIER.wait_f().await;
+                }
+            },
+            format!("barrier_coroutine_{}", i),
+        ));
+    }
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    assert_eq!(LEADERS.load(Ordering::Acquire), ROUNDS);
+    println!("(5) barrier passed {} rounds", ROUNDS);
+
+    // Latch：主任务等待三个协程倒计数
+    for i in 0..LATCH.count() {
+        tasks.push(new_f(
+            async {
+                yield_now_f().await;
+                LATCH.count_down();
+            },
+            format!("latch_coroutine_{}", i),
+        ));
+    }
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    LATCH.wait();
+    assert_eq!(LATCH.count(), 0);
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    println!("(6) latch is opened");
+
+    // Event：手动重置的事件唤醒所有等待者，自动重置的事件每次唤醒一个等待者
+    tasks.push(new(
+        || {
+            MANUAL.wait();
+            AUTO.wait();
+            WOKEN.fetch_add(1, Ordering::AcqRel);
+        },
+        "event_thread".into(),
+        config::TASK_STACK_SIZE,
+    ));
+    tasks.push(new_f(
+        async {
+            MANUAL.wait_f().await;
+            AUTO.wait_f().await;
+            WOKEN.fetch_add(1, Ordering::AcqRel);
+        },
+        "event_coroutine".into(),
+    ));
+    for task in &tasks {
+        spawn(task.clone()).unwrap();
+    }
+    yield_now();
+    MANUAL.set();
+    yield_now();
+    assert!(MANUAL.is_set());
+    AUTO.set();
+    while WOKEN.load(Ordering::Acquire) < 1 {
+        yield_now();
+    }
+    assert!(!AUTO.is_set());
+    assert_eq!(WOKEN.load(Ordering::Acquire), 1);
+    AUTO.set();
+    for task in tasks.drain(..) {
+        task.join();
+    }
+    assert_eq!(WOKEN.load(Ordering::Acquire), 2);
+    println!("(7) events wake up the waiters");
+
+    println!("back to main task");
+    exit(0)
+}
@@ -0,0 +1,78 @@
+use config::AxCpuMask;
+use task_management::task_api::*;
+use user_test::*;
+
+const SMALL_STACK_SIZE: usize = 0x8000;
+
+struct Tag(&'static str);
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    init_cpu_id();
+    // 只有CFS调度策略支持普通任务的优先级
+    init_vsched(SchedPolicy::Cfs);
+
+    // 名称、优先级、CPU和任务局部变量在任务运行前已经生效
+    let thread = TaskBuilder::new()
+        .name("builder_thread".into())
+        .stack_size(SMALL_STACK_SIZE)
+        .priority(5)
+        .cpumask(AxCpuMask::one_shot(0))
+        .cpu(0)
+        .local(Tag("thread"))
+        .local(1usize)
+        .spawn(|| {
+            with_task_local(|count: &mut usize| *count += 1);
+            let tag = with_task_local(|tag: &mut Tag| tag.0).unwrap();
+            let count = with_task_local(|count: &mut usize| *count).unwrap();
+            format!("{} {}", tag, count)
+        })
+        .unwrap();
+    assert_eq!(thread.task().name(), "builder_thread");
+    assert_eq!(get_priority(thread.task()), 5);
+    assert_eq!(thread.join().as_deref(), Some("thread 2"));
+    println!("(1) thread configured by the builder");
+
+    // 使用不同栈大小的协程交替运行，各自获得所需大小的栈
+    let mut handles = Vec::new();
+    for i in 0..4 {
+        let stack_size = if i % 2 == 0 {
+            SMALL_STACK_SIZE
+        } else {
+            config::TASK_STACK_SIZE
+        };
+        handles.push(
+            TaskBuilder::new()
+                .name(format!("builder_coroutine_{}", i))
+                .stack_size(stack_size)
+                .local(i)
+                .spawn_f(async move {
+                    for _ in 0..3 {
+                        yield_now_f().await;
+                    }
+                    assert_eq!(with_task_local(|i: &mut usize| *i), Some(i));
+                    assert_eq!(set_task_local(i + 1), Some(i));
+                    with_task_local(|i: &mut usize| *i).unwrap()
+                })
+                .unwrap(),
+        );
+    }
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join(), Some(i + 1));
+    }
+    println!("(2) coroutines ran with their own stack sizes");
+
+    // 分离的任务不能被等待
+    let detached = TaskBuilder::new()
+        .name("detached".into())
+        .detached(true)
+        .spawn(|| 0)
+        .unwrap();
+    assert!(detached.task().is_detached());
+    assert_eq!(detached.join(), None);
+    println!("(3) detached task cannot be joined");
+
+    println!("back to main task");
+    exit(0)
+}
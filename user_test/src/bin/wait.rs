@@ -4,7 +4,7 @@ fn main() {
     env_logger::init();
     libvsched::load_and_init();
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
     // task3等待task2，task2等待task1
     // 运行顺序：task3 -> task2 -> task1 -> task2 -> task3
     // 打印顺序：(1) -> (2) -> (3) -> (4) -> (5)
@@ -34,9 +34,9 @@ fn main() {
         },
         "task__3".into(),
     );
-    spawn(task3.clone());
-    spawn(task2.clone());
-    spawn(task1.clone());
+    spawn(task3.clone()).unwrap();
+    spawn(task2.clone()).unwrap();
+    spawn(task1.clone()).unwrap();
 
     yield_now();
 
@@ -0,0 +1,147 @@
+use std::{
+    pin::Pin,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use config::AxCpuMask;
+use task_management::{task::run_idle, task_api::*};
+use user_test::*;
+
+const PAIRS: usize = 6;
+const ROUNDS: usize = 2000;
+/// 所有协程都没有进展的时间超过该值时，认为有唤醒丢失。
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 一对协程与通知线程之间的通知槽。
+struct Slot {
+    ready: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+    ack: AtomicUsize,
+}
+
+impl Slot {
+    const fn new() -> Self {
+        Self {
+            ready: AtomicBool::new(false),
+            waker: Mutex::new(None),
+            ack: AtomicUsize::new(0),
+        }
+    }
+
+    fn notify(&self) {
+        self.ready.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+static SLOTS: [Slot; PAIRS] = [const { Slot::new() }; PAIRS];
+
+/// 等待槽被通知的`Future`，先注册`Waker`再检查通知，因此通知可能发生在poll返回之前。
+struct Notified(&'static Slot);
+
+impl Future for Notified {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        *self.0.waker.lock().unwrap() = Some(cx.waker().clone());
+        if self.0.ready.swap(false, Ordering::AcqRel) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+fn progress() -> usize {
+    SLOTS
+        .iter()
+        .map(|slot| slot.ack.load(Ordering::Acquire))
+        .sum()
+}
+
+fn main() {
+    env_logger::init();
+    libvsched::load_and_init();
+    assert!(
+        config::SMP >= 4,
+        "the waker_stress test needs at least 4 CPUs"
+    );
+    static BOOT_COUNT: AtomicUsize = AtomicUsize::new(1);
+    for _ in 0..(config::SMP - 1) {
+        let _thread_handle = std::thread::spawn(|| {
+            init_cpu_id();
+            init_vsched_secondary(sched_policy());
+            BOOT_COUNT.fetch_add(1, Ordering::Relaxed);
+            run_idle();
+        });
+    }
+
+    init_cpu_id();
+    init_vsched(sched_policy());
+    while BOOT_COUNT.load(Ordering::Relaxed) < config::SMP {
+        core::hint::spin_loop();
+    }
+
+    // 每个协程都在CPU 1~3中的一个上等待，由另一个CPU上的线程通知，使唤醒与协程的poll并发进行
+    let mut handles = Vec::new();
+    for (i, slot) in SLOTS.iter().enumerate() {
+        let waiter = TaskBuilder::new()
+            .name(format!("waiter_{}", i))
+            .cpumask(AxCpuMask::one_shot(1 + i % 3))
+            .spawn_f(async move {
+                for round in 1..=ROUNDS {
+                    Notified(slot).await;
+                    slot.ack.store(round, Ordering::Release);
+                }
+            })
+            .unwrap();
+        let notifier = TaskBuilder::new()
+            .name(format!("notifier_{}", i))
+            .cpumask(AxCpuMask::one_shot(1 + (i + 1) % 3))
+            .spawn(move || {
+                for round in 1..=ROUNDS {
+                    slot.notify();
+                    while slot.ack.load(Ordering::Acquire) < round {
+                        yield_now();
+                    }
+                }
+            })
+            .unwrap();
+        handles.push((waiter, notifier));
+    }
+
+    // CPU 0上的看门狗：唤醒丢失时，对应的协程和通知线程都不再有进展
+    let mut last = progress();
+    let mut stalled = Duration::ZERO;
+    while last < PAIRS * ROUNDS {
+        sleep(Duration::from_millis(100));
+        let now = progress();
+        if now == last {
+            stalled += Duration::from_millis(100);
+            assert!(
+                stalled < STALL_TIMEOUT,
+                "lost wakeup: no progress for {:?} at {}/{}",
+                stalled,
+                now,
+                PAIRS * ROUNDS
+            );
+        } else {
+            stalled = Duration::ZERO;
+        }
+        last = now;
+    }
+
+    for (waiter, notifier) in handles {
+        waiter.join();
+        notifier.join();
+    }
+    println!("{} wakeups across CPUs without a loss", PAIRS * ROUNDS);
+    exit(0)
+}
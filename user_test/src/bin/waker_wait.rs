@@ -1,53 +1,129 @@
-use std::sync::Arc;
+use std::{
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+};
 
-use task_management::{task_api::*, waker_queue::WakerQueue};
+use task_management::{task_api::*, wait_queue::WaitQueue};
 use user_test::*;
+
+/// 不依赖vsched协程的`Waker`，被唤醒时只设置标志。
+struct FlagWaker(AtomicBool);
+
+impl Wake for FlagWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// 在线程中轮询`future`直到完成，两次轮询之间让出CPU。
+fn block_on<F: Future>(future: F) -> F::Output {
+    let flag = Arc::new(FlagWaker(AtomicBool::new(true)));
+    let waker = Waker::from(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if flag.0.swap(false, Ordering::AcqRel) {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+        yield_now();
+    }
+}
+
 fn main() {
     env_logger::init();
     libvsched::load_and_init();
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
 
-    let queue = Arc::new(WakerQueue::new());
-    let queue_clone = queue.clone();
-    let task1 = new_f(
-        async move {
-            println!("task1: before wait");
-            queue_clone.wait_f().await;
-            println!("task1: after wait");
-        },
-        "task__1".into(),
-    );
+    // 协程通过vsched的`Waker`等待阻塞队列
+    let queue = Arc::new(WaitQueue::new());
+    let tasks: Vec<_> = (1..=3)
+        .map(|i| {
+            let queue = queue.clone();
+            new_f(
+                async move {
+                    println!("task{}: before wait", i);
+                    queue.wait_waker().await;
+                    println!("task{}: after wait", i);
+                },
+                format!("task__{}", i),
+            )
+        })
+        .collect();
+    for task in tasks.iter().rev() {
+        spawn(task.clone()).unwrap();
+    }
+
+    yield_now();
+    assert_eq!(queue.len(), 3);
+    println!("notify all");
+    queue.notify_all(true);
+    yield_now();
+
+    for task in &tasks {
+        task.join();
+    }
+
+    // 线程通过自定义的`Waker`等待阻塞队列，与阻塞在队列上的线程一起被唤醒
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
     let queue_clone = queue.clone();
-    let task2 = new_f(
-        async move {
-            println!("task2: before wait");
-            queue_clone.wait_f().await;
-            println!("task2: after wait");
+    let polled = new(
+        move || {
+            block_on(queue_clone.wait_until_waker(|| COUNT.load(Ordering::Acquire) > 0));
+            println!("polled thread: woken by a custom waker");
         },
-        "task__2".into(),
+        "polled".into(),
+        config::TASK_STACK_SIZE,
     );
     let queue_clone = queue.clone();
-    let task3 = new_f(
-        async move {
-            println!("task3: before wait");
-            queue_clone.wait_f().await;
-            println!("task3: after wait");
+    let blocked = new(
+        move || {
+            queue_clone.wait_until(|| COUNT.load(Ordering::Acquire) > 0);
+            println!("blocked thread: woken by vsched");
         },
-        "task__3".into(),
+        "blocked".into(),
+        config::TASK_STACK_SIZE,
     );
-    spawn(task3.clone());
-    spawn(task2.clone());
-    spawn(task1.clone());
-
-    yield_now();
-    println!("notify all");
+    spawn(polled.clone()).unwrap();
+    spawn(blocked.clone()).unwrap();
+    while queue.len() < 2 {
+        yield_now();
+    }
+    COUNT.fetch_add(1, Ordering::Release);
     queue.notify_all(true);
-    yield_now();
+    polled.join();
+    blocked.join();
+    assert!(queue.is_empty());
+
+    // 同一任务的多个`Future`使用相同的`Waker`，但各自占用阻塞队列中的一个等待项
+    let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut first = Box::pin(queue.wait_waker());
+    let mut second = Box::pin(queue.wait_waker());
+    let mut third = Box::pin(queue.wait_waker());
+    assert!(first.as_mut().poll(&mut cx).is_pending());
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+    assert!(third.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(queue.len(), 3);
+    queue.notify_one(false);
+    assert!(flag.0.swap(false, Ordering::AcqRel));
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+    assert!(first.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(queue.len(), 2);
+    // 被唤醒但未被轮询就被丢弃的`Future`将唤醒转交给下一个等待者
+    queue.notify_one(false);
+    drop(second);
+    assert!(queue.is_empty());
+    assert!(third.as_mut().poll(&mut cx).is_ready());
+    println!("futures sharing a waker are woken one by one");
 
-    task1.join();
-    task2.join();
-    task3.join();
     println!("back to idle task");
     exit(0)
 }
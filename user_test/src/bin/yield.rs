@@ -4,7 +4,7 @@ fn main() {
     env_logger::init();
     libvsched::load_and_init();
     init_cpu_id();
-    init_vsched();
+    init_vsched(sched_policy());
 
     // 运行顺序： main task -> thread1 -> coroutine2 -> coroutine3 -> main task -> thread1 -> coroutine2 -> coroutine3
     // 打印顺序：(1) -> (2) -> (3) -> (4) -> (5) -> (6) -> (7) -> (8)
@@ -33,12 +33,22 @@ fn main() {
         },
         "task__3".into(),
     );
-    spawn(task1);
-    spawn(task2);
-    spawn(task3);
+    let tasks = [task1.clone(), task2.clone(), task3.clone()];
+    spawn(task1).unwrap();
+    spawn(task2).unwrap();
+    spawn(task3).unwrap();
     println!("(1) main task before yield");
     yield_now();
     println!("(5) back to main task");
+    // 每个任务都让出了一次，让出时任务仍可运行，因此计为被动切换。
+    for task in tasks.iter() {
+        let stats = task_stats(task);
+        assert_eq!(stats.involuntary_switches, 1);
+        assert_eq!(stats.voluntary_switches, 0);
+        // 没有时钟中断时，调度器的时钟也会在每次切换前更新，因此时间统计不为0。
+        assert!(stats.runtime > 0);
+        assert!(stats.wait_time > 0);
+    }
     yield_now();
     exit(0)
 }
@@ -1,11 +1,21 @@
 #![feature(unsafe_cell_access)]
 
-use std::{cell::RefCell, sync::atomic::AtomicUsize};
+use std::{
+    cell::RefCell,
+    sync::{
+        LazyLock,
+        atomic::{AtomicI32, AtomicU32, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use crate_interface::impl_interface;
 use libvsched::{MappingFlags, MemIf};
 use memmap2::{Mmap, MmapMut};
-use task_management::interface::{SMPIf, TaskIf};
+use task_management::{
+    interface::{IdleIf, SMPIf, TaskIf, TimeIf},
+    task_api::SchedPolicy,
+};
 extern crate alloc;
 
 // mod vsched;
@@ -16,6 +26,9 @@ pub static CPU_NUM: usize = config::SMP;
 
 static CPU_ID_ALLOCATOR: AtomicUsize = AtomicUsize::new(0);
 
+/// 各CPU对应的OS线程的ID，在[`init_resched_ipi`]中记录，为0表示该CPU不接收重新调度请求。
+static CPU_TIDS: [AtomicI32; config::SMP] = [const { AtomicI32::new(0) }; config::SMP];
+
 thread_local! {
     pub static CPU_ID: RefCell<usize> = RefCell::new(0);
 }
@@ -25,7 +38,17 @@ pub fn get_cpu_id() -> usize {
 }
 
 pub fn init_cpu_id() {
-    CPU_ID.set(CPU_ID_ALLOCATOR.fetch_add(1, std::sync::atomic::Ordering::Relaxed));
+    CPU_ID.set(CPU_ID_ALLOCATOR.fetch_add(1, Ordering::Relaxed));
+}
+
+/// 根据环境变量`SCHED`（`fifo`、`rr`、`cfs`或`edf`）选择调度策略，默认为FIFO。
+pub fn sched_policy() -> SchedPolicy {
+    match std::env::var("SCHED").as_deref() {
+        Ok("rr") => SchedPolicy::RoundRobin,
+        Ok("cfs") => SchedPolicy::Cfs,
+        Ok("edf") => SchedPolicy::Edf,
+        _ => SchedPolicy::Fifo,
+    }
 }
 
 struct SMPIfImpl;
@@ -35,6 +58,16 @@ impl SMPIf for SMPIfImpl {
     fn get_cpu_id() -> usize {
         get_cpu_id()
     }
+
+    /// 向CPU对应的OS线程发送`SIGUSR1`信号。
+    ///
+    /// 若该CPU未调用[`init_resched_ipi`]，则不发送信号，被唤醒的任务在该CPU下一次调度时运行。
+    fn send_resched_ipi(cpu_id: usize) {
+        let tid = CPU_TIDS[cpu_id].load(Ordering::Acquire);
+        if tid != 0 {
+            unsafe { libc::syscall(libc::SYS_tgkill, libc::getpid(), tid, libc::SIGUSR1) };
+        }
+    }
 }
 
 struct TaskIfImpl;
@@ -46,6 +79,138 @@ impl TaskIf for TaskIfImpl {
     }
 }
 
+/// 单调时间的起点，在首次获取时间时确定。
+static START_TIME: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+struct TimeIfImpl;
+
+#[impl_interface]
+impl TimeIf for TimeIfImpl {
+    fn current_time() -> Duration {
+        START_TIME.elapsed()
+    }
+}
+
+struct IdleIfImpl;
+
+/// 通过futex阻塞和唤醒空闲CPU（即其对应的OS线程）。
+///
+/// vVAR可能在多个地址空间中共享，因此不使用`FUTEX_PRIVATE_FLAG`。
+#[impl_interface]
+impl IdleIf for IdleIfImpl {
+    fn wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+        let timeout = libc::timespec {
+            tv_sec: timeout.as_secs() as _,
+            tv_nsec: timeout.subsec_nanos() as _,
+        };
+        // 被信号打断、超时或`word`已改变时返回，均由调用者重新检查。
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                word.as_ptr(),
+                libc::FUTEX_WAIT,
+                expected,
+                &timeout as *const libc::timespec,
+            )
+        };
+    }
+
+    fn wake(word: &AtomicU32) {
+        unsafe { libc::syscall(libc::SYS_futex, word.as_ptr(), libc::FUTEX_WAKE, 1) };
+    }
+}
+
+/// 时钟信号的处理函数，处理当前CPU的时钟中断，并可能在其中抢占当前任务。
+///
+/// 被抢占的任务在恢复运行后才从该函数返回，因此需要保存和恢复`errno`。
+#[cfg(feature = "preempt")]
+extern "C" fn preempt_timer_handler(_signo: libc::c_int) {
+    let errno = unsafe { *libc::__errno_location() };
+    task_management::task_api::timer_tick();
+    unsafe { *libc::__errno_location() = errno };
+}
+
+/// 为当前CPU（即当前OS线程）创建一个间隔为`interval`的定时器，作为该CPU的时钟中断。
+///
+/// 定时器到期时向当前OS线程发送`SIGALRM`信号，其处理函数调用
+/// [`timer_tick`](task_management::task_api::timer_tick)。
+/// 信号处理函数可能切换到其它任务，因此使用`SA_NODEFER`，使切换后的任务不会屏蔽该信号。
+///
+/// 应在当前CPU初始化调度器后调用。
+#[cfg(feature = "preempt")]
+pub fn init_preempt_timer(interval: Duration) {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = preempt_timer_handler as extern "C" fn(libc::c_int) as usize;
+        action.sa_flags = libc::SA_NODEFER | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        assert_eq!(
+            libc::sigaction(libc::SIGALRM, &action, std::ptr::null_mut()),
+            0,
+            "sigaction failed"
+        );
+
+        let mut event: libc::sigevent = std::mem::zeroed();
+        event.sigev_notify = libc::SIGEV_THREAD_ID;
+        event.sigev_signo = libc::SIGALRM;
+        event.sigev_notify_thread_id = libc::syscall(libc::SYS_gettid) as libc::c_int;
+        let mut timer: libc::timer_t = std::mem::zeroed();
+        assert_eq!(
+            libc::timer_create(libc::CLOCK_MONOTONIC, &mut event, &mut timer),
+            0,
+            "timer_create failed"
+        );
+
+        let interval = libc::timespec {
+            tv_sec: interval.as_secs() as _,
+            tv_nsec: interval.subsec_nanos() as _,
+        };
+        let spec = libc::itimerspec {
+            it_interval: interval,
+            it_value: interval,
+        };
+        assert_eq!(
+            libc::timer_settime(timer, 0, &spec, std::ptr::null_mut()),
+            0,
+            "timer_settime failed"
+        );
+    }
+}
+
+/// 重新调度信号的处理函数，处理其它CPU发送到当前CPU的重新调度请求，并可能在其中抢占当前任务。
+///
+/// 与[`preempt_timer_handler`]相同，需要保存和恢复`errno`。
+#[cfg(feature = "preempt")]
+extern "C" fn resched_ipi_handler(_signo: libc::c_int) {
+    let errno = unsafe { *libc::__errno_location() };
+    task_management::task_api::resched_ipi();
+    unsafe { *libc::__errno_location() = errno };
+}
+
+/// 使当前CPU（即当前OS线程）接收其它CPU发送的重新调度请求，作为用户态的核间中断。
+///
+/// 其它CPU唤醒应抢占当前CPU上的任务时，向当前OS线程发送`SIGUSR1`信号，其处理函数调用
+/// [`resched_ipi`](task_management::task_api::resched_ipi)。
+/// 与[`init_preempt_timer`]相同，信号处理函数使用`SA_NODEFER`。
+///
+/// 应在当前CPU初始化调度器后调用。
+#[cfg(feature = "preempt")]
+pub fn init_resched_ipi() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = resched_ipi_handler as extern "C" fn(libc::c_int) as usize;
+        action.sa_flags = libc::SA_NODEFER | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        assert_eq!(
+            libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()),
+            0,
+            "sigaction failed"
+        );
+        let tid = libc::syscall(libc::SYS_gettid) as i32;
+        CPU_TIDS[get_cpu_id()].store(tid, Ordering::Release);
+    }
+}
+
 struct MemIfImpl;
 
 #[impl_interface]
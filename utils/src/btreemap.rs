@@ -432,6 +432,65 @@ where
         None
     }
 
+    /// 删除指定键的键值对，返回其值
+    /// 键不存在时返回None
+    pub fn remove(&self, key: &K) -> Option<V> {
+        for i in 0..CAPACITY {
+            if let Some(version) = self.slot_states[i].get_version_if_valid() {
+                unsafe {
+                    let entry_ptr = (*self.storage[i].get()).as_ptr();
+                    if entry_ptr.is_null() {
+                        continue;
+                    }
+                    let entry = &*entry_ptr;
+                    if entry.key != *key || entry.slot_version != version as usize {
+                        continue;
+                    }
+
+                    if let Some(locked_version) = self.slot_states[i].try_lock_for_delete() {
+                        // 双重检查：锁定后版本号和键仍要匹配，避免ABA问题
+                        if locked_version == version && entry.key == *key {
+                            let value = entry.value.clone();
+
+                            // 清理槽位
+                            ptr::drop_in_place((*self.storage[i].get()).as_mut_ptr());
+                            self.slot_states[i].mark_empty();
+                            self.len.fetch_sub(1, Ordering::Relaxed);
+
+                            return Some(value);
+                        }
+                        // 如果验证失败，恢复状态
+                        self.slot_states[i].mark_valid(locked_version);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// 删除满足`f`的最小键值对，其它元素的位置不变
+    /// 找到的元素被其它线程并发删除时重试，限制重试次数避免活锁
+    pub fn remove_first_by<F>(&self, mut f: F) -> Option<(K, V)>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        const MAX_RETRIES: usize = 16;
+
+        for _retry in 0..MAX_RETRIES {
+            let mut first: Option<K> = None;
+            self.for_each(|key, value| {
+                if first.as_ref().is_none_or(|first| key < first) && f(key, value) {
+                    first = Some(key.clone());
+                }
+            });
+            let key = first?;
+            if let Some(value) = self.remove(&key) {
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
     /// 检查是否为空
     pub fn is_empty(&self) -> bool {
         self.len.load(Ordering::Acquire) == 0
@@ -648,6 +707,22 @@ mod tests {
         assert_eq!(count, 3);
     }
 
+    #[test]
+    fn test_remove_first_by() {
+        let map: LockFreeBTreeMap<i32, &'static str, 10> = LockFreeBTreeMap::new();
+
+        map.insert(3, "three");
+        map.insert(1, "one");
+        map.insert(2, "two");
+
+        // 删除满足条件的最小键，其它元素不受影响
+        assert_eq!(map.remove_first_by(|&k, _| k % 2 == 0), Some((2, "two")));
+        assert_eq!(map.remove_first_by(|&k, _| k % 2 == 0), None);
+        assert_eq!(map.exact_len(), 2);
+        assert_eq!(map.pop_first(), Some((1, "one")));
+        assert_eq!(map.pop_first(), Some((3, "three")));
+    }
+
     #[test]
     fn test_clear() {
         let map: LockFreeBTreeMap<i32, &'static str, 10> = LockFreeBTreeMap::new();
@@ -664,6 +739,34 @@ mod tests {
         assert!(map.is_empty());
     }
 
+    #[test]
+    fn test_remove() {
+        let map: LockFreeBTreeMap<i32, &'static str, 10> = LockFreeBTreeMap::new();
+
+        map.insert(1, "one");
+        map.insert(2, "two");
+        map.insert(3, "three");
+
+        // 删除中间的键
+        assert_eq!(map.remove(&2), Some("two"));
+        assert_eq!(map.remove(&2), None);
+        assert_eq!(map.remove(&4), None);
+        assert_eq!(map.exact_len(), 2);
+
+        // 剩余元素仍然有序
+        assert_eq!(map.pop_first(), Some((1, "one")));
+        assert_eq!(map.pop_first(), Some((3, "three")));
+        assert!(map.is_empty());
+
+        // 删除后的槽位可以重新使用
+        let full: LockFreeBTreeMap<i32, &'static str, 2> = LockFreeBTreeMap::new();
+        assert!(full.insert(1, "one").is_some());
+        assert!(full.insert(2, "two").is_some());
+        assert!(full.insert(3, "three").is_none());
+        assert_eq!(full.remove(&1), Some("one"));
+        assert!(full.insert(3, "three").is_some());
+    }
+
     // 模拟MPMC场景的测试（无真实多线程，但测试逻辑正确性）
     #[test]
     fn test_simulated_concurrent_operations() {
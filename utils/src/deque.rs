@@ -10,6 +10,8 @@ const SLOT_EMPTY: u8 = 0;
 const SLOT_WRITING: u8 = 1;
 const SLOT_READY: u8 = 2;
 const SLOT_READING: u8 = 3;
+// The item is removed from the middle of the deque, and the slot is skipped by pops
+const SLOT_REMOVED: u8 = 4;
 
 struct Slot<T> {
     data: UnsafeCell<MaybeUninit<T>>,
@@ -249,6 +251,9 @@ impl<T, const CAPACITY: usize> LockFreeDeque<T, CAPACITY> {
                     if current_state == SLOT_EMPTY {
                         // Slot became empty, queue might be empty now
                         return None;
+                    } else if current_state == SLOT_REMOVED {
+                        // Skip the removed item
+                        self.skip_removed(slot, &self.head, head, (head + 1) % CAPACITY);
                     } else if current_state == SLOT_WRITING {
                         // Slot is being written to, wait a bit
                         for _ in 0..10 {
@@ -323,6 +328,9 @@ impl<T, const CAPACITY: usize> LockFreeDeque<T, CAPACITY> {
                     if current_state == SLOT_EMPTY {
                         // Slot became empty, queue might be empty now
                         return None;
+                    } else if current_state == SLOT_REMOVED {
+                        // Skip the removed item
+                        self.skip_removed(slot, &self.tail, tail, last_pos);
                     } else if current_state == SLOT_WRITING {
                         // Slot is being written to, wait a bit
                         for _ in 0..10 {
@@ -335,6 +343,71 @@ impl<T, const CAPACITY: usize> LockFreeDeque<T, CAPACITY> {
         }
     }
 
+    /// Remove the first item (from front to back) that matches `f` from the deque,
+    /// which may be in the middle of the deque
+    /// Returns None if no item matches
+    ///
+    /// The slot of the removed item is left as a hole, which is skipped by the
+    /// following pops. It still takes up the capacity until then.
+    pub fn remove_by<F>(&self, mut f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let head = self.head.load(Ordering::Acquire);
+        let len = self.len();
+        for i in 0..len {
+            let slot = &self.buffer[(head + i) % CAPACITY];
+
+            // Claim the slot for reading, so that it cannot be popped while checking
+            if slot
+                .state
+                .compare_exchange(
+                    SLOT_READY,
+                    SLOT_READING,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+            {
+                continue;
+            }
+
+            if f(unsafe { (*slot.data.get()).assume_init_ref() }) {
+                let item = unsafe { (*slot.data.get()).assume_init_read() };
+
+                // Mark slot as removed
+                slot.state.store(SLOT_REMOVED, Ordering::Release);
+                return Some(item);
+            }
+
+            // Not matched, restore slot state
+            slot.state.store(SLOT_READY, Ordering::Release);
+        }
+        None
+    }
+
+    /// Move `cursor` (head or tail) from `pos` to `new_pos` over a removed slot
+    fn skip_removed(&self, slot: &Slot<T>, cursor: &AtomicUsize, pos: usize, new_pos: usize) {
+        if slot
+            .state
+            .compare_exchange(
+                SLOT_REMOVED,
+                SLOT_READING,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return;
+        }
+        match cursor.compare_exchange(pos, new_pos, Ordering::Release, Ordering::Relaxed) {
+            // The item has been read out by `remove_by`, just mark slot as empty
+            Ok(_) => slot.state.store(SLOT_EMPTY, Ordering::Release),
+            // Failed to update cursor, restore slot state and retry
+            Err(_) => slot.state.store(SLOT_REMOVED, Ordering::Release),
+        }
+    }
+
     /// Get the current length of the deque (approximate in concurrent scenarios)
     pub fn len(&self) -> usize {
         let (head, tail) = loop {
@@ -742,6 +815,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_remove_by() {
+        let deque: LockFreeDeque<i32, 8> = LockFreeDeque::new();
+        for i in 0..5 {
+            assert!(deque.push_back(i).is_ok());
+        }
+
+        // Remove items in the middle and at both ends
+        assert_eq!(deque.remove_by(|&x| x == 2), Some(2));
+        assert_eq!(deque.remove_by(|&x| x == 2), None);
+        assert_eq!(deque.remove_by(|&x| x == 0), Some(0));
+        assert_eq!(deque.remove_by(|&x| x == 4), Some(4));
+
+        // The holes are skipped by pops from both ends
+        assert!(deque.push_back(5).is_ok());
+        assert_eq!(deque.pop_front(), Some(1));
+        assert_eq!(deque.pop_back(), Some(5));
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_front(), None);
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_remove_by() {
+        let pad = 1000usize;
+        let deque = Arc::new(LockFreeDeque::<usize, 256>::new());
+        let flag = Arc::new(AtomicI32::new(1));
+
+        let p = deque.clone();
+        let flag_p = flag.clone();
+        let producer = thread::spawn(move || {
+            for i in 0..pad {
+                while p.push_back(i).is_err() {
+                    thread::yield_now();
+                }
+            }
+            flag_p.fetch_sub(1, Ordering::SeqCst);
+        });
+
+        // Remove the odd items, and pop the others
+        let r = deque.clone();
+        let flag_r = flag.clone();
+        let remover = thread::spawn(move || {
+            let mut sum = 0;
+            while flag_r.load(Ordering::SeqCst) != 0 || !r.is_empty() {
+                if let Some(num) = r.remove_by(|&x| x % 2 == 1) {
+                    sum += num;
+                }
+            }
+            sum
+        });
+
+        let mut sum = 0;
+        while flag.load(Ordering::SeqCst) != 0 || !deque.is_empty() {
+            if let Some(num) = deque.pop_front() {
+                sum += num;
+            }
+        }
+
+        producer.join().unwrap();
+        sum += remover.join().unwrap();
+        assert_eq!(sum, (0..pad).sum());
+    }
+
     // currently, this test will deadlock because of an unsolved bug.
     #[test]
     fn test_mpmc_full_mix() {
@@ -2,7 +2,9 @@ use core::mem::MaybeUninit;
 
 use crate::sched::{get_run_queue, get_run_queue_uninit};
 pub use base_task::TaskRef;
-use base_task::{BaseScheduler, PerCPU, TaskState, percpu_size_4k_aligned};
+use base_task::{
+    PerCPU, SchedClass, SchedPolicy, SchedStats, SchedStatus, TaskState, percpu_size_4k_aligned,
+};
 
 /// 将调度器的上一任务的`on_cpu`字段清除
 #[unsafe(no_mangle)]
@@ -16,6 +18,15 @@ pub extern "C" fn take_prev_task_and_clear_on_cpu(cpu_id: usize) -> TaskRef {
     crate::sched::take_prev_task_and_clear_on_cpu(get_run_queue(cpu_id))
 }
 
+/// 将被延迟的上一任务放入其它CPU的就绪队列，需在清除上一任务的`on_cpu`字段后调用
+///
+/// 被切换出的运行中任务需要放入其它CPU的就绪队列时，在当前CPU切换出该任务后才放入，
+/// 以免其它CPU选中仍在当前CPU上运行的任务。返回是否有被延迟的任务
+#[unsafe(no_mangle)]
+pub extern "C" fn migrate_prev_task(cpu_id: usize) -> bool {
+    crate::sched::migrate_prev_task(get_run_queue(cpu_id))
+}
+
 /// Gets the current task.
 ///
 /// # Panics
@@ -31,7 +42,6 @@ pub extern "C" fn current(cpu_id: usize) -> TaskRef {
     }
 }
 
-/// Initializes the task scheduler (for the primary CPU).
 // #[unsafe(no_mangle)]
 // pub extern "C" fn init_vsched(cpu_id: usize, idle_task: TaskRef, boot_task: TaskRef) {
 //     let per_cpu_base = get_data_base() as *mut u8;
@@ -41,9 +51,20 @@ pub extern "C" fn current(cpu_id: usize) -> TaskRef {
 //         *per_cpu = MaybeUninit::new(PerCPU::new(cpu_id, idle_task, boot_task));
 //     }
 // }
+
+/// Initializes the task scheduler (for the primary CPU).
+///
+/// `policy` is the scheduling policy of this CPU's run queue,
+/// so different CPUs can use different policies.
 #[unsafe(no_mangle)]
-pub extern "C" fn init_vsched(cpu_id: usize, idle_task: TaskRef, boot_task: TaskRef) {
-    get_run_queue_uninit(cpu_id).write(PerCPU::new(cpu_id, idle_task, boot_task));
+pub extern "C" fn init_vsched(
+    cpu_id: usize,
+    idle_task: TaskRef,
+    boot_task: TaskRef,
+    policy: SchedPolicy,
+) {
+    get_run_queue_uninit(cpu_id).write(PerCPU::new(cpu_id, idle_task, boot_task, policy));
+    crate::sched::mark_run_queue_init(cpu_id);
 }
 
 /// Spawns a new task with the default parameters.
@@ -51,10 +72,11 @@ pub extern "C" fn init_vsched(cpu_id: usize, idle_task: TaskRef, boot_task: Task
 /// The default task name is an empty string. The default task stack size is
 /// [`axconfig::TASK_STACK_SIZE`].
 ///
-/// Returns the task reference.
+/// Returns [`SchedStatus::RunQueueFull`] if the run queue is full, and the
+/// task is not spawned.
 #[unsafe(no_mangle)]
-pub extern "C" fn spawn(cpu_id: usize, task_ref: TaskRef) {
-    crate::sched::add_task(get_run_queue(cpu_id), task_ref);
+pub extern "C" fn spawn(cpu_id: usize, task_ref: TaskRef) -> SchedStatus {
+    crate::sched::add_task(get_run_queue(cpu_id), task_ref)
 }
 
 /// Set the priority for current task.
@@ -63,6 +85,9 @@ pub extern "C" fn spawn(cpu_id: usize, task_ref: TaskRef) {
 /// example, in the [CFS] scheduler, the priority is the nice value, ranging from
 /// -20 to 19.
 ///
+/// If the current task is a real-time task, the priority is its real-time
+/// priority, ranging from 0 to [`base_task::RT_PRIO_LEVELS`] - 1.
+///
 /// Returns `true` if the priority is set successfully.
 ///
 /// [CFS]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
@@ -71,18 +96,225 @@ pub extern "C" fn set_priority(prio: isize, cpu_id: usize) -> bool {
     crate::sched::set_current_priority(get_run_queue(cpu_id), prio)
 }
 
-/// task tick
+/// Set the priority for any task, which may be ready in a run queue, blocked
+/// in a wait queue, or running.
+///
+/// The range of the priority is the same as [`set_priority`], and it is
+/// checked by the scheduler of the run queue where the task is ready, or the
+/// run queue of `cpu_id` if the task is not ready. A ready task is put back
+/// to its run queue with the new priority.
+///
+/// Returns `true` if the priority is set successfully.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_task_priority(task: &TaskRef, prio: isize, cpu_id: usize) -> bool {
+    crate::sched::set_task_priority(get_run_queue(cpu_id), task, prio)
+}
+
+/// Set the cpumask of any task, where the `i`-th bit is set if the task can
+/// run on CPU `i`.
+///
+/// A ready task in the run queue of a CPU that is no longer allowed is moved
+/// to an allowed CPU, and a running task on such a CPU is moved when it is
+/// switched out. If the running task is on another cpu than `src_cpu_id`,
+/// returns [`SchedStatus::RemoteResched`] and sets `resched_cpu_id` to that
+/// cpu, so the caller should notify it to call [`take_resched_pending`].
+///
+/// Returns [`SchedStatus::NotAllowed`] if the cpumask allows no CPU, or the
+/// task has a deadline reservation.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_task_cpumask(
+    task: &TaskRef,
+    cpumask: usize,
+    src_cpu_id: usize,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    crate::sched::set_task_cpumask(get_run_queue(src_cpu_id), task, cpumask, resched_cpu_id)
+}
+
+/// Set the scheduling class for current task.
+///
+/// Real-time tasks are always scheduled before normal tasks. `prio` is the
+/// real-time priority, and it is ignored when `class` is [`SchedClass::Normal`].
+///
+/// Returns `true` if the scheduling class is set successfully.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_sched_class(class: SchedClass, prio: isize, cpu_id: usize) -> bool {
+    crate::sched::set_current_sched_class(get_run_queue(cpu_id), class, prio)
+}
+
+/// Set the scheduling class for any task, which may be ready in a run queue,
+/// blocked in a wait queue, or running.
+///
+/// `class` and `prio` are the same as [`set_sched_class`], and they are
+/// checked by the scheduler of the run queue where the task is ready, or the
+/// run queue of `cpu_id` if the task is not ready. A ready task is put back
+/// to its run queue in the new class.
+///
+/// Returns `true` if the scheduling class is set successfully.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_task_sched_class(
+    task: &TaskRef,
+    class: SchedClass,
+    prio: isize,
+    cpu_id: usize,
+) -> bool {
+    crate::sched::set_task_sched_class(get_run_queue(cpu_id), task, class, prio)
+}
+
+/// Set the reservation parameters of current task in the EDF scheduler.
+///
+/// The current task gets `runtime` nanoseconds in every `period` nanoseconds,
+/// and the runtime should be used up within `deadline` nanoseconds from the
+/// start of each period. It is admitted only if `0 < runtime <= deadline <= period`
+/// and the total bandwidth of the run queue does not exceed 100%. Setting
+/// `runtime` to 0 removes the reservation.
+///
+/// Returns `false` if the policy of the run queue is not
+/// [`SchedPolicy::Edf`], or the reservation is not admitted.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_deadline_params(
+    runtime: usize,
+    deadline: usize,
+    period: usize,
+    cpu_id: usize,
+) -> bool {
+    crate::sched::set_current_deadline_params(get_run_queue(cpu_id), runtime, deadline, period)
+}
+
+/// Gets the scheduling statistics of a task, e.g. its runtime and the number
+/// of switches.
+///
+/// Times are measured in nanoseconds of the clocks set by [`update_clock`] on
+/// the run queues where the task ran or waited.
+#[unsafe(no_mangle)]
+pub extern "C" fn task_stats(task: &TaskRef) -> SchedStats {
+    task.stats().get()
+}
+
+/// Gets the scheduling statistics of the CPU `cpu_id`, which are the totals
+/// of the tasks that ran or waited on it, excluding its idle task.
+///
+/// Times are measured in nanoseconds of the clock set by [`update_clock`].
+#[unsafe(no_mangle)]
+pub extern "C" fn cpu_stats(cpu_id: usize) -> SchedStats {
+    get_run_queue(cpu_id).stats()
+}
+
+/// Advances the clock of the run queue of `cpu_id` to `now`, the monotonic
+/// time in nanoseconds, which should have the same base on all CPUs.
+///
+/// The scheduling statistics and the EDF deadlines are measured by the clock,
+/// so it should be updated before the operations that switch tasks or put
+/// them into the run queue, e.g. [`resched`], [`unblock_task`] and [`task_tick`].
 #[unsafe(no_mangle)]
-pub extern "C" fn task_tick(cpu_id: usize, task_ref: &TaskRef) -> bool {
-    crate::sched::task_tick(get_run_queue(cpu_id), task_ref)
+pub extern "C" fn update_clock(cpu_id: usize, now: usize) {
+    get_run_queue(cpu_id).scheduler.update_clock(now)
 }
 
-/// migrate_entry
+/// Advances the run queue of `cpu_id` at a timer tick, with `elapsed`
+/// nanoseconds since the last tick, read from the clock of the caller.
+///
+/// The time slices, the CFS vruntime and the EDF budgets are all measured by
+/// the elapsed time, so the ticks need not be regular.
+///
+/// Returns `true` if the current task `task_ref` should be rescheduled.
 #[unsafe(no_mangle)]
-pub extern "C" fn migrate_entry(cpu_id: usize, migrated_task: TaskRef) {
-    get_run_queue(cpu_id)
-        .scheduler
-        .put_prev_task(migrated_task, false);
+pub extern "C" fn task_tick(cpu_id: usize, task_ref: &TaskRef, elapsed: usize) -> bool {
+    crate::sched::task_tick(get_run_queue(cpu_id), task_ref, elapsed)
+}
+
+/// Pulls ready tasks from the busiest run queue to the run queue of `cpu_id`,
+/// if the load balancing interval has passed in [`task_tick`] since the last
+/// balancing.
+///
+/// The timer tick may come in a signal handler, so the balancing is not done
+/// there, and this should be called after task switches and in the idle loop.
+///
+/// Returns `true` if any task is moved to the run queue.
+#[unsafe(no_mangle)]
+pub extern "C" fn periodic_balance(cpu_id: usize) -> bool {
+    crate::sched::periodic_balance(get_run_queue(cpu_id))
+}
+
+/// Gets the time slice of the round-robin tasks on the CPU `cpu_id`, in
+/// nanoseconds.
+#[unsafe(no_mangle)]
+pub extern "C" fn time_slice(cpu_id: usize) -> usize {
+    get_run_queue(cpu_id).scheduler.time_slice()
+}
+
+/// Sets the time slice of the round-robin tasks on the CPU `cpu_id`, in
+/// nanoseconds, i.e. the tasks of the [`SchedPolicy::RoundRobin`] policy and
+/// the [`SchedClass::RtRoundRobin`] class.
+///
+/// It takes effect when a task gets a new time slice.
+///
+/// Returns `false` if `time_slice` is 0.
+#[unsafe(no_mangle)]
+pub extern "C" fn set_time_slice(time_slice: usize, cpu_id: usize) -> bool {
+    get_run_queue(cpu_id).scheduler.set_time_slice(time_slice)
+}
+
+/// Puts a task moved from another CPU into the run queue of `cpu_id`.
+///
+/// The task must have been detached from the run queue of its previous CPU
+/// by [`base_task::Scheduler::detach_task`], so that its CFS vruntime is
+/// preserved across the run queues. It is also used by the periodic load
+/// balancing in [`periodic_balance`].
+///
+/// Returns [`SchedStatus::RunQueueFull`] if the run queue is full, and the
+/// task stays detached.
+#[unsafe(no_mangle)]
+pub extern "C" fn migrate_entry(cpu_id: usize, migrated_task: TaskRef) -> SchedStatus {
+    match crate::sched::migrate_entry(get_run_queue(cpu_id), migrated_task) {
+        Ok(()) => SchedStatus::Ok,
+        Err(_) => SchedStatus::RunQueueFull,
+    }
+}
+
+/// Migrates any task to the run queue of `dst_cpu_id`.
+///
+/// A ready task is moved from its run queue to the destination at once. A
+/// blocked task is put into the destination when it is woken up, and a running
+/// task is put into it when it is switched out at its next scheduling point.
+///
+/// The destination CPU should be initialized and allowed by the cpumask of the
+/// task.
+///
+/// Returns [`SchedStatus::RemoteResched`] if the task is running on another
+/// cpu than `src_cpu_id`, and sets `resched_cpu_id` to that cpu, so the caller
+/// should notify it to call [`take_resched_pending`].
+///
+/// Returns [`SchedStatus::NotAllowed`] if the task has a deadline reservation,
+/// and [`SchedStatus::RunQueueFull`] if the run queue of the destination is
+/// full, and the ready task stays in its run queue.
+#[unsafe(no_mangle)]
+pub extern "C" fn migrate(
+    task: &TaskRef,
+    dst_cpu_id: usize,
+    src_cpu_id: usize,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    crate::sched::migrate_task(
+        get_run_queue(src_cpu_id),
+        get_run_queue(dst_cpu_id),
+        task,
+        resched_cpu_id,
+    )
+}
+
+/// Removes a ready task from the run queue of `cpu_id`, wherever it is in the
+/// queue, e.g. to kill it, or to change its priority, affinity or CPU.
+///
+/// The removed task keeps the [`TaskState::Ready`] state, and should be put
+/// into a run queue again by [`spawn`], or by [`migrate_entry`] after being
+/// detached.
+///
+/// Returns `false` if the task is not in the run queue, e.g. it is running,
+/// blocked, or in the run queue of another CPU.
+#[unsafe(no_mangle)]
+pub extern "C" fn remove_task(cpu_id: usize, task: &TaskRef) -> bool {
+    crate::sched::remove_task(get_run_queue(cpu_id), task)
 }
 
 /// Current task gives up the CPU time voluntarily, and switches to another
@@ -120,18 +352,83 @@ pub extern "C" fn resched_f(cpu_id: usize) -> bool {
 }
 
 /// Wake up a task to the distination cpu,
-#[rustfmt::skip]
+///
+/// Returns [`SchedStatus::RemoteResched`] if the woken task should preempt the
+/// current task of the cpu where it is put, which is not the source cpu. Then
+/// `resched_cpu_id` is set to that cpu, and the caller should notify it to
+/// call [`take_resched_pending`].
+///
+/// If the run queue of the destination cpu is full, the task is woken up to
+/// another cpu allowed by its cpumask. Returns [`SchedStatus::RunQueueFull`]
+/// if the run queues of all these cpus are full, and the task is still
+/// blocked, so the caller should wake it up again later.
+#[unsafe(no_mangle)]
+pub extern "C" fn unblock_task(
+    task: TaskRef,
+    resched: bool,
+    dst_cpu_id: usize,
+    src_cpu_id: usize,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    crate::sched::unblock_task(
+        get_run_queue(dst_cpu_id),
+        task,
+        resched,
+        src_cpu_id,
+        resched_cpu_id,
+    )
+}
+
+/// Takes the reschedule request sent to the cpu by other cpus when they wake
+/// up tasks onto it. Returns `true` if there is one, and the current task of
+/// the cpu should be preempted.
 #[unsafe(no_mangle)]
-pub extern "C" fn unblock_task(task: TaskRef, resched: bool, dst_cpu_id: usize, src_cpu_id: usize) {
-    crate::sched::unblock_task(get_run_queue(dst_cpu_id), task, resched, src_cpu_id);
+pub extern "C" fn take_resched_pending(cpu_id: usize) -> bool {
+    crate::sched::take_resched_pending(get_run_queue(cpu_id))
+}
+
+/// Marks the cpu as idle before it sleeps.
+///
+/// Returns `false` if the run queue of the cpu is not empty, and the cpu
+/// should not sleep. Otherwise, the cpu can sleep until its word returned by
+/// [`idle_state`] is not [`CPU_IDLE`], and should set the word to
+/// [`CPU_RUNNING`] after waking up.
+///
+/// [`CPU_IDLE`]: base_task::CPU_IDLE
+/// [`CPU_RUNNING`]: base_task::CPU_RUNNING
+#[unsafe(no_mangle)]
+pub extern "C" fn idle_enter(cpu_id: usize) -> bool {
+    crate::sched::idle_enter(get_run_queue(cpu_id))
+}
+
+/// Gets the idle state of the cpu, which is set to [`CPU_KICKED`] when a task
+/// is put into the run queue of the idle cpu, and the cpu should be woken up.
+///
+/// The state is also the word that the idle cpu sleeps on, e.g. with a futex.
+///
+/// [`CPU_KICKED`]: base_task::CPU_KICKED
+#[unsafe(no_mangle)]
+pub extern "C" fn idle_state(cpu_id: usize) -> &'static core::sync::atomic::AtomicU32 {
+    &get_run_queue(cpu_id).idle_state
 }
 
 /// yield future
+///
+/// Returns [`SchedStatus::Resched`] if the current task is switched out, and
+/// [`SchedStatus::RunQueueFull`] if the run queue is full, where the current
+/// task keeps running.
 #[unsafe(no_mangle)]
-pub extern "C" fn yield_f(cpu_id: usize) -> bool {
+pub extern "C" fn yield_f(cpu_id: usize) -> SchedStatus {
     let per_cpu = get_run_queue(cpu_id);
     let curr = unsafe { per_cpu.current_task.as_ref_unchecked() };
     assert!(curr.is_running());
-    crate::sched::put_task_with_state(per_cpu, curr.clone(), TaskState::Running, false);
-    crate::sched::resched_f(per_cpu)
+    let put = crate::sched::put_task_with_state(per_cpu, curr.clone(), TaskState::Running, false);
+    if put.is_err() {
+        return SchedStatus::RunQueueFull;
+    }
+    if crate::sched::resched_f(per_cpu) {
+        SchedStatus::Resched
+    } else {
+        SchedStatus::Ok
+    }
 }
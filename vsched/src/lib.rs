@@ -13,15 +13,22 @@ pub use api::*;
 
 pub use base_task::PerCPU;
 use config::SMP;
-use core::{cell::UnsafeCell, mem::MaybeUninit};
+use core::{cell::UnsafeCell, mem::MaybeUninit, sync::atomic::AtomicBool};
 use vdso_helper::vvar_data;
 
-/// vVAR数据区，用于存储每个CPU的调度器（调度队列、当前任务等）
-pub struct VvarDataInner(pub [UnsafeCell<MaybeUninit<PerCPU>>; SMP]);
+/// vVAR数据区，用于存储每个CPU的调度器（调度队列、当前任务等），
+/// 以及每个CPU的调度器是否已初始化（用于其它CPU从中窃取任务）
+pub struct VvarDataInner(
+    pub [UnsafeCell<MaybeUninit<PerCPU>>; SMP],
+    pub [AtomicBool; SMP],
+);
 
 impl Default for VvarDataInner {
     fn default() -> Self {
-        Self([const { UnsafeCell::new(MaybeUninit::uninit()) }; SMP])
+        Self(
+            [const { UnsafeCell::new(MaybeUninit::uninit()) }; SMP],
+            [const { AtomicBool::new(false) }; SMP],
+        )
     }
 }
 
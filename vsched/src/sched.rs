@@ -1,8 +1,9 @@
 
-use base_task::{percpu_size_4k_aligned, TaskRef, BaseScheduler, PerCPU, TaskInner, TaskState};
+use base_task::{percpu_size_4k_aligned, TaskRef, BaseScheduler, PerCPU, SchedClass, SchedStatus, TaskInner, TaskState, CPU_IDLE, CPU_KICKED, CPU_RUNNING};
 use config::{PAGES_SIZE_4K, SMP};
 use vdso_helper::{get_vvar_data, vvar_data};
 use core::mem::MaybeUninit;
+use core::sync::atomic::{fence, Ordering};
 
 use crate::VvarDataInner;
 
@@ -61,19 +62,49 @@ pub fn get_run_queue_uninit(index: usize) -> &'static mut MaybeUninit<PerCPU> {
     unsafe { get_vvar_data!(data, PAGES_SIZE_4K).0[index].as_mut_unchecked() }
 }
 
+/// 标记runqueue已初始化，此后其它CPU可以从中窃取任务。
+#[inline]
+pub fn mark_run_queue_init(index: usize) {
+    unsafe { get_vvar_data!(data, PAGES_SIZE_4K).1[index].store(true, Ordering::Release) }
+}
+
+/// 若runqueue已初始化，则返回其引用。
+#[inline]
+fn try_get_run_queue(index: usize) -> Option<&'static PerCPU> {
+    if unsafe { get_vvar_data!(data, PAGES_SIZE_4K).1[index].load(Ordering::Acquire) } {
+        Some(get_run_queue(index))
+    } else {
+        None
+    }
+}
+
 /// Puts target task into current run queue with `Ready` state
 /// if its state matches `current_state` (except idle task).
 ///
+/// If the task is migrated to another CPU by `migrate_task()`, it is put into
+/// the run queue of that CPU instead. Otherwise, if the cpumask of the task
+/// does not allow it to run on this CPU, e.g. the cpumask of a running task is
+/// changed, it is put into the run queue of an allowed CPU.
+///
+/// A running task is put into the run queue of another CPU only after this CPU
+/// switches out of it, see `migrate_prev_task()`, so the caller must switch
+/// out of the task then.
+///
 /// If `preempt`, keep current task's time slice, otherwise reset it.
 ///
-/// Returns `true` if the target task is put into this run queue successfully,
-/// otherwise `false`.
+/// If the run queue is full, a blocked task being woken up is put into the run
+/// queue of another allowed CPU instead, unless it is reserved on this CPU.
+///
+/// Returns `Ok(Some(rq))` if the target task is put into the run queue `rq`
+/// successfully, `Ok(None)` if its state does not match `current_state`, and
+/// `Err` if the run queue is full. In the last case, the state of the task is restored to 
+/// `current_state`.
 pub(crate) fn put_task_with_state(
     percpu: &'static PerCPU,
     task: TaskRef,
     current_state: TaskState,
     preempt: bool,
-) -> bool {
+) -> Result<Option<&'static PerCPU>, TaskRef> {
     // If the task's state matches `current_state`, set its state to `Ready` and
     // put it back to the run queue (except idle task).
     if task
@@ -87,61 +118,281 @@ pub(crate) fn put_task_with_state(
             // If the owning (remote) CPU is still in the middle of schedule() with
             // this task (next task) as prev, wait until it's done referencing the task.
             //
-            // Pairs with the `clear_prev_task_on_cpu()`.
-            //
             // Note:
             // 1. This should be placed after the judgement of `TaskState::Blocked,`,
             //    because the task may have been woken up by other cores.
             // 2. This can be placed in the front of `switch_to()`
-            while task.on_cpu() {
-                // Wait for the task to finish its scheduling process.
-                core::hint::spin_loop();
+            wait_for_switch_out(&task);
+            // The task may run on other CPUs as soon as it is put into the
+            // run queue, so account the wakeup before that.
+            percpu.scheduler.account_wakeup(&task);
+        }
+        let target = task
+            .take_home_cpu()
+            .filter(|&cpu_id| task.can_run_on(cpu_id))
+            .and_then(try_get_run_queue)
+            .unwrap_or_else(|| allowed_run_queue(percpu, &task));
+        let moved = if target.cpu_id == percpu.cpu_id {
+            Err(task)
+        } else if current_state == TaskState::Running {
+            // The running task may be picked by the remote CPU as soon as it
+            // is put into the run queue, so it is put there after this CPU
+            // switches out of it. See `migrate_prev_task()`.
+            unsafe { percpu.deferred_task.replace(Some((task, target.cpu_id))) };
+            Ok(target)
+        } else {
+            move_task(percpu, target, task).map(|()| target)
+        };
+        match moved {
+            Ok(target) => Ok(Some(target)),
+            Err(task) => {
+                // TODO: priority
+                let task = match percpu.scheduler.put_prev_task(task, preempt) {
+                    Ok(()) => {
+                        kick_if_idle(percpu);
+                        return Ok(Some(percpu));
+                    }
+                    Err(task) => task,
+                };
+                // A running task keeps running if it cannot be put back.
+                if current_state == TaskState::Blocked && percpu.scheduler.can_migrate(&task) {
+                    return migrate_to_allowed(percpu, task).map(Some).map_err(|task| {
+                        task.set_state(current_state);
+                        task
+                    });
+                }
+                task.set_state(current_state);
+                Err(task)
             }
         }
-        // TODO: priority
-        percpu.scheduler.put_prev_task(task, preempt);
-        true
     } else {
-        false
+        Ok(None)
+    }
+}
+
+/// Returns the run queue where the task should be put: this run queue if the
+/// task can run on this CPU, otherwise the first initialized run queue of an
+/// allowed CPU, searched starting from the next CPU.
+///
+/// This run queue is returned if no allowed CPU is initialized.
+fn allowed_run_queue(percpu: &'static PerCPU, task: &TaskRef) -> &'static PerCPU {
+    if task.can_run_on(percpu.cpu_id) {
+        return percpu;
+    }
+    (1..SMP)
+        .map(|i| (percpu.cpu_id + i) % SMP)
+        .filter(|&cpu_id| task.can_run_on(cpu_id))
+        .find_map(try_get_run_queue)
+        .unwrap_or(percpu)
+}
+
+/// Set the cpumask of any task, which may be ready in a run queue, blocked,
+/// or running.
+///
+/// A ready task in the run queue of a CPU that is no longer allowed is moved
+/// to the run queue of an allowed CPU. A running task on such a CPU is
+/// rescheduled by `resched_running()`, and is moved when it is switched out.
+/// A blocked task is put into an allowed run queue when it is woken up.
+///
+/// Returns [`SchedStatus::NotAllowed`] if the cpumask allows no CPU, or the
+/// task has a deadline reservation, which is only guaranteed on the CPU that
+/// admitted it. Then the cpumask is not set.
+pub fn set_task_cpumask(
+    percpu: &'static PerCPU,
+    task: &TaskRef,
+    cpumask: usize,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    if cpumask & (usize::MAX >> (usize::BITS as usize - SMP)) == 0
+        || !percpu.scheduler.can_migrate(task)
+    {
+        return SchedStatus::NotAllowed;
+    }
+    task.set_cpumask_bits(cpumask);
+    for cpu_id in (0..SMP).filter(|&cpu_id| !task.can_run_on(cpu_id)) {
+        let Some(rq) = try_get_run_queue(cpu_id) else {
+            continue;
+        };
+        if rq.is_current(task) {
+            return resched_running(percpu, rq, task, resched_cpu_id);
+        }
+        if let Some(task) = rq.scheduler.remove_task(task) {
+            wait_for_switch_out(&task);
+            if let Err(task) = move_task(rq, allowed_run_queue(rq, &task), task) {
+                put_back(rq, task);
+            }
+            break;
+        }
+    }
+    SchedStatus::Ok
+}
+
+/// Migrate any task to the run queue of `dst`, which may be ready in a run
+/// queue, blocked, or running.
+///
+/// A ready task is removed from the run queue of its CPU and put into `dst`.
+/// Otherwise, `dst` is recorded as the home CPU of the task, where a blocked
+/// task is put when it is woken up, and a running task is put when it is
+/// switched out. The running task is rescheduled by `resched_running()`, so
+/// it is switched out at its next scheduling point.
+///
+/// The cpumask of the task should allow it to run on `dst`.
+///
+/// Returns [`SchedStatus::NotAllowed`] if the task has a deadline
+/// reservation, which is only guaranteed on the CPU that admitted it, and
+/// [`SchedStatus::RunQueueFull`] if the run queue of `dst` is full, where the
+/// ready task stays in its run queue.
+pub fn migrate_task(
+    percpu: &'static PerCPU,
+    dst: &'static PerCPU,
+    task: &TaskRef,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    if !dst.scheduler.can_migrate(task) {
+        return SchedStatus::NotAllowed;
+    }
+    task.set_home_cpu(dst.cpu_id);
+    for cpu_id in 0..SMP {
+        let Some(rq) = try_get_run_queue(cpu_id) else {
+            continue;
+        };
+        if rq.is_current(task) {
+            if cpu_id == dst.cpu_id {
+                task.take_home_cpu();
+                return SchedStatus::Ok;
+            }
+            return resched_running(percpu, rq, task, resched_cpu_id);
+        }
+        if cpu_id == dst.cpu_id {
+            continue;
+        }
+        if let Some(task) = rq.scheduler.remove_task(task) {
+            wait_for_switch_out(&task);
+            task.take_home_cpu();
+            return match move_task(rq, dst, task) {
+                Ok(()) => SchedStatus::Ok,
+                Err(task) => {
+                    put_back(rq, task);
+                    SchedStatus::RunQueueFull
+                }
+            };
+        }
+    }
+    SchedStatus::Ok
+}
+
+/// Reschedule a task running on the CPU of `rq`, so that it is switched out
+/// at its next scheduling point.
+///
+/// If `rq` belongs to this CPU, the task is the caller and is marked to be
+/// preempted. Otherwise, the request is left in the `resched_pending` mailbox
+/// of `rq`, `resched_cpu_id` is set to the CPU of `rq`, and
+/// [`SchedStatus::RemoteResched`] is returned so that the caller can notify
+/// that CPU, since the task may never reach a scheduling point by itself.
+fn resched_running(
+    percpu: &'static PerCPU,
+    rq: &'static PerCPU,
+    task: &TaskRef,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    if rq.cpu_id == percpu.cpu_id {
+        task.set_preempt_pending(true);
+        SchedStatus::Ok
+    } else {
+        rq.resched_pending.store(true, Ordering::Release);
+        *resched_cpu_id = rq.cpu_id;
+        SchedStatus::RemoteResched
     }
 }
 
 /// Adds a task to the scheduler.
 ///
 /// This function is used to add a new task to the scheduler.
-pub fn add_task(percpu: &'static PerCPU, task: TaskRef) {
+pub fn add_task(percpu: &'static PerCPU, task: TaskRef) -> SchedStatus {
     assert!(task.is_ready());
-    percpu.scheduler.add_task(task);
+    match percpu.scheduler.add_task(task) {
+        Ok(()) => {
+            kick_if_idle(percpu);
+            SchedStatus::Ok
+        }
+        Err(_) => SchedStatus::RunQueueFull,
+    }
 }
 
 /// Unblock one task by inserting it into the run queue.
 ///
 /// This function does nothing if the task is not in [`TaskState::Blocked`],
 /// which means the task is already unblocked by other cores.
-pub fn unblock_task(percpu: &'static PerCPU, task: TaskRef, resched: bool, src_cpu_id: usize) {
+///
+/// If `resched` and the scheduler decides that the woken task should preempt
+/// the current task of the run queue where it is put (see
+/// `Scheduler::check_preempt_current()`), the current task is marked to be
+/// preempted if the run queue belongs to the source CPU. Otherwise, the
+/// request is left in the `resched_pending` mailbox of the run queue,
+/// `resched_cpu_id` is set to the CPU of the run queue, and
+/// [`SchedStatus::RemoteResched`] is returned so that the caller can notify
+/// that CPU.
+///
+/// The current task of a remote CPU may be switched out and freed at any time,
+/// so it is never referenced here.
+///
+/// Returns [`SchedStatus::RunQueueFull`] if the run queues of all the allowed
+/// CPUs are full, and the task is kept in [`TaskState::Blocked`].
+pub fn unblock_task(
+    percpu: &'static PerCPU,
+    task: TaskRef,
+    resched: bool,
+    src_cpu_id: usize,
+    resched_cpu_id: &mut usize,
+) -> SchedStatus {
+    let woken = task.clone();
+    if src_cpu_id != percpu.cpu_id {
+        percpu.scheduler.update_clock(get_run_queue(src_cpu_id).scheduler.clock());
+    }
     // Try to change the state of the task from `Blocked` to `Ready`,
     // if successful, the task will be put into this run queue,
     // otherwise, the task is already unblocked by other cores.
     // Note:
     // target task can not be insert into the run queue until it finishes its scheduling process.
-    if put_task_with_state(percpu, task, TaskState::Blocked, resched) {
-        // Since now, the task to be unblocked is in the `Ready` state.
-        // Note: when the task is unblocked on another CPU's run queue,
-        // we just ingiore the `resched` flag.
-        if resched && src_cpu_id == percpu.cpu_id {
-            // TODO: 增加判断当前任务的条件
-            unsafe { 
-                get_run_queue(src_cpu_id)
-                .current_task
-                .as_ref_unchecked()
-                .set_preempt_pending(true);
-            };
-        }
+    let Ok(put) = put_task_with_state(percpu, task, TaskState::Blocked, resched) else {
+        return SchedStatus::RunQueueFull;
+    };
+    // Since now, the task to be unblocked is in the `Ready` state.
+    // Note: the task may be put into another run queue than `percpu`, see
+    // `put_task_with_state()`.
+    let Some(target) = put.filter(|_| resched) else {
+        return SchedStatus::Ok;
+    };
+    if !target.scheduler.check_preempt_current(&woken) {
+        return SchedStatus::Ok;
+    }
+    if target.cpu_id == src_cpu_id {
+        // The current task of the source CPU is the caller.
+        unsafe { target.current_task.as_ref_unchecked() }.set_preempt_pending(true);
+        SchedStatus::Ok
+    } else {
+        target.resched_pending.store(true, Ordering::Release);
+        *resched_cpu_id = target.cpu_id;
+        SchedStatus::RemoteResched
     }
 }
 
-pub fn task_tick(percpu: &'static PerCPU, task: &TaskRef) -> bool {
-    percpu.scheduler.task_tick(task)
+/// Takes the reschedule request left in the mailbox of the run queue by other
+/// CPUs. Returns `true` if there is one.
+pub fn take_resched_pending(percpu: &'static PerCPU) -> bool {
+    percpu.resched_pending.swap(false, Ordering::AcqRel)
+}
+
+/// Advance the scheduler at a timer tick.
+///
+/// The load balancing is only accounted here, since the timer tick may come
+/// in a signal handler. It is done later by `periodic_balance()`.
+pub fn task_tick(percpu: &'static PerCPU, task: &TaskRef, elapsed: usize) -> bool {
+    percpu.balance_time.fetch_add(elapsed, Ordering::AcqRel);
+    let resched = percpu.scheduler.task_tick(task, elapsed);
+    // The vruntime or the deadline of the current task may be advanced.
+    percpu.scheduler.set_current(task, task.is_idle());
+    resched
 }
 
 /// Yield the current task and reschedule.
@@ -150,8 +401,10 @@ pub fn task_tick(percpu: &'static PerCPU, task: &TaskRef) -> bool {
 pub fn yield_current(percpu: &'static PerCPU) {
     let curr = unsafe { percpu.current_task.as_ref_unchecked() };
     assert!(curr.is_running());
-    put_task_with_state(percpu, curr.clone(), TaskState::Running, false);
-    resched(percpu);
+    // If the run queue is full, the current task keeps running.
+    if put_task_with_state(percpu, curr.clone(), TaskState::Running, false).is_ok() {
+        resched(percpu);
+    }
 }
 
 /// Yield the current task and reschedule.
@@ -160,22 +413,354 @@ pub fn yield_current(percpu: &'static PerCPU) {
 pub fn preempt_current(percpu: &'static PerCPU) {
     let curr = unsafe { percpu.current_task.as_ref_unchecked() };
     assert!(curr.is_running());
-    put_task_with_state(percpu, curr.clone(), TaskState::Running, true);
-    resched(percpu);
+    // If the run queue is full, the current task keeps running.
+    if put_task_with_state(percpu, curr.clone(), TaskState::Running, true).is_ok() {
+        resched(percpu);
+    }
 }
 
 pub fn set_current_priority(percpu: &'static PerCPU, prio: isize) -> bool {
-    percpu.scheduler
-        .set_priority(unsafe { percpu.current_task.as_ref_unchecked() }, prio)
+    let curr = unsafe { percpu.current_task.as_ref_unchecked() };
+    let ok = percpu.scheduler.set_priority(curr, prio);
+    percpu.scheduler.set_current(curr, curr.is_idle());
+    ok
+}
+
+/// Set the priority of any task, which may be ready in a run queue, blocked,
+/// or running.
+///
+/// A ready task is searched in the run queues starting from this one. It is
+/// removed from its run queue and put back after setting the priority, so
+/// that its position in the ready queue and the load of the run queue are
+/// recomputed. Otherwise (the task is blocked or running), the priority is
+/// checked by the scheduler of this run queue.
+///
+/// The task may be put into a run queue by another CPU at the same time, e.g.
+/// when it is running there or being moved. It is safe since the schedulers
+/// find a ready task and subtract its load by the key and the weight recorded
+/// when it was put into the run queue, rather than by its priority.
+pub fn set_task_priority(percpu: &'static PerCPU, task: &TaskRef, prio: isize) -> bool {
+    for i in 0..SMP {
+        let Some(rq) = try_get_run_queue((percpu.cpu_id + i) % SMP) else {
+            continue;
+        };
+        if let Some(task) = rq.scheduler.remove_task(task) {
+            let ok = rq.scheduler.set_priority(&task, prio);
+            put_back(rq, task);
+            return ok;
+        }
+    }
+    percpu.scheduler.set_priority(task, prio)
+}
+
+/// Move the current task to another scheduling class.
+/// 
+/// The current task is not in the ready queue, so it will be put into 
+/// the queue of its new class at the next scheduling point.
+pub fn set_current_sched_class(percpu: &'static PerCPU, class: SchedClass, prio: isize) -> bool {
+    let curr = unsafe { percpu.current_task.as_ref_unchecked() };
+    let ok = percpu.scheduler.set_sched_class(curr, class, prio);
+    percpu.scheduler.set_current(curr, curr.is_idle());
+    ok
+}
+
+/// Move any task to another scheduling class, which may be ready in a run
+/// queue, blocked, or running.
+///
+/// A ready task is searched in the run queues starting from this one. It is
+/// removed from its run queue and put back after setting the class, as
+/// `set_task_priority()` does, so that it is put into the ready queue of its
+/// new class. Otherwise (the task is blocked or running), the class is set by
+/// the scheduler of this run queue, and the task is put into the ready queue
+/// of its new class when it is put into a run queue.
+pub fn set_task_sched_class(percpu: &'static PerCPU, task: &TaskRef, class: SchedClass, prio: isize) -> bool {
+    for i in 0..SMP {
+        let Some(rq) = try_get_run_queue((percpu.cpu_id + i) % SMP) else {
+            continue;
+        };
+        if let Some(task) = rq.scheduler.remove_task(task) {
+            let ok = rq.scheduler.set_sched_class(&task, class, prio);
+            put_back(rq, task);
+            return ok;
+        }
+    }
+    percpu.scheduler.set_sched_class(task, class, prio)
+}
+
+/// Set the reservation parameters of the current task in the EDF scheduler.
+/// 
+/// The current task is not in the ready queue, so the new deadline takes effect
+/// when it is put back to the run queue.
+pub fn set_current_deadline_params(percpu: &'static PerCPU, runtime: usize, deadline: usize, period: usize) -> bool {
+    let curr = unsafe { percpu.current_task.as_ref_unchecked() };
+    let ok = percpu.scheduler.set_deadline_params(curr, runtime, deadline, period);
+    percpu.scheduler.set_current(curr, curr.is_idle());
+    ok
+}
+
+/// Remove a ready task from this run queue, wherever it is in the queue.
+///
+/// The removed task keeps its `Ready` state, the caller is responsible for
+/// putting it into a run queue again.
+///
+/// Returns `false` if the task is not in this run queue.
+pub fn remove_task(percpu: &'static PerCPU, task: &TaskRef) -> bool {
+    percpu.scheduler.remove_task(task).is_some()
+}
+
+/// Returns `true` if a ready task in the run queue `src` can be moved to the
+/// run queue `dst`.
+///
+/// A task just yielded by the CPU of `src` is skipped until that CPU finishes
+/// switching out of it, so the caller never waits for another CPU. The
+/// `on_cpu` flag of a ready task is never set again before it is picked.
+fn can_move_task(src: &'static PerCPU, dst: &'static PerCPU, task: &TaskRef) -> bool {
+    task.can_run_on(dst.cpu_id) && src.scheduler.can_migrate(task) && !task.on_cpu()
+}
+
+/// Steal a ready task from the run queues of other CPUs.
+///
+/// It is used when this run queue is empty. Other run queues are visited 
+/// starting from the next CPU, and the first task of each run queue that can
+/// be moved to this CPU is taken, while other tasks stay in their places.
+fn steal_task(percpu: &'static PerCPU) -> Option<TaskRef> {
+    for i in 1..SMP {
+        let Some(victim) = try_get_run_queue((percpu.cpu_id + i) % SMP) else {
+            continue;
+        };
+        let Some(task) = victim
+            .scheduler
+            .remove_first_by(|task| can_move_task(victim, percpu, task))
+        else {
+            continue;
+        };
+        victim.scheduler.detach_task(&task);
+        percpu.scheduler.attach_task(&task);
+        return Some(task);
+    }
+    None
+}
+
+/// Put a task taken from a run queue back to it.
+///
+/// The slot of the task was just freed, so it only fails if other CPUs fill 
+/// the run queue at the same time. Then the task is put into the run queue of
+/// another allowed CPU instead, and it is only retried if all of them are
+/// full, or the task is reserved on this CPU. Otherwise, it would be lost.
+fn put_back(percpu: &'static PerCPU, mut task: TaskRef) {
+    loop {
+        task = match percpu.scheduler.put_prev_task(task, true) {
+            Ok(()) => return,
+            Err(task) => task,
+        };
+        if percpu.scheduler.can_migrate(&task) {
+            task = match migrate_to_allowed(percpu, task) {
+                Ok(_) => return,
+                Err(task) => task,
+            };
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Put a task into the run queue of another CPU allowed by its cpumask, when
+/// the run queue of `percpu` is full. Other run queues are visited starting
+/// from the next CPU.
+///
+/// The task is moved by `move_task()`. If all the run queues are full, the task
+/// is returned and stays attached to `percpu`.
+fn migrate_to_allowed(percpu: &'static PerCPU, mut task: TaskRef) -> Result<&'static PerCPU, TaskRef> {
+    for i in 1..SMP {
+        let cpu_id = (percpu.cpu_id + i) % SMP;
+        let Some(rq) = try_get_run_queue(cpu_id).filter(|_| task.can_run_on(cpu_id)) else {
+            continue;
+        };
+        task = match move_task(percpu, rq, task) {
+            Ok(()) => return Ok(rq),
+            Err(task) => task,
+        };
+    }
+    Err(task)
+}
+
+/// Wait for the CPU where the task is just yielded or blocked to finish
+/// switching out of it.
+/// 
+/// A task woken up by `put_task_with_state()`, or a ready task removed from
+/// the run queue of another CPU, can only be put into a run queue after that
+/// CPU completes its scheduling process. The task is no longer in any run
+/// queue, so that CPU never waits for the caller.
+///
+/// Pairs with the `clear_prev_task_on_cpu()`.
+#[inline]
+fn wait_for_switch_out(task: &TaskRef) {
+    while task.on_cpu() {
+        core::hint::spin_loop();
+    }
+}
+
+/// Put the task just switched out of this CPU into the run queue of another
+/// CPU, if `put_task_with_state()` deferred it until this CPU finished
+/// switching out of it.
+///
+/// It should be called after the `on_cpu` flag of the previous task is
+/// cleared. If the run queue of that CPU is full, the task is put back into
+/// this run queue, or the run queue of another allowed CPU.
+///
+/// Returns `true` if there is a deferred task.
+pub fn migrate_prev_task(percpu: &'static PerCPU) -> bool {
+    let Some((task, cpu_id)) = (unsafe { percpu.deferred_task.replace(None) }) else {
+        return false;
+    };
+    // The task may be migrated again by `migrate_task()` after it is switched out.
+    let target = task
+        .take_home_cpu()
+        .filter(|&cpu_id| task.can_run_on(cpu_id))
+        .and_then(try_get_run_queue)
+        .unwrap_or_else(|| get_run_queue(cpu_id));
+    if let Err(task) = move_task(percpu, target, task) {
+        put_back(percpu, task);
+    }
+    true
+}
+
+/// Interval of load balancing, in nanoseconds.
+const LOAD_BALANCE_INTERVAL: usize = 20_000_000;
+
+/// Maximum number of tasks moved in one load balancing.
+const LOAD_BALANCE_MAX_MOVES: usize = 8;
+
+/// Pull ready tasks to this run queue by `load_balance()`, if
+/// [`LOAD_BALANCE_INTERVAL`] has passed in the timer ticks since the last
+/// balancing.
+///
+/// It should be called out of the timer tick, e.g. after a task switch or in
+/// the idle loop.
+///
+/// Returns `true` if any task is moved to this run queue.
+pub fn periodic_balance(percpu: &'static PerCPU) -> bool {
+    if percpu.balance_time.load(Ordering::Acquire) < LOAD_BALANCE_INTERVAL {
+        return false;
+    }
+    percpu.balance_time.store(0, Ordering::Release);
+    load_balance(percpu)
+}
+
+/// Pull ready tasks from the busiest run queue to this run queue.
+/// 
+/// Tasks are moved one by one while the load of the busiest run queue exceeds 
+/// the load of this run queue by more than the weight of a nice-0 task, so a
+/// single task never bounces between CPUs. 
+/// 
+/// Returns `true` if any task is moved to this run queue.
+pub fn load_balance(percpu: &'static PerCPU) -> bool {
+    const NICE_0_LOAD: usize = 1024;
+
+    let busiest = (0..SMP)
+        .filter(|&cpu_id| cpu_id != percpu.cpu_id)
+        .filter_map(try_get_run_queue)
+        .max_by_key(|rq| rq.scheduler.load());
+    let Some(busiest) = busiest else {
+        return false;
+    };
+    let mut moved = false;
+    for _ in 0..LOAD_BALANCE_MAX_MOVES {
+        if busiest.scheduler.load() <= percpu.scheduler.load() + NICE_0_LOAD {
+            break;
+        }
+        let Some(task) = busiest
+            .scheduler
+            .remove_first_by(|task| can_move_task(busiest, percpu, task))
+        else {
+            break;
+        };
+        if let Err(task) = move_task(busiest, percpu, task) {
+            put_back(busiest, task);
+            break;
+        }
+        moved = true;
+    }
+    moved
+}
+
+/// Move a task taken from the run queue `src` into the run queue `dst`.
+///
+/// The task is detached from `src` and put into `dst` by `migrate_entry()`,
+/// so its CFS vruntime is preserved relative to the `min_vruntime` of the run
+/// queues. If `dst` is full, the task is returned and stays attached to `src`.
+fn move_task(src: &'static PerCPU, dst: &'static PerCPU, task: TaskRef) -> Result<(), TaskRef> {
+    // The clock of a remote CPU may be stale, e.g. when it is idle.
+    dst.scheduler.update_clock(src.scheduler.clock());
+    src.scheduler.detach_task(&task);
+    migrate_entry(dst, task).map_err(|task| {
+        src.scheduler.attach_task(&task);
+        task
+    })
+}
+
+/// Put a task moved from another run queue into this run queue.
+/// 
+/// The task must have been detached from its previous run queue by 
+/// `Scheduler::detach_task()`. If this run queue is full, the task is 
+/// returned and stays detached.
+pub fn migrate_entry(percpu: &'static PerCPU, task: TaskRef) -> Result<(), TaskRef> {
+    percpu.scheduler.attach_task(&task);
+    percpu.scheduler.put_prev_task(task, false).map_err(|task| {
+        percpu.scheduler.detach_task(&task);
+        task
+    })?;
+    kick_if_idle(percpu);
+    Ok(())
+}
+
+/// Kick the CPU of the run queue if it is idle, after a task is put into the
+/// run queue.
+///
+/// The CPU is only marked as [`CPU_KICKED`] here, since the vDSO cannot wake
+/// it up. The caller should find the kicked CPUs and wake them up, e.g. with
+/// a futex on `idle_state`, see `idle_enter()`.
+fn kick_if_idle(percpu: &'static PerCPU) {
+    // Pairs with the fence in `idle_enter()`: either the idle CPU sees the
+    // task, or this CPU sees the idle state.
+    fence(Ordering::SeqCst);
+    let _ = percpu.idle_state.compare_exchange(CPU_IDLE, CPU_KICKED, Ordering::SeqCst, Ordering::Relaxed);
+}
+
+/// Mark the CPU as idle before it sleeps, when its idle task finds nothing to
+/// run.
+///
+/// Returns `false` if the run queue is not empty, where the CPU is marked as
+/// running again and should not sleep. Otherwise, the CPU may sleep until
+/// `idle_state` is not [`CPU_IDLE`], and should mark itself as
+/// [`CPU_RUNNING`] after waking up.
+pub fn idle_enter(percpu: &'static PerCPU) -> bool {
+    percpu.idle_state.store(CPU_IDLE, Ordering::SeqCst);
+    fence(Ordering::SeqCst);
+    if percpu.scheduler.is_empty() {
+        true
+    } else {
+        percpu.idle_state.store(CPU_RUNNING, Ordering::Release);
+        false
+    }
+}
+
+/// Pick the next task to run on this CPU.
+/// 
+/// If this run queue is empty, try to steal a task from other CPUs,
+/// and fall back to the idle task if there is nothing to steal.
+fn pick_next_task(percpu: &'static PerCPU) -> TaskRef {
+    percpu.scheduler.pick_next_task()
+        .or_else(|| steal_task(percpu))
+        .unwrap_or_else(|| 
+            // Safety: IRQs must be disabled at this time.
+            percpu.idle_task.clone()
+        )
 }
 
 /// Core reschedule subroutine.
 /// Pick the next task to run and switch to it.
 pub(crate) fn resched(percpu: &'static PerCPU) {
-    let next = percpu.scheduler.pick_next_task().unwrap_or_else(|| 
-        // Safety: IRQs must be disabled at this time.
-        percpu.idle_task.clone()
-    );
+    let next = pick_next_task(percpu);
     assert!(
         next.is_ready()
     );
@@ -189,6 +774,9 @@ pub(crate) fn switch_to(percpu: &'static PerCPU, prev_task: &TaskRef, next_task:
     if prev_task.ptr_eq(&next_task) {
         return;
     }
+    // The previous task is voluntarily switched out if it is not runnable.
+    percpu.scheduler.account_switch(prev_task, &next_task, !prev_task.is_ready());
+    percpu.scheduler.set_current(&next_task, next_task.is_idle());
 
     // Claim the task as running, we do this before switching to it
     // such that any running task will have this set.
@@ -204,6 +792,7 @@ pub(crate) fn switch_to(percpu: &'static PerCPU, prev_task: &TaskRef, next_task:
         // Store the weak pointer of **prev_task** in percpu variable `PREV_TASK`.
         percpu.prev_task.replace(MaybeUninit::new(prev_task.clone()));
 
+        percpu.current_addr.store(next_task.addr(), Ordering::Release);
         percpu.current_task.replace(next_task);
 
 
@@ -235,10 +824,7 @@ pub(crate) fn take_prev_task_and_clear_on_cpu(percpu: &'static PerCPU) -> TaskRe
 /// 
 /// The return value indicates whether resched is needed. 
 pub(crate) fn resched_f(percpu: &'static PerCPU) -> bool {
-    let next_task = percpu.scheduler.pick_next_task().unwrap_or_else(|| 
-        // Safety: IRQs must be disabled at this time.
-        percpu.idle_task.clone()
-    );
+    let next_task = pick_next_task(percpu);
     assert!(
         next_task.is_ready(),
     );
@@ -249,6 +835,8 @@ pub(crate) fn resched_f(percpu: &'static PerCPU) -> bool {
     if prev_task.ptr_eq(&next_task) {
         return false;
     }
+    percpu.scheduler.account_switch(prev_task, &next_task, !prev_task.is_ready());
+    percpu.scheduler.set_current(&next_task, next_task.is_idle());
 
     // Claim the task as running, we do this before switching to it
     // such that any running task will have this set.
@@ -264,6 +852,7 @@ pub(crate) fn resched_f(percpu: &'static PerCPU) -> bool {
         // assert!(Arc::strong_count(&next_task) >= 1);
 
         // Directly change the `CurrentTask` and return `Pending`.
+        percpu.current_addr.store(next_task.addr(), Ordering::Release);
         percpu.current_task.replace(next_task);
         true
     }
@@ -16,15 +16,21 @@ fn main() {
 fn build_vsched_api(out_path: PathBuf) {
     let vsched_api_file_content = fs::read_to_string(VSCHED_API_PATH).unwrap();
     let re = regex::Regex::new(
-        r#"#\[unsafe\(no_mangle\)\]\npub extern \"C\" fn ([a-zA-Z0-9_]?.*)(\([a-zA-Z0-9_:]?.*\)[->]?.*) \{"#,
+        r#"#\[unsafe\(no_mangle\)\]\npub extern \"C\" fn ([a-zA-Z0-9_]+)(\([^)]*\)(?: -> [^{]*)?) \{"#,
     )
     .unwrap();
-    // 获取共享调度器的 api
+    // 获取共享调度器的 api，参数列表被 rustfmt 折成多行时合并为一行
     let mut fns = vec![];
     for (_, [name, args]) in re
         .captures_iter(&vsched_api_file_content)
         .map(|c| c.extract())
     {
+        let args = args
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .replace("( ", "(")
+            .replace(", )", ")");
         // println!("{}: {}", name, args);
         fns.push((name, args));
     }